@@ -5,44 +5,82 @@
 // except according to those terms.
 
 use std::rc::Rc;
+use std::sync::Arc;
 
-use commands::parser::{Command, CommandTree, ParseError, Parser};
+use commands::integrations::linefeed::CommandCompleter;
+use commands::parser::{Command, CommandTree, Node, ParseError, Parser, VerifyError};
+use commands::render;
 use commands::tokenizer::tokenize;
 use linefeed::{Interface, ReadResult};
 
-fn main() {
+fn build_tree() -> Rc<Node> {
     let mut tree = CommandTree::new();
     tree.command(Command::new("show"));
-    let root = tree.finalize();
+    tree.finalize()
+}
+
+fn main() {
+    let root = build_tree();
 
     let reader = Interface::new("example").unwrap();
+    reader.set_completer(Arc::new(CommandCompleter::new(build_tree)));
     reader.set_prompt(">> ").unwrap();
     while let Ok(ReadResult::Input(line)) = reader.read_line() {
-        reader.add_history(line.clone());
         if let Ok(tokens) = tokenize(&line) {
             let mut parser = Parser::new(Rc::clone(&root));
-            if let Err(err) = parser.parse(tokens) {
+            let parsed = parser.parse(tokens);
+            // Record abbreviated commands in their full form.
+            match parsed {
+                Ok(()) => reader.add_history(parser.canonical_line()),
+                Err(_) => reader.add_history(line.clone()),
+            };
+            if let Err(err) = parsed {
                 match err {
-                    ParseError::NoMatches(_, acceptable) => {
-                        println!("No match for '{}'", line);
-                        println!("\nPossible options:");
-                        for option in &acceptable {
-                            let n = option.node();
-                            println!("  {} - {}", n.help_symbol, n.help_text);
+                    ParseError::NoMatches(token, ref acceptable) => {
+                        let columns = err.location().display_columns(&line);
+                        println!(
+                            "   {}{}",
+                            " ".repeat(columns.start),
+                            "^".repeat(columns.len().max(1))
+                        );
+                        println!("No match for '{}'", token.text);
+                        let suggestions = err.suggestions();
+                        if !suggestions.is_empty() {
+                            println!("Did you mean: {}?", suggestions.join(", "));
                         }
+                        println!("\nPossible options:");
+                        print!("{}", render::nodes(acceptable));
+                    }
+                    ParseError::AlreadySpecified(_, ref node) => {
+                        println!("'{}' has already been given", node.node().name);
                     }
                     ParseError::AmbiguousMatch(_, matches) => {
                         println!("\nCan be interpreted as:");
-                        for option in &matches {
-                            let n = option.node();
-                            println!("  {} - {}", n.help_symbol, n.help_text);
-                        }
+                        print!("{}", render::nodes(&matches));
                     }
+                    other => println!("{}", other),
                 }
             } else if let Err(err) = parser.verify() {
-                println!("{}", err);
+                for problem in err.errors() {
+                    match *problem {
+                        VerifyError::MissingParameter(ref name) => {
+                            println!("Missing parameter: {}", name)
+                        }
+                        VerifyError::InvalidValue(ref name) => {
+                            println!("Invalid value for: {}", name)
+                        }
+                        ref other => println!("{}", other),
+                    }
+                }
             } else {
-                parser.execute();
+                match parser.execute() {
+                    Ok(output) => {
+                        for line in output.lines() {
+                            println!("{}", line);
+                        }
+                    }
+                    Err(err) => println!("{}", err),
+                }
             }
         }
         println!();
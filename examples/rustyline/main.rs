@@ -4,80 +4,77 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use commands::parser::{Command, CommandTree, Node, ParseError, Parser};
+use commands::integrations::rustyline::CommandHelper;
+use commands::parser::{Command, CommandTree, ParseError, Parser, VerifyError};
+use commands::render;
 use commands::tokenizer::tokenize;
-use rustyline::completion::Completer;
-use rustyline::{Editor, Result};
+use rustyline::history::DefaultHistory;
+use rustyline::Editor;
 use std::rc::Rc;
 
-struct CommandCompleter {
-    root: Rc<Node>,
-}
-
-impl CommandCompleter {
-    fn new(root: Rc<Node>) -> Self {
-        CommandCompleter { root }
-    }
-}
-
-impl Completer for CommandCompleter {
-    fn complete(&self, line: &str, _pos: usize) -> Result<(usize, Vec<String>)> {
-        // TODO: This is an initial implementation that needs a lot more work.
-        if let Ok(tokens) = tokenize(line) {
-            let p = Parser::new(Rc::clone(&self.root));
-            let cs = p.complete(Some(tokens[0]));
-            if !cs.is_empty() {
-                Ok((
-                    0,
-                    cs[0]
-                        .options
-                        .iter()
-                        .map(|co| co.option_string.clone())
-                        .collect(),
-                ))
-            } else {
-                Ok((0, Vec::new()))
-            }
-        } else {
-            Ok((0, Vec::new()))
-        }
-    }
-}
-
 fn main() {
     let mut tree = CommandTree::new();
     tree.command(Command::new("show"));
     let root = tree.finalize();
 
-    let c = CommandCompleter::new(Rc::clone(&root));
-    let mut rl = Editor::<CommandCompleter>::new();
-    rl.set_completer(Some(c));
+    let mut rl = Editor::<CommandHelper, DefaultHistory>::new().unwrap();
+    rl.set_helper(Some(CommandHelper::new(Rc::clone(&root))));
     while let Ok(line) = rl.readline(">> ") {
-        rl.add_history_entry(&line);
         if let Ok(tokens) = tokenize(&line) {
             let mut parser = Parser::new(Rc::clone(&root));
-            if let Err(err) = parser.parse(tokens) {
+            let parsed = parser.parse(tokens);
+            // Record abbreviated commands in their full form.
+            let _ = match parsed {
+                Ok(()) => rl.add_history_entry(parser.canonical_line()),
+                Err(_) => rl.add_history_entry(&line),
+            };
+            if let Err(err) = parsed {
                 match err {
-                    ParseError::NoMatches(_, acceptable) => {
-                        println!("No match for '{}'", line);
-                        println!("\nPossible options:");
-                        for option in &acceptable {
-                            let n = option.node();
-                            println!("  {} - {}", n.help_symbol, n.help_text);
+                    ParseError::NoMatches(token, ref acceptable) => {
+                        let columns = err.location().display_columns(&line);
+                        println!(
+                            "   {}{}",
+                            " ".repeat(columns.start),
+                            "^".repeat(columns.len().max(1))
+                        );
+                        println!("No match for '{}'", token.text);
+                        let suggestions = err.suggestions();
+                        if !suggestions.is_empty() {
+                            println!("Did you mean: {}?", suggestions.join(", "));
                         }
+                        println!("\nPossible options:");
+                        print!("{}", render::nodes(acceptable));
+                    }
+                    ParseError::AlreadySpecified(_, ref node) => {
+                        println!("'{}' has already been given", node.node().name);
                     }
                     ParseError::AmbiguousMatch(_, matches) => {
                         println!("\nCan be interpreted as:");
-                        for option in &matches {
-                            let n = option.node();
-                            println!("  {} - {}", n.help_symbol, n.help_text);
-                        }
+                        print!("{}", render::nodes(&matches));
                     }
+                    other => println!("{}", other),
                 }
             } else if let Err(err) = parser.verify() {
-                println!("{}", err);
+                for problem in err.errors() {
+                    match *problem {
+                        VerifyError::MissingParameter(ref name) => {
+                            println!("Missing parameter: {}", name)
+                        }
+                        VerifyError::InvalidValue(ref name) => {
+                            println!("Invalid value for: {}", name)
+                        }
+                        ref other => println!("{}", other),
+                    }
+                }
             } else {
-                parser.execute();
+                match parser.execute() {
+                    Ok(output) => {
+                        for line in output.lines() {
+                            println!("{}", line);
+                        }
+                    }
+                    Err(err) => println!("{}", err),
+                }
             }
         }
         println!();
@@ -7,6 +7,8 @@
 //! # Command Tables
 
 use crate::menu_definition::MenuDefinition;
+use std::collections::BTreeMap;
+use std::ops::{Bound, RangeBounds};
 use std::rc::Rc;
 
 /// A command
@@ -31,13 +33,48 @@ pub struct CommandTableEntry {
 ///
 /// Command tables can be interacted with via menus,
 /// toolbars, keystrokes, gestures, and more.
+///
+/// The commands are kept in order by name, so that they can be
+/// found by their exact name, by a prefix of it or by a range of
+/// names, as menus and command palettes do.
+///
+/// ```
+/// use commands::command_table::{Command, CommandTable, CommandTableEntry};
+/// use std::rc::Rc;
+///
+/// struct Noop;
+///
+/// impl Command for Noop {
+///     fn execute(&self) {}
+/// }
+///
+/// let entry = |name: &str| CommandTableEntry {
+///     name: name.to_string(),
+///     command: Rc::new(Noop),
+/// };
+/// let edit = CommandTable::new(
+///     "edit".to_string(),
+///     vec![],
+///     vec![entry("paste"), entry("copy"), entry("cut")],
+/// );
+/// let view = CommandTable::new("view".to_string(), vec![edit], vec![entry("zoom")]);
+///
+/// assert!(view.get("copy").is_none());
+/// assert_eq!(view.lookup("copy").unwrap().name, "copy");
+/// let names = |entries: Vec<&CommandTableEntry>| {
+///     entries.iter().map(|e| e.name.clone()).collect::<Vec<_>>()
+/// };
+/// let edit = &view.inherit[0];
+/// assert_eq!(names(edit.with_prefix("c").collect()), ["copy", "cut"]);
+/// assert_eq!(names(edit.range("cu".."q").collect()), ["cut", "paste"]);
+/// ```
 pub struct CommandTable {
     /// The name of the command table
     pub name: String,
     /// Tables inherited by this table
     pub inherit: Vec<Rc<CommandTable>>,
-    /// Commands in this table
-    pub commands: Vec<CommandTableEntry>,
+    /// Commands in this table, by name
+    pub commands: BTreeMap<String, CommandTableEntry>,
     /// Menu description
     pub menu_definition: Option<MenuDefinition>,
 }
@@ -52,8 +89,44 @@ impl CommandTable {
         Rc::new(CommandTable {
             name,
             inherit,
-            commands,
+            commands: commands.into_iter().map(|c| (c.name.clone(), c)).collect(),
             menu_definition: None,
         })
     }
+
+    /// The command in this table with the given `name`.
+    pub fn get(&self, name: &str) -> Option<&CommandTableEntry> {
+        self.commands.get(name)
+    }
+
+    /// The command with the given `name` in this table or else in
+    /// the tables that it inherits, searched in order.
+    pub fn lookup(&self, name: &str) -> Option<&CommandTableEntry> {
+        self.get(name)
+            .or_else(|| self.inherit.iter().find_map(|t| t.lookup(name)))
+    }
+
+    /// The commands in this table whose names start with `prefix`,
+    /// in order by name.
+    pub fn with_prefix<'a>(
+        &'a self,
+        prefix: &'a str,
+    ) -> impl Iterator<Item = &'a CommandTableEntry> + 'a {
+        self.commands
+            .range::<str, _>((Bound::Included(prefix), Bound::Unbounded))
+            .take_while(move |(name, _)| name.starts_with(prefix))
+            .map(|(_, entry)| entry)
+    }
+
+    /// The commands in this table whose names are within `range`,
+    /// like `"copy".."paste"`, in order by name.
+    pub fn range<'a, R: RangeBounds<&'a str>>(
+        &self,
+        range: R,
+    ) -> impl Iterator<Item = &CommandTableEntry> {
+        let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+        self.commands
+            .range::<str, _>(bounds)
+            .map(|(_, entry)| entry)
+    }
 }
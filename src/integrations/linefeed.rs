@@ -0,0 +1,111 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Linefeed
+//!
+//! A [`CommandCompleter`] lets a `linefeed` interface complete the
+//! commands of a command tree:
+//!
+//! ```no_run
+//! use commands::integrations::linefeed::CommandCompleter;
+//! use commands::parser::{Command, CommandTree};
+//! use linefeed::Interface;
+//! use std::sync::Arc;
+//!
+//! let reader = Interface::new("example").unwrap();
+//! reader.set_completer(Arc::new(CommandCompleter::new(|| {
+//!     let mut tree = CommandTree::new();
+//!     tree.command(Command::new("show interface"));
+//!     tree.finalize()
+//! })));
+//! ```
+
+use super::{complete_options, SharedTree};
+use crate::parser::{Node, Parser};
+use linefeed::complete::{Completer, Completion};
+use linefeed::{Prompter, Terminal};
+use std::rc::Rc;
+
+/// A `linefeed` [`Completer`] which completes commands.
+///
+/// Completion uses [`Parser::complete_line`], so it considers all
+/// of the tokens before the cursor and replaces just the token
+/// being completed. Only options which complete the token are
+/// offered, rather than placeholders like `<name>`.
+///
+/// The tree is built by a function, as described for the
+/// [`integrations`].
+///
+/// [`integrations`]: crate::integrations
+/// [`Parser::complete_line`]: crate::parser::Parser::complete_line
+pub struct CommandCompleter {
+    tree: SharedTree,
+}
+
+impl CommandCompleter {
+    /// Construct a completer for the command tree built by `build`.
+    pub fn new<F>(build: F) -> Self
+    where
+        F: Fn() -> Rc<Node> + Send + Sync + 'static,
+    {
+        CommandCompleter {
+            tree: SharedTree::new(build),
+        }
+    }
+
+    fn completions(&self, line: &str, end: usize) -> Vec<Completion> {
+        let completion = Parser::new(self.tree.root()).complete_line(line, end);
+        complete_options(&completion)
+            .into_iter()
+            .map(|(option, _)| Completion::simple(option.to_string()))
+            .collect()
+    }
+}
+
+impl<Term: Terminal> Completer<Term> for CommandCompleter {
+    fn complete(
+        &self,
+        _word: &str,
+        prompter: &Prompter<Term>,
+        _start: usize,
+        end: usize,
+    ) -> Option<Vec<Completion>> {
+        Some(self.completions(prompter.buffer(), end))
+    }
+
+    fn word_start(&self, line: &str, end: usize, _prompter: &Prompter<Term>) -> usize {
+        Parser::new(self.tree.root())
+            .complete_line(line, end)
+            .replace
+            .start
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree};
+
+    #[test]
+    fn complete_commands() {
+        let completer = CommandCompleter::new(|| {
+            let mut tree = CommandTree::new();
+            tree.command(Command::new("show interface"));
+            tree.command(Command::new("show ip"));
+            tree.finalize()
+        });
+        let options = |line: &str| {
+            completer
+                .completions(line, line.len())
+                .into_iter()
+                .map(|c| c.completion)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(options("sh"), ["show"]);
+        assert_eq!(options("show i"), ["interface", "ip"]);
+        assert_eq!(options("show x"), Vec::<String>::new());
+    }
+}
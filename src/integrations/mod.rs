@@ -0,0 +1,165 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Integrations
+//!
+//! Ready-made glue between a command tree and line editing
+//! libraries. Each integration is enabled by the feature of the
+//! same name:
+//!
+//! * `linefeed`: a [`linefeed`] completer.
+//! * `reedline`: a [`reedline`] completer and validator.
+//! * `rustyline`: a [`rustyline`] helper, providing completion,
+//!   hints and validation.
+//!
+//! The validators ask for another line when the input ends within
+//! a quote or with a `\` continuing the line, so that the editor
+//! shows its continuation prompt. This also happens within a here
+//! document when [`TreeConfig::tokenizer`] enables them.
+//!
+//! The completers for `linefeed` and `reedline` must be `Send`,
+//! which a command tree is not. They are given a function which
+//! builds the tree instead, which is called the first time that
+//! the tree is needed on each thread.
+//!
+//! [`linefeed`]: https://docs.rs/linefeed
+//! [`reedline`]: https://docs.rs/reedline
+//! [`rustyline`]: https://docs.rs/rustyline
+//! [`TreeConfig::tokenizer`]: crate::parser::TreeConfig::tokenizer
+
+#[cfg(feature = "linefeed")]
+pub mod linefeed;
+#[cfg(feature = "reedline")]
+pub mod reedline;
+#[cfg(feature = "rustyline")]
+pub mod rustyline;
+
+#[cfg(any(feature = "linefeed", feature = "reedline"))]
+pub(crate) use self::shared::SharedTree;
+
+#[cfg(any(feature = "linefeed", feature = "reedline", feature = "rustyline"))]
+use crate::parser::LineCompletion;
+
+/// The options which complete the token, each with the help text
+/// of the completion which offered it. Options offered more than
+/// once are only given the first time.
+#[cfg(any(feature = "linefeed", feature = "reedline", feature = "rustyline"))]
+pub(crate) fn complete_options<'c>(completion: &'c LineCompletion) -> Vec<(&'c str, &'c str)> {
+    let mut options: Vec<(&str, &str)> = vec![];
+    for c in &completion.completions {
+        for o in c.options.iter().filter(|o| o.complete) {
+            if !options.iter().any(|(option, _)| *option == o.option_string) {
+                options.push((&o.option_string, &c.help_text));
+            }
+        }
+    }
+    options
+}
+
+/// Whether the `line` ends within a construct which continues
+/// onto the next line, so more input is needed to complete it.
+/// The line is tokenized with the tokenizer config of `parser`.
+#[cfg(any(feature = "reedline", feature = "rustyline"))]
+pub(crate) fn needs_more_input(parser: &crate::parser::Parser, line: &str) -> bool {
+    use crate::tokenizer::{tokenize_partial_with, Unfinished};
+
+    match tokenize_partial_with(&parser.config.tokenizer, line).1 {
+        None | Some(Unfinished::Invalid(_)) => false,
+        Some(_) => true,
+    }
+}
+
+#[cfg(any(feature = "linefeed", feature = "reedline"))]
+mod shared {
+    use crate::parser::Node;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::rc::Rc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    thread_local! {
+        static TREES: RefCell<HashMap<usize, Rc<Node>>> = RefCell::new(HashMap::new());
+    }
+
+    /// A command tree which can be held by a value that is `Send`
+    /// and `Sync`. The tree is built by `build` the first time it
+    /// is needed on a thread and kept for that thread until the
+    /// `SharedTree` is dropped.
+    pub(crate) struct SharedTree {
+        id: usize,
+        build: Arc<dyn Fn() -> Rc<Node> + Send + Sync>,
+    }
+
+    impl SharedTree {
+        pub(crate) fn new<F>(build: F) -> Self
+        where
+            F: Fn() -> Rc<Node> + Send + Sync + 'static,
+        {
+            SharedTree {
+                id: NEXT_ID.fetch_add(1, Ordering::Relaxed),
+                build: Arc::new(build),
+            }
+        }
+
+        /// The root of the tree for the current thread.
+        pub(crate) fn root(&self) -> Rc<Node> {
+            if let Some(root) = TREES.with(|trees| trees.borrow().get(&self.id).cloned()) {
+                return root;
+            }
+            // Build outside of the borrow, in case building uses
+            // another `SharedTree`.
+            let root = (self.build)();
+            TREES.with(|trees| trees.borrow_mut().insert(self.id, Rc::clone(&root)));
+            root
+        }
+    }
+
+    impl Drop for SharedTree {
+        fn drop(&mut self) {
+            // The trees built on other threads are kept until those
+            // threads exit.
+            let _ = TREES.try_with(|trees| trees.borrow_mut().remove(&self.id));
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+        use super::*;
+        use crate::parser::{Command, CommandTree};
+        use std::cell::Cell;
+
+        #[test]
+        fn shared_tree_builds_once_per_thread() {
+            thread_local! {
+                static BUILT: Cell<usize> = const { Cell::new(0) };
+            }
+            let tree = SharedTree::new(|| {
+                BUILT.with(|b| b.set(b.get() + 1));
+                let mut tree = CommandTree::new();
+                tree.command(Command::new("show"));
+                tree.finalize()
+            });
+            let first = tree.root();
+            assert!(Rc::ptr_eq(&first, &tree.root()));
+            assert_eq!(BUILT.with(Cell::get), 1);
+            let tree = Arc::new(tree);
+            let other = Arc::clone(&tree);
+            let built = std::thread::spawn(move || {
+                assert!(other.root().lookup("show").is_some());
+                BUILT.with(Cell::get)
+            })
+            .join()
+            .unwrap();
+            assert_eq!(built, 1);
+            drop(first);
+            drop(tree);
+            assert_eq!(TREES.with(|trees| trees.borrow().len()), 0);
+        }
+    }
+}
@@ -0,0 +1,167 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Reedline
+//!
+//! A [`CommandCompleter`] lets a `reedline` editor complete the
+//! commands of a command tree, and a [`CommandValidator`] lets it
+//! continue them across lines:
+//!
+//! ```no_run
+//! use commands::integrations::reedline::{CommandCompleter, CommandValidator};
+//! use commands::parser::{Command, CommandTree, Node};
+//! use reedline::Reedline;
+//! use std::rc::Rc;
+//!
+//! fn build() -> Rc<Node> {
+//!     let mut tree = CommandTree::new();
+//!     tree.command(Command::new("show interface"));
+//!     tree.finalize()
+//! }
+//!
+//! let editor = Reedline::create()
+//!     .with_completer(Box::new(CommandCompleter::new(build)))
+//!     .with_validator(Box::new(CommandValidator::new(build)));
+//! ```
+
+use super::{complete_options, needs_more_input, SharedTree};
+use crate::parser::{Node, Parser};
+use reedline::{Completer, Span, Suggestion, ValidationResult, Validator};
+use std::rc::Rc;
+
+/// A `reedline` [`Completer`] which completes commands.
+///
+/// Completion uses [`Parser::complete_line`], so it considers all
+/// of the tokens before the cursor and replaces just the token
+/// being completed. Only options which complete the token are
+/// suggested, rather than placeholders like `<name>`. Each is
+/// described by the help text of its node.
+///
+/// The tree is built by a function, as described for the
+/// [`integrations`].
+///
+/// [`integrations`]: crate::integrations
+/// [`Parser::complete_line`]: crate::parser::Parser::complete_line
+pub struct CommandCompleter {
+    tree: SharedTree,
+}
+
+impl CommandCompleter {
+    /// Construct a completer for the command tree built by `build`.
+    pub fn new<F>(build: F) -> Self
+    where
+        F: Fn() -> Rc<Node> + Send + Sync + 'static,
+    {
+        CommandCompleter {
+            tree: SharedTree::new(build),
+        }
+    }
+}
+
+impl Completer for CommandCompleter {
+    fn complete(&mut self, line: &str, pos: usize) -> Vec<Suggestion> {
+        let completion = Parser::new(self.tree.root()).complete_line(line, pos);
+        let span = Span::new(completion.replace.start, completion.replace.end);
+        complete_options(&completion)
+            .into_iter()
+            .map(|(option, help_text)| Suggestion {
+                value: option.to_string(),
+                description: Some(help_text.to_string()),
+                span,
+                append_whitespace: true,
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+/// A `reedline` [`Validator`] which treats input ending within a
+/// quote, or with a `\` continuing the line, as incomplete, so that
+/// Enter starts a new line. Input ending within a here document is
+/// also incomplete when the [`TreeConfig::tokenizer`] of the tree
+/// enables them.
+///
+/// The tree is built by a function, as described for the
+/// [`integrations`].
+///
+/// [`integrations`]: crate::integrations
+/// [`TreeConfig::tokenizer`]: crate::parser::TreeConfig::tokenizer
+pub struct CommandValidator {
+    tree: SharedTree,
+}
+
+impl CommandValidator {
+    /// Construct a validator for the command tree built by `build`.
+    pub fn new<F>(build: F) -> Self
+    where
+        F: Fn() -> Rc<Node> + Send + Sync + 'static,
+    {
+        CommandValidator {
+            tree: SharedTree::new(build),
+        }
+    }
+}
+
+impl Validator for CommandValidator {
+    fn validate(&self, line: &str) -> ValidationResult {
+        if needs_more_input(&Parser::new(self.tree.root()), line) {
+            ValidationResult::Incomplete
+        } else {
+            ValidationResult::Complete
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, TreeConfig};
+    use crate::tokenizer::TokenizerConfig;
+
+    #[test]
+    fn complete_commands() {
+        let mut completer = CommandCompleter::new(|| {
+            let mut tree = CommandTree::new();
+            tree.command(Command::new("show interface").help("Show interfaces"));
+            tree.command(Command::new("show ip").help("Show IP"));
+            tree.finalize()
+        });
+        let suggestions = completer.complete("show i", 6);
+        let values = suggestions
+            .iter()
+            .map(|s| (s.value.as_str(), s.description.as_deref(), s.span))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            values,
+            [
+                ("interface", Some("Show interfaces"), Span::new(5, 6)),
+                ("ip", Some("Show IP"), Span::new(5, 6)),
+            ]
+        );
+    }
+
+    #[test]
+    fn validate_continuations() {
+        let validator = CommandValidator::new(|| CommandTree::new().finalize());
+        let incomplete = |line| matches!(validator.validate(line), ValidationResult::Incomplete);
+        assert!(incomplete("show \\"));
+        assert!(incomplete("show \\\n"));
+        assert!(incomplete("show \"a"));
+        assert!(!incomplete("show \\\ninterface"));
+        assert!(!incomplete("show\n"));
+        assert!(!incomplete("show \\!"));
+        assert!(!incomplete("load <<EOF\nabc"));
+
+        let validator = CommandValidator::new(|| {
+            let mut tree = CommandTree::new();
+            tree.config(TreeConfig::new().tokenizer(TokenizerConfig::new().here_documents(true)));
+            tree.finalize()
+        });
+        let incomplete = |line| matches!(validator.validate(line), ValidationResult::Incomplete);
+        assert!(incomplete("load <<EOF\nabc"));
+        assert!(!incomplete("load <<EOF\nabc\nEOF"));
+    }
+}
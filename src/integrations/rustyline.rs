@@ -0,0 +1,172 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Rustyline
+//!
+//! A [`CommandHelper`] lets a `rustyline` editor complete and hint
+//! the commands of a command tree, and continue them across lines:
+//!
+//! ```no_run
+//! use commands::integrations::rustyline::CommandHelper;
+//! use commands::parser::{Command, CommandTree};
+//! use rustyline::history::DefaultHistory;
+//! use rustyline::Editor;
+//!
+//! let mut tree = CommandTree::new();
+//! tree.command(Command::new("show interface"));
+//! let root = tree.finalize();
+//!
+//! let mut editor = Editor::<CommandHelper, DefaultHistory>::new().unwrap();
+//! editor.set_helper(Some(CommandHelper::new(root)));
+//! while let Ok(line) = editor.readline(">> ") {
+//!     println!("{line}");
+//! }
+//! ```
+
+use super::{complete_options, needs_more_input};
+use crate::parser::{LineCompletion, Node, ParserBuilder};
+use rustyline::completion::{Completer, Pair};
+use rustyline::highlight::Highlighter;
+use rustyline::hint::Hinter;
+use rustyline::validate::{ValidationContext, ValidationResult, Validator};
+use rustyline::{Context, Helper, Result};
+use std::rc::Rc;
+
+/// A `rustyline` [`Helper`] which completes and hints commands.
+///
+/// Completion uses [`Parser::complete_line`], so it considers all
+/// of the tokens before the cursor and replaces just the token
+/// being completed. Only options which complete the token are
+/// offered, rather than placeholders like `<name>`.
+///
+/// Hints are given by [`Parser::hint`].
+///
+/// When Enter is pressed in input which ends within a quote, or
+/// with a `\` continuing the line, a new line is started rather
+/// than the input being accepted. The same happens within a here
+/// document when the [`TreeConfig::tokenizer`] of the parsers
+/// enables them.
+///
+/// [`Parser::complete_line`]: crate::parser::Parser::complete_line
+/// [`Parser::hint`]: crate::parser::Parser::hint
+/// [`TreeConfig::tokenizer`]: crate::parser::TreeConfig::tokenizer
+pub struct CommandHelper {
+    builder: ParserBuilder,
+}
+
+impl CommandHelper {
+    /// Construct a helper for the command tree starting at `root`.
+    pub fn new(root: Rc<Node>) -> Self {
+        CommandHelper::with_builder(ParserBuilder::new(root))
+    }
+
+    /// Construct a helper whose parsers are built by `builder`, to
+    /// use a configuration or handlers other than those of the root.
+    pub fn with_builder(builder: ParserBuilder) -> Self {
+        CommandHelper { builder }
+    }
+
+    fn complete_line<'text>(&self, line: &'text str, pos: usize) -> LineCompletion<'text> {
+        self.builder.build().complete_line(line, pos)
+    }
+}
+
+impl Completer for CommandHelper {
+    type Candidate = Pair;
+
+    fn complete(&self, line: &str, pos: usize, _ctx: &Context) -> Result<(usize, Vec<Pair>)> {
+        let completion = self.complete_line(line, pos);
+        let candidates = complete_options(&completion)
+            .into_iter()
+            .map(|(option, _)| Pair {
+                display: option.to_string(),
+                replacement: option.to_string(),
+            })
+            .collect();
+        Ok((completion.replace.start, candidates))
+    }
+}
+
+impl Hinter for CommandHelper {
+    type Hint = String;
+
+    fn hint(&self, line: &str, pos: usize, _ctx: &Context) -> Option<String> {
+        self.builder.build().hint(line, pos)
+    }
+}
+
+impl Highlighter for CommandHelper {}
+
+impl Validator for CommandHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult> {
+        if needs_more_input(&self.builder.build(), ctx.input()) {
+            Ok(ValidationResult::Incomplete)
+        } else {
+            Ok(ValidationResult::Valid(None))
+        }
+    }
+}
+
+impl Helper for CommandHelper {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, TreeConfig};
+    use crate::tokenizer::TokenizerConfig;
+    use rustyline::history::MemHistory;
+
+    fn helper() -> CommandHelper {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface"));
+        tree.command(Command::new("show ip"));
+        tree.command(Command::new("set"));
+        CommandHelper::new(tree.finalize())
+    }
+
+    #[test]
+    fn complete_and_hint() {
+        let helper = helper();
+        let history = MemHistory::new();
+        let ctx = Context::new(&history);
+        let complete = |line: &str| {
+            let (start, candidates) = helper.complete(line, line.len(), &ctx).unwrap();
+            let replacements = candidates
+                .into_iter()
+                .map(|c| c.replacement)
+                .collect::<Vec<_>>();
+            (start, replacements)
+        };
+        assert_eq!(
+            complete("s"),
+            (0, vec!["set".to_string(), "show".to_string()])
+        );
+        assert_eq!(
+            complete("show i"),
+            (5, vec!["interface".to_string(), "ip".to_string()])
+        );
+        assert_eq!(helper.hint("sh", 2, &ctx), Some("ow".to_string()));
+        assert_eq!(helper.hint("show in", 7, &ctx), Some("terface".to_string()));
+        assert_eq!(helper.hint("show i", 6, &ctx), None);
+        assert_eq!(helper.hint("show in", 3, &ctx), None);
+    }
+
+    #[test]
+    fn validate_here_documents() {
+        // A `ValidationContext` can't be constructed outside of
+        // `rustyline`, so check the input as `validate` would.
+        let incomplete =
+            |helper: &CommandHelper, line| needs_more_input(&helper.builder.build(), line);
+        assert!(!incomplete(&helper(), "load <<EOF\nabc"));
+
+        let config = TreeConfig::new().tokenizer(TokenizerConfig::new().here_documents(true));
+        let builder = ParserBuilder::new(CommandTree::new().finalize()).config(config);
+        let helper = CommandHelper::with_builder(builder);
+        assert!(incomplete(&helper, "load <<EOF\nabc"));
+        assert!(!incomplete(&helper, "load <<EOF\nabc\nEOF"));
+        assert!(incomplete(&helper, "show \\"));
+    }
+}
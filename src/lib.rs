@@ -18,11 +18,26 @@
 //! * Commands can be defined and grouped into command tables.
 //! * Commands can be hooked up with a [`Parser`] for implementing
 //!   command line interfaces with completion and parameter validation.
+//! * Completions and help can be [`render`]ed as aligned columns.
+//! * State lasting across command lines, like history, is kept
+//!   for a [`session`] and used by optional built-in commands.
+//! * Line editors can be hooked up to a command tree by way of
+//!   the [`integrations`], each enabled by a feature.
+//!
+//! The library reports problems with its input, including command
+//! trees built wrongly by plugins, as errors rather than panicking,
+//! like `InvocationError::Internal`, so that they can't take down
+//! the thread of an application's user interface. Panics within
+//! the handlers, completers and other callbacks given by the
+//! application are its own.
 //!
 //! This library is in the early stages of development and
 //! not everything works yet.
 //!
 //! [`Parser`]: crate::parser::Parser
+//! [`integrations`]: crate::integrations
+//! [`render`]: crate::render
+//! [`session`]: crate::session
 
 #![warn(missing_docs)]
 #![deny(
@@ -34,7 +49,10 @@
 )]
 
 pub mod command_table;
+pub mod integrations;
 pub mod menu_definition;
 pub mod parser;
+pub mod render;
+pub mod session;
 pub mod tokenizer;
 pub mod util;
@@ -5,16 +5,355 @@
 // except according to those terms.
 
 //! # Menu Definitions
+//!
+//! With the `serde` feature, menu definitions can be serialized
+//! and deserialized, so that a menu layout can be kept in a data
+//! file and loaded at runtime:
+//!
+//! ```json
+//! {
+//!   "items": [
+//!     { "command": { "name": "copy", "label": "Copy" } },
+//!     "separator",
+//!     { "submenu": { "label": "More", "menu": { "items": [] } } }
+//!   ]
+//! }
+//! ```
+//!
+//! A loaded definition should be checked with
+//! [`MenuDefinition::validate`] against the command table whose
+//! commands it refers to.
+
+use crate::command_table::CommandTable;
+use std::error::Error;
+use std::fmt;
 
 /// Types of menu items.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum MenuItem {
     /// A separator item in a menu.
     Separator,
+    /// An item which executes a command from a command table.
+    Command {
+        /// The name of the command within the table.
+        name: String,
+        /// The text shown for the item. Without a label, the name
+        /// of the command is shown.
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        label: Option<String>,
+        /// The letter of the label which selects the item from the
+        /// keyboard while the menu is open. See
+        /// [`MenuDefinition::assign_mnemonics`].
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        mnemonic: Option<char>,
+    },
+    /// An item which opens another menu.
+    Submenu {
+        /// The text shown for the item.
+        label: String,
+        /// The menu which is opened.
+        menu: MenuDefinition,
+        /// The letter of the label which opens the menu from the
+        /// keyboard.
+        #[cfg_attr(
+            feature = "serde",
+            serde(default, skip_serializing_if = "Option::is_none")
+        )]
+        mnemonic: Option<char>,
+    },
+}
+
+impl MenuItem {
+    /// The text shown for the item. A separator has none.
+    pub fn label(&self) -> Option<&str> {
+        match *self {
+            MenuItem::Separator => None,
+            MenuItem::Command {
+                ref name,
+                ref label,
+                ..
+            } => Some(label.as_deref().unwrap_or(name)),
+            MenuItem::Submenu { ref label, .. } => Some(label),
+        }
+    }
+
+    /// The letter which selects the item, if it has one.
+    pub fn mnemonic(&self) -> Option<char> {
+        match *self {
+            MenuItem::Separator => None,
+            MenuItem::Command { mnemonic, .. } | MenuItem::Submenu { mnemonic, .. } => mnemonic,
+        }
+    }
+
+    /// The byte offset of the `mnemonic` within the `label`, which
+    /// is where a GUI underlines it. This is the first occurrence
+    /// of the letter, ignoring case.
+    pub fn mnemonic_offset(&self) -> Option<usize> {
+        let mnemonic = self.mnemonic()?;
+        self.label()?
+            .char_indices()
+            .find(|(_, c)| same_letter(*c, mnemonic))
+            .map(|(i, _)| i)
+    }
+
+    fn set_mnemonic(&mut self, letter: Option<char>) {
+        match *self {
+            MenuItem::Separator => {}
+            MenuItem::Command {
+                ref mut mnemonic, ..
+            }
+            | MenuItem::Submenu {
+                ref mut mnemonic, ..
+            } => *mnemonic = letter,
+        }
+    }
 }
 
 /// The definition to be used to generate a menu or
 /// toolbar in whatever GUI framework is being used.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MenuDefinition {
     /// The items in the menu. Ordered vector.
     pub items: Vec<MenuItem>,
 }
+
+impl MenuDefinition {
+    /// Generate a menu with an item for each command of the `table`,
+    /// in order by name, with mnemonics assigned.
+    pub fn for_table(table: &CommandTable) -> Self {
+        let mut menu = MenuDefinition {
+            items: table
+                .commands
+                .keys()
+                .map(|name| MenuItem::Command {
+                    name: name.clone(),
+                    label: None,
+                    mnemonic: None,
+                })
+                .collect(),
+        };
+        menu.assign_mnemonics();
+        menu
+    }
+
+    /// Give each item of the menu and of its submenus a mnemonic,
+    /// a letter of its label which is unique within its menu.
+    ///
+    /// Mnemonics which were already given are kept. For the other
+    /// items, in order, the first letter of a word of the label is
+    /// preferred, then any other letter or digit. Letters differing
+    /// only in case are the same mnemonic. An item is left without
+    /// one when every letter of its label is taken.
+    ///
+    /// ```
+    /// use commands::menu_definition::{MenuDefinition, MenuItem};
+    ///
+    /// let item = |label: &str| MenuItem::Command {
+    ///     name: label.to_lowercase(),
+    ///     label: Some(label.to_string()),
+    ///     mnemonic: None,
+    /// };
+    /// let mut menu = MenuDefinition {
+    ///     items: vec![item("Save"), item("Save As"), item("Settings")],
+    /// };
+    /// menu.assign_mnemonics();
+    /// let mnemonics = menu.items.iter().map(|i| i.mnemonic()).collect::<Vec<_>>();
+    /// assert_eq!(mnemonics, [Some('S'), Some('A'), Some('e')]);
+    /// assert_eq!(menu.items[2].mnemonic_offset(), Some(1));
+    /// ```
+    pub fn assign_mnemonics(&mut self) {
+        let mut taken = self
+            .items
+            .iter()
+            .filter_map(MenuItem::mnemonic)
+            .collect::<Vec<_>>();
+        for item in &mut self.items {
+            if let MenuItem::Submenu { ref mut menu, .. } = *item {
+                menu.assign_mnemonics();
+            }
+            if item.mnemonic().is_some() {
+                continue;
+            }
+            let label = match item.label() {
+                Some(label) => label,
+                None => continue,
+            };
+            let free = |c: &char| c.is_alphanumeric() && !taken.iter().any(|t| same_letter(*t, *c));
+            let word_starts = label
+                .split_whitespace()
+                .filter_map(|word| word.chars().find(|c| c.is_alphanumeric()));
+            let letter = word_starts.chain(label.chars()).find(free);
+            taken.extend(letter);
+            item.set_mnemonic(letter);
+        }
+    }
+
+    /// Check that each command item, including those of submenus,
+    /// names a command in the `table` or in the tables that it
+    /// inherits.
+    pub fn validate(&self, table: &CommandTable) -> Result<(), MenuError> {
+        for item in &self.items {
+            match *item {
+                MenuItem::Separator => {}
+                MenuItem::Command { ref name, .. } => {
+                    if table.lookup(name).is_none() {
+                        return Err(MenuError::UnknownCommand(name.clone()));
+                    }
+                }
+                MenuItem::Submenu { ref menu, .. } => menu.validate(table)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether `a` and `b` are the same mnemonic, ignoring case.
+fn same_letter(a: char, b: char) -> bool {
+    a == b || a.to_lowercase().eq(b.to_lowercase())
+}
+
+/// Errors that [`MenuDefinition::validate`] can raise.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MenuError {
+    /// A menu item names a command which isn't in the table.
+    UnknownCommand(String),
+}
+
+impl Error for MenuError {}
+
+impl fmt::Display for MenuError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            MenuError::UnknownCommand(_) => "Unknown command.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::command_table::{Command, CommandTableEntry};
+    use std::rc::Rc;
+
+    struct Noop;
+
+    impl Command for Noop {
+        fn execute(&self) {}
+    }
+
+    fn item(name: &str) -> MenuItem {
+        MenuItem::Command {
+            name: name.to_string(),
+            label: None,
+            mnemonic: None,
+        }
+    }
+
+    #[test]
+    fn validate_menus() {
+        let entry = CommandTableEntry {
+            name: "copy".to_string(),
+            command: Rc::new(Noop),
+        };
+        let table = CommandTable::new("edit".to_string(), vec![], vec![entry]);
+        let mut menu = MenuDefinition {
+            items: vec![
+                item("copy"),
+                MenuItem::Separator,
+                MenuItem::Submenu {
+                    label: "More".to_string(),
+                    menu: MenuDefinition {
+                        items: vec![item("paste")],
+                    },
+                    mnemonic: None,
+                },
+            ],
+        };
+        assert_eq!(
+            menu.validate(&table),
+            Err(MenuError::UnknownCommand("paste".to_string()))
+        );
+        menu.items.pop();
+        assert_eq!(menu.validate(&table), Ok(()));
+    }
+
+    #[test]
+    fn mnemonics() {
+        let entries = ["cut", "copy", "paste", "cc"]
+            .iter()
+            .map(|name| CommandTableEntry {
+                name: name.to_string(),
+                command: Rc::new(Noop),
+            })
+            .collect();
+        let table = CommandTable::new("edit".to_string(), vec![], entries);
+        let mut menu = MenuDefinition::for_table(&table);
+        let mnemonics = |menu: &MenuDefinition| {
+            menu.items
+                .iter()
+                .map(MenuItem::mnemonic)
+                .collect::<Vec<_>>()
+        };
+        // In order by name: cc, copy, cut, paste.
+        assert_eq!(
+            mnemonics(&menu),
+            [Some('c'), Some('o'), Some('u'), Some('p')]
+        );
+
+        menu.items[0].set_mnemonic(None);
+        menu.items[3].set_mnemonic(Some('C'));
+        menu.items.push(MenuItem::Separator);
+        menu.items.push(MenuItem::Submenu {
+            label: "Paste Special".to_string(),
+            menu: MenuDefinition {
+                items: vec![item("paste")],
+            },
+            mnemonic: None,
+        });
+        menu.assign_mnemonics();
+        assert_eq!(
+            mnemonics(&menu),
+            [None, Some('o'), Some('u'), Some('C'), None, Some('P')]
+        );
+        assert_eq!(menu.items[3].mnemonic_offset(), None);
+        assert_eq!(menu.items[5].mnemonic_offset(), Some(0));
+        if let MenuItem::Submenu { ref menu, .. } = menu.items[5] {
+            assert_eq!(mnemonics(menu), [Some('p')]);
+        } else {
+            panic!();
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_menus() {
+        let json = serde_json::json!({
+            "items": [
+                { "command": { "name": "copy", "label": "Copy" } },
+                "separator",
+                { "submenu": { "label": "More", "menu": { "items": [{ "command": { "name": "cut" } }] } } },
+            ]
+        });
+        let menu: MenuDefinition = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            menu.items[0],
+            MenuItem::Command {
+                name: "copy".to_string(),
+                label: Some("Copy".to_string()),
+                mnemonic: None,
+            }
+        );
+        assert_eq!(menu.items[1], MenuItem::Separator);
+        assert_eq!(serde_json::to_value(&menu).unwrap(), json);
+    }
+}
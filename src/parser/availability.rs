@@ -0,0 +1,170 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::Parser;
+use std::cell::{Cell, RefCell};
+use std::collections::HashSet;
+use std::rc::Rc;
+
+/// The state which decides which commands are available, such as
+/// the current mode, the features which are enabled or the
+/// permissions of the user, kept as a set of named flags.
+///
+/// Commands are hidden while a flag isn't set by giving the
+/// predicate from [`hidden_unless`] to `Command::hidden_when`.
+/// Menus, toolbars and completion caches can [`subscribe`] to be
+/// told when a flag changes, rather than checking the predicates
+/// again and again.
+///
+/// ```
+/// use commands::parser::{Availability, Command, CommandTree, Parser};
+/// use std::cell::Cell;
+/// use std::rc::Rc;
+///
+/// let availability = Rc::new(Availability::new());
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("reload").hidden_when(availability.hidden_unless("admin")));
+/// tree.command(Command::new("show"));
+/// let root = tree.finalize();
+///
+/// let refreshes = Rc::new(Cell::new(0));
+/// let counter = Rc::clone(&refreshes);
+/// availability.subscribe(move |_| counter.set(counter.get() + 1));
+///
+/// assert_eq!(Parser::new(Rc::clone(&root)).complete(None).len(), 1);
+/// availability.set("admin", true);
+/// availability.set("admin", true);
+/// assert_eq!(refreshes.get(), 1);
+/// assert_eq!(Parser::new(root).complete(None).len(), 2);
+/// ```
+///
+/// [`hidden_unless`]: Availability::hidden_unless
+/// [`subscribe`]: Availability::subscribe
+#[derive(Default)]
+pub struct Availability {
+    flags: RefCell<HashSet<String>>,
+    subscribers: RefCell<Vec<(SubscriptionId, AvailabilityListener)>>,
+    next_id: Cell<usize>,
+}
+
+/// Called by an [`Availability`] with each change to its flags.
+pub type AvailabilityListener = Rc<dyn Fn(&AvailabilityChange)>;
+
+/// Identifies a subscription to an [`Availability`], so that it
+/// can be ended with [`Availability::unsubscribe`].
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct SubscriptionId(usize);
+
+/// A change to a flag of an [`Availability`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AvailabilityChange {
+    /// The name of the flag.
+    pub flag: String,
+    /// Whether the flag is now set.
+    pub set: bool,
+}
+
+impl Availability {
+    /// Construct an `Availability` without any flags set.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Whether or not the `flag` is set.
+    pub fn is_set(&self, flag: &str) -> bool {
+        self.flags.borrow().contains(flag)
+    }
+
+    /// Set or clear the `flag`. When this changes it, each of the
+    /// subscribers is told, in the order that they subscribed.
+    ///
+    /// A mode is best kept as a flag for each mode, of which one
+    /// is set at a time, so switching modes is two changes.
+    pub fn set(&self, flag: &str, set: bool) {
+        let changed = if set {
+            self.flags.borrow_mut().insert(flag.to_string())
+        } else {
+            self.flags.borrow_mut().remove(flag)
+        };
+        if changed {
+            let change = AvailabilityChange {
+                flag: flag.to_string(),
+                set,
+            };
+            // Listeners may subscribe, unsubscribe or change flags.
+            let listeners = self
+                .subscribers
+                .borrow()
+                .iter()
+                .map(|(_, listener)| Rc::clone(listener))
+                .collect::<Vec<_>>();
+            for listener in listeners {
+                listener(&change);
+            }
+        }
+    }
+
+    /// Call `listener` with each change to the flags from now on.
+    pub fn subscribe<F: Fn(&AvailabilityChange) + 'static>(&self, listener: F) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.get());
+        self.next_id.set(id.0 + 1);
+        self.subscribers.borrow_mut().push((id, Rc::new(listener)));
+        id
+    }
+
+    /// Stop telling the subscriber with the given `id` of changes.
+    pub fn unsubscribe(&self, id: SubscriptionId) {
+        self.subscribers.borrow_mut().retain(|(i, _)| *i != id);
+    }
+
+    /// A predicate for `Command::hidden_when` or
+    /// `Parameter::hidden_when` which hides the node unless the
+    /// `flag` is set.
+    pub fn hidden_unless(self: &Rc<Self>, flag: &str) -> impl Fn(&Parser) -> bool + 'static {
+        let availability = Rc::clone(self);
+        let flag = flag.to_string();
+        move |_| !availability.is_set(&flag)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn subscriptions() {
+        let availability = Rc::new(Availability::new());
+        let seen = Rc::new(RefCell::new(vec![]));
+        let log = Rc::clone(&seen);
+        let first = availability.subscribe(move |change| {
+            log.borrow_mut()
+                .push(format!("{} {}", change.flag, change.set))
+        });
+        // A listener which changes another flag in response.
+        let inner = Rc::clone(&availability);
+        availability.subscribe(move |change| {
+            if change.flag == "mode:config" && change.set {
+                inner.set("mode:exec", false);
+            }
+        });
+        availability.set("mode:exec", true);
+        availability.set("mode:config", true);
+        availability.set("missing", false);
+        assert!(availability.is_set("mode:config"));
+        assert!(!availability.is_set("mode:exec"));
+        availability.unsubscribe(first);
+        availability.set("feature:bgp", true);
+        assert_eq!(
+            *seen.borrow(),
+            ["mode:exec true", "mode:config true", "mode:exec false"]
+        );
+        let hidden = availability.hidden_unless("feature:bgp");
+        let parser = Parser::new(crate::parser::CommandTree::new().finalize());
+        assert!(!hidden(&parser));
+        availability.set("feature:bgp", false);
+        assert!(hidden(&parser));
+    }
+}
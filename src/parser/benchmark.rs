@@ -0,0 +1,170 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::ParserBuilder;
+use std::time::{Duration, Instant};
+
+/// Complete each `(line, cursor)` pair of a `corpus` with a new
+/// parser from the `builder`, recording the candidates offered and
+/// how long each completion took.
+///
+/// An application can keep a corpus of the lines its users complete
+/// and run it in a test, comparing the report with one from an
+/// earlier version of its command tree to catch completions which
+/// changed or became slower as the tree grew. With a deterministic
+/// [`TreeConfig`], the times are all zero, so that the report can be
+/// compared with a snapshot.
+///
+/// ```
+/// use commands::parser::{complete_corpus, Command, CommandTree, ParserBuilder};
+/// use std::time::Duration;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show interface"));
+/// tree.command(Command::new("show ip"));
+/// let builder = ParserBuilder::new(tree.finalize());
+///
+/// let report = complete_corpus(&builder, &[("show i", 6), ("sh", 2)]);
+/// assert_eq!(report.samples[0].candidates, ["interface", "ip"]);
+/// assert_eq!(report.samples[1].candidates, ["show"]);
+/// assert!(report.slower_than(Duration::from_secs(1)).next().is_none());
+///
+/// let baseline = complete_corpus(&builder, &[("show i", 6)]);
+/// assert!(report.changed_from(&baseline).is_empty());
+/// ```
+///
+/// [`TreeConfig`]: crate::parser::TreeConfig
+pub fn complete_corpus(builder: &ParserBuilder, corpus: &[(&str, usize)]) -> CompletionReport {
+    let samples = corpus
+        .iter()
+        .map(|&(line, cursor)| {
+            let mut parser = builder.build();
+            let start = Instant::now();
+            let completion = parser.complete_line(line, cursor);
+            let elapsed = parser.config.elapsed_since(start);
+            let mut candidates: Vec<String> = vec![];
+            for option in completion.completions.iter().flat_map(|c| &c.options) {
+                if !candidates.contains(&option.option_string) {
+                    candidates.push(option.option_string.clone());
+                }
+            }
+            CompletionSample {
+                line: line.to_string(),
+                cursor,
+                candidates,
+                elapsed,
+            }
+        })
+        .collect();
+    CompletionReport { samples }
+}
+
+/// The results of [`complete_corpus`].
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompletionReport {
+    /// A sample for each pair of the corpus, in the same order.
+    pub samples: Vec<CompletionSample>,
+}
+
+impl CompletionReport {
+    /// The time taken by all of the completions.
+    pub fn total(&self) -> Duration {
+        self.samples.iter().map(|s| s.elapsed).sum()
+    }
+
+    /// The sample which took the longest, if there are any.
+    pub fn slowest(&self) -> Option<&CompletionSample> {
+        self.samples.iter().max_by_key(|s| s.elapsed)
+    }
+
+    /// The samples which took longer than `limit`.
+    pub fn slower_than(&self, limit: Duration) -> impl Iterator<Item = &CompletionSample> {
+        self.samples.iter().filter(move |s| s.elapsed > limit)
+    }
+
+    /// The samples whose candidates differ from those of the
+    /// sample for the same line and cursor in the `baseline`.
+    /// Samples which aren't in the `baseline` are not compared.
+    pub fn changed_from(&self, baseline: &CompletionReport) -> Vec<&CompletionSample> {
+        self.samples
+            .iter()
+            .filter(|sample| {
+                baseline
+                    .samples
+                    .iter()
+                    .find(|b| b.line == sample.line && b.cursor == sample.cursor)
+                    .is_some_and(|b| b.candidates != sample.candidates)
+            })
+            .collect()
+    }
+}
+
+/// The completion of a single line within a [`CompletionReport`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CompletionSample {
+    /// The line which was completed.
+    pub line: String,
+    /// The byte offset within the `line` which was completed at.
+    pub cursor: usize,
+    /// The option strings of the completions, without duplicates,
+    /// in the order that they were offered.
+    pub candidates: Vec<String>,
+    /// How long the completion took, not counting building the
+    /// parser.
+    pub elapsed: Duration,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, Parameter, TreeConfig};
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn corpus_changes() {
+        let corpus = [("s", 1), ("show ", 5), ("x", 1), ("show", 9)];
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        let before = complete_corpus(&ParserBuilder::new(tree.finalize()), &corpus);
+        tree.command(Command::new("set"));
+        let after = complete_corpus(&ParserBuilder::new(tree.finalize()), &corpus);
+
+        assert_eq!(after.samples.len(), 4);
+        assert_eq!(before.samples[0].candidates, ["show"]);
+        assert_eq!(after.samples[0].candidates, ["set", "show"]);
+        assert!(after.samples[2].candidates.is_empty());
+        assert!(after.samples[3].candidates.is_empty());
+        let changed = after.changed_from(&before);
+        assert_eq!(changed.len(), 1);
+        assert_eq!(changed[0].line, "s");
+        assert!(after.slowest().is_some());
+        assert_eq!(after.slower_than(after.total()).count(), 0);
+    }
+
+    #[test]
+    fn deterministic_reports() {
+        let mut tree = CommandTree::new();
+        tree.config(TreeConfig::new().deterministic(true));
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("host"))
+                .parameter(Parameter::new("count"))
+                .parameter(Parameter::new("bytes")),
+        );
+        let builder = ParserBuilder::new(tree.finalize());
+        let report = complete_corpus(&builder, &[("p", 1), ("ping ", 5)]);
+        assert_eq!(report.total(), Duration::ZERO);
+
+        let mut parser = builder.build();
+        if let Ok(tokens) = tokenize("ping a b c") {
+            parser.parse(tokens).unwrap();
+        }
+        let names = parser.parameters().names().collect::<Vec<_>>();
+        assert_eq!(names, ["bytes", "count", "host"]);
+    }
+}
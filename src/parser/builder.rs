@@ -6,6 +6,15 @@
 
 use super::constants::*;
 use super::nodes::*;
+use super::ValueType;
+use super::{
+    BindError, Complete, Completion, ExecContext, InvocationSink, ParsedParameters, Parser,
+    StaticCommand, TreeConfig, TreeError,
+};
+use crate::tokenizer::Token;
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::error::Error;
 use std::rc::Rc;
 
 /// Store a command tree while populating it. This is used
@@ -22,6 +31,14 @@ use std::rc::Rc;
 #[derive(Default)]
 pub struct CommandTree<'a> {
     commands: Vec<Command<'a>>,
+    filters: Vec<Command<'a>>,
+    config: TreeConfig,
+    fallback: Option<FallbackHandler>,
+    value_completer: Option<ValueCompleter>,
+    home_dir: Option<HomeDirProvider>,
+    invocation_sink: Option<Rc<dyn InvocationSink>>,
+    execution_profile: Option<Rc<Cell<ExecutionProfile>>>,
+    help_fragments: HashMap<String, String>,
 }
 
 impl<'a> CommandTree<'a> {
@@ -35,164 +52,837 @@ impl<'a> CommandTree<'a> {
         self.commands.push(command);
     }
 
+    /// Add a filter command, which can only be given after a `|`
+    /// in a pipeline. The filters are a tree of their own, so their
+    /// names don't collide with those of other commands, and the
+    /// commands of the tree can't be given after a `|` once there
+    /// are any filters. See [`execute_pipeline`].
+    ///
+    /// The filters in [`builtins`] can be added here, along with
+    /// any of the application's own.
+    ///
+    /// ```
+    /// use commands::parser::{execute_pipeline, Command, CommandTree};
+    /// use commands::session::builtins;
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.filter(builtins::count());
+    /// tree.command(Command::new("show running-config").handler(|context| {
+    ///     context.output().line("hostname r1");
+    ///     context.output().line("interface eth0");
+    ///     Ok(())
+    /// }));
+    /// tree.command(Command::new("count").help("A command, not a filter"));
+    /// let root = tree.finalize();
+    ///
+    /// if let Ok(tokens) = tokenize("show run | count") {
+    ///     let output = execute_pipeline(&root, tokens).unwrap();
+    ///     assert_eq!(output.lines(), ["2"]);
+    /// }
+    /// ```
+    ///
+    /// [`builtins`]: crate::session::builtins
+    /// [`execute_pipeline`]: crate::parser::execute_pipeline
+    pub fn filter(&mut self, command: Command<'a>) {
+        self.filters.push(command);
+    }
+
+    /// Set the handler for the command which was added with the
+    /// name `path`, replacing any handler that it already had.
+    /// Whitespace within the `path` is not significant.
+    ///
+    /// Handlers can also be bound once the tree has been built
+    /// with [`RootNode::bind`].
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// assert!(tree.bind("show  interface", |_| Ok(())).is_ok());
+    /// assert!(tree.bind("show", |_| Ok(())).is_err());
+    /// ```
+    ///
+    /// [`RootNode::bind`]: crate::parser::RootNode::bind
+    pub fn bind<F>(&mut self, path: &str, handler: F) -> Result<(), BindError>
+    where
+        F: Fn(&ExecContext) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        let words = path.split_whitespace().collect::<Vec<_>>();
+        match self
+            .commands
+            .iter_mut()
+            .find(|c| c.name.split_whitespace().eq(words.iter().copied()))
+        {
+            Some(command) => {
+                command.handler = Some(Rc::new(handler));
+                Ok(())
+            }
+            None => Err(BindError::UnknownPath(path.to_string())),
+        }
+    }
+
+    /// Supply the [`TreeConfig`] to be used with this tree.
+    ///
+    /// [`TreeConfig`]: crate::parser::TreeConfig
+    pub fn config(&mut self, config: TreeConfig) {
+        self.config = config;
+    }
+
+    /// Supply a handler to be run by [`Parser::execute`] when the
+    /// input doesn't match any command. This can be used to pass
+    /// the input along to another shell or to resolve commands
+    /// dynamically.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show"));
+    /// tree.fallback(|tokens| {
+    ///     assert_eq!(tokens[0].text, "ls");
+    ///     Ok(())
+    /// });
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("ls -l") {
+    ///     assert!(parser.parse(tokens).is_err());
+    /// }
+    /// assert!(parser.execute().is_ok());
+    /// ```
+    ///
+    /// [`Parser::execute`]: crate::parser::Parser::execute
+    pub fn fallback<F>(&mut self, handler: F)
+    where
+        F: Fn(&[Token]) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        self.fallback = Some(Rc::new(handler));
+    }
+
+    /// Supply values to offer when completing any named or simple
+    /// parameter, along with the parameter's own values. This can
+    /// be used to complete references to variables.
+    ///
+    /// The `completer` is given the text entered so far.
+    pub fn value_completer<F>(&mut self, completer: F)
+    where
+        F: Fn(&str) -> Vec<String> + 'static,
+    {
+        self.value_completer = Some(Rc::new(completer));
+    }
+
+    /// Supply the home directory used to expand a leading `~` in
+    /// the values of path parameters, rather than using the `HOME`
+    /// environment variable.
+    pub fn home_dir<F>(&mut self, provider: F)
+    where
+        F: Fn() -> Option<String> + 'static,
+    {
+        self.home_dir = Some(Rc::new(provider));
+    }
+
+    /// Supply a sink which is given an [`InvocationRecord`] for each
+    /// command that is executed.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, InvocationOutcome, Parser};
+    /// use commands::tokenizer::tokenize;
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let records = Rc::new(RefCell::new(vec![]));
+    /// let sink = Rc::clone(&records);
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("save").handler(|_| Ok(())));
+    /// tree.invocation_sink(move |record: &_| sink.borrow_mut().push(Clone::clone(record)));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("sa") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// parser.execute().unwrap();
+    /// assert_eq!(records.borrow()[0].line, "save");
+    /// assert_eq!(records.borrow()[0].outcome, InvocationOutcome::Succeeded);
+    /// ```
+    ///
+    /// [`InvocationRecord`]: crate::parser::InvocationRecord
+    pub fn invocation_sink<S: InvocationSink + 'static>(&mut self, sink: S) {
+        self.invocation_sink = Some(Rc::new(sink));
+    }
+
+    /// Share an [`ExecutionProfile`] which decides the commands that
+    /// may be executed, according to their tags. The application
+    /// keeps the other reference to the `profile` and can switch it
+    /// at any time. Executing a command which it doesn't allow fails
+    /// with `ExecuteError::Blocked`.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTag, CommandTree, ExecuteError};
+    /// use commands::parser::{ExecutionProfile, Parser};
+    /// use commands::tokenizer::tokenize;
+    /// use std::cell::Cell;
+    /// use std::rc::Rc;
+    ///
+    /// let profile = Rc::new(Cell::new(ExecutionProfile::Full));
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("erase")
+    ///         .tag(CommandTag::Destructive)
+    ///         .handler(|_| Ok(())),
+    /// );
+    /// tree.execution_profile(Rc::clone(&profile));
+    /// let root = tree.finalize();
+    ///
+    /// let erase = || {
+    ///     let mut parser = Parser::new(Rc::clone(&root));
+    ///     if let Ok(tokens) = tokenize("erase") {
+    ///         parser.parse(tokens).unwrap();
+    ///     }
+    ///     parser.execute()
+    /// };
+    /// assert!(erase().is_ok());
+    /// profile.set(ExecutionProfile::Safe);
+    /// assert!(matches!(erase(), Err(ExecuteError::Blocked(path)) if path == "erase"));
+    /// ```
+    pub fn execution_profile(&mut self, profile: Rc<Cell<ExecutionProfile>>) {
+        self.execution_profile = Some(profile);
+    }
+
+    /// Supply a fragment of help text which can be shared by the
+    /// help text of many commands and parameters, where it is
+    /// given as a `{name}` placeholder.
+    ///
+    /// Help text can also refer to where it is used with these
+    /// placeholders:
+    ///
+    /// * `{command}`: The full name of the command, or of the
+    ///   command that a parameter belongs to.
+    /// * `{name}`: The name of the command word or parameter.
+    /// * `{parent}`: The help text of the enclosing command: the
+    ///   command with one fewer word, or the command that a
+    ///   parameter belongs to.
+    ///
+    /// A fragment's own placeholders refer to where it is used.
+    /// Other placeholders are left as they are.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.help_fragment("vrf", "in the given VRF");
+    /// tree.command(Command::new("show").help("Show information"));
+    /// tree.command(
+    ///     Command::new("show route")
+    ///         .help("{parent}: routes {vrf}")
+    ///         .parameter(Parameter::new("vrf").help("The VRF for '{command}'")),
+    /// );
+    ///
+    /// let root = tree.finalize();
+    /// let route = root.lookup("show route").unwrap();
+    /// assert_eq!(route.node().help_text, "Show information: routes in the given VRF");
+    /// let vrf = &route.successors()[0];
+    /// assert_eq!(vrf.node().help_text, "The VRF for 'show route'");
+    /// ```
+    pub fn help_fragment(&mut self, name: &str, text: &str) {
+        self.help_fragments
+            .insert(name.to_string(), text.to_string());
+    }
+
+    /// Add each of the `commands` described by a `static`, as
+    /// declared by hand or by the output of [`static_source`].
+    ///
+    /// [`static_source`]: CommandTree::static_source
+    pub fn static_commands(&mut self, commands: &'static [StaticCommand]) {
+        self.commands.extend(commands.iter().map(Command::from));
+    }
+
+    /// Write Rust source declaring a `static` named `name` which
+    /// describes the commands of the tree as [`StaticCommand`]s.
+    ///
+    /// A build script can build a tree as usual and write this to
+    /// a file within `OUT_DIR`, which the application then brings in
+    /// with `include!` and passes to [`static_commands`]. This keeps
+    /// the cost of describing a large set of commands out of the
+    /// application's startup, although the nodes are still built
+    /// when the tree is finalized.
+    ///
+    /// Only what can be written as a constant is described. Handlers,
+    /// completers, groups and other closures are left out, and so
+    /// are wrapped roots. A node hidden by a predicate is described
+    /// as always hidden.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show version"));
+    /// let source = tree.static_source("COMMANDS");
+    /// assert!(source.starts_with("pub static COMMANDS: &[::commands::parser::StaticCommand]"));
+    /// assert!(source.contains("name: \"show version\","));
+    /// ```
+    ///
+    /// [`static_commands`]: CommandTree::static_commands
+    pub fn static_source(&self, name: &str) -> String {
+        let mut source = format!("pub static {name}: &[::commands::parser::StaticCommand] = &[\n");
+        for command in &self.commands {
+            source.push_str("    ::commands::parser::StaticCommand {\n");
+            source.push_str(&format!("        name: {:?},\n", command.name));
+            source.push_str(&format!("        help_text: {:?},\n", command.help_text));
+            source.push_str(&format!("        hidden: {},\n", command.hidden.may_hide()));
+            source.push_str(&format!(
+                "        priority: ::commands::parser::Priority::new({}),\n",
+                command.priority.value()
+            ));
+            let tags = command
+                .tags
+                .iter()
+                .map(|t| format!("::commands::parser::CommandTag::{t:?}"))
+                .collect::<Vec<_>>();
+            source.push_str(&format!("        tags: &[{}],\n", tags.join(", ")));
+            source.push_str(&format!("        deprecated: {:?},\n", command.deprecated));
+            source.push_str("        parameters: &[\n");
+            for parameter in &command.parameters {
+                source.push_str(&static_parameter_source(parameter));
+            }
+            source.push_str("        ],\n    },\n");
+        }
+        source.push_str("];\n");
+        source
+    }
+
     /// Construct the `CommandTree` and produce a [`RootNode`].
     ///
     /// [`RootNode`]: crate::parser::RootNode
+    ///
+    /// Commands with names consisting of multiple words, like
+    /// `show interface`, result in a node for each word. Commands
+    /// sharing leading words share the nodes for those words.
+    ///
+    /// While finalizing, an index of the full paths to each command
+    /// is built so that commands can be found with [`Node::lookup`].
+    ///
+    /// This doesn't check for reserved words. See [`try_finalize`].
+    ///
+    /// [`try_finalize`]: CommandTree::try_finalize
+    /// [`Node::lookup`]: crate::parser::Node::lookup
     pub fn finalize(&self) -> Rc<Node> {
-        let mut successors: Vec<Rc<Node>> = vec![];
-        for c in &self.commands {
-            successors.push(Rc::new(Node::Command(self.build_command(c))));
+        let mut root = self.build_root(&self.commands);
+        if !self.filters.is_empty() {
+            let mut filters = self.build_root(&self.filters);
+            filters.config = Rc::clone(&root.config);
+            root.filters = Some(Rc::new(Node::Root(filters)));
         }
-        Rc::new(Node::Root(RootNode::new(successors)))
+        root.fallback = self.fallback.clone();
+        root.value_completer = self.value_completer.clone();
+        root.home_dir = self.home_dir.clone();
+        root.invocation_sink = self.invocation_sink.clone();
+        root.execution_profile = self.execution_profile.clone();
+        let root = Rc::new(Node::Root(root));
+        for command in &self.commands {
+            if let Some(ref path) = command.wrapped_root {
+                let name = command
+                    .name
+                    .split_whitespace()
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                let wrapped = match path.split_whitespace().collect::<Vec<_>>().join(" ") {
+                    path if path.is_empty() => Some(Rc::clone(&root)),
+                    path => root.lookup(&path).cloned(),
+                };
+                if let (Some(Node::Command(node)), Some(wrapped)) =
+                    (root.lookup(&name).map(|n| &**n), wrapped)
+                {
+                    *node.wrapped_root.borrow_mut() = Some(Rc::downgrade(&wrapped));
+                }
+            }
+        }
+        root
+    }
+
+    /// Build a root node leading to the `commands`.
+    fn build_root(&self, commands: &[Command]) -> RootNode {
+        let commands = commands
+            .iter()
+            .map(|c| (c.name.split_whitespace().collect::<Vec<_>>(), c))
+            .filter(|(words, _)| !words.is_empty())
+            .collect::<Vec<_>>();
+        let mut index = HashMap::new();
+        let successors = self.build_level(&commands, 0, "", &mut index);
+        let mut root = RootNode::new(successors);
+        root.config = Rc::new(self.config.clone());
+        root.index = index;
+        root
     }
 
-    fn build_command(&self, command: &Command) -> CommandNode {
+    /// Construct the `CommandTree` as with [`finalize`], once it has
+    /// been checked for names which are among the `reserved_words`
+    /// of its [`TreeConfig`]. Those are the words of command names
+    /// and the names and aliases of named parameters and flags.
+    /// The names of simple parameters are never typed, so they
+    /// aren't checked.
+    ///
+    /// [`finalize`]: CommandTree::finalize
+    pub fn try_finalize(&self) -> Result<Rc<Node>, TreeError> {
+        for command in self.commands.iter().chain(&self.filters) {
+            let words = command.name.split_whitespace().collect::<Vec<_>>();
+            if let Some(word) = words.iter().find(|w| self.config.is_reserved(w)) {
+                return Err(TreeError::ReservedWord(words.join(" "), word.to_string()));
+            }
+            for parameter in &command.parameters {
+                if parameter.kind == ParameterKind::Simple {
+                    continue;
+                }
+                let mut names = Some(&parameter.name).into_iter().chain(&parameter.aliases);
+                if let Some(name) = names.find(|n| self.config.is_reserved(n)) {
+                    return Err(TreeError::ReservedWord(
+                        format!("{} {}", words.join(" "), parameter.name),
+                        name.to_string(),
+                    ));
+                }
+            }
+        }
+        Ok(self.finalize())
+    }
+
+    /// Build the nodes for the word at `depth` of each of the `commands`,
+    /// which all share the same leading words.
+    fn build_level(
+        &self,
+        commands: &[(Vec<&str>, &Command)],
+        depth: usize,
+        parent_help: &str,
+        index: &mut HashMap<String, Rc<Node>>,
+    ) -> Vec<Rc<Node>> {
+        let mut words: Vec<&str> = vec![];
+        for (command_words, _) in commands {
+            if !words.contains(&command_words[depth]) {
+                words.push(command_words[depth]);
+            }
+        }
+        let mut nodes = vec![];
+        for word in words {
+            let group = commands
+                .iter()
+                .filter(|(command_words, _)| command_words[depth] == word)
+                .cloned()
+                .collect::<Vec<_>>();
+            let (children, defined): (Vec<_>, Vec<_>) = group
+                .into_iter()
+                .partition(|(command_words, _)| command_words.len() > depth + 1);
+            let path = children
+                .first()
+                .or(defined.first())
+                .map(|(command_words, _)| command_words[..=depth].join(" "))
+                .unwrap_or_default();
+            let scope = HelpScope {
+                fragments: &self.help_fragments,
+                command: &path,
+                parent: parent_help,
+            };
+            let help = defined
+                .first()
+                .and_then(|(_, command)| scope.render(word, command.help_text));
+            let successors =
+                self.build_level(&children, depth + 1, help.as_deref().unwrap_or(""), index);
+            let mut node = match defined.first() {
+                Some((_, command)) => {
+                    let scope = HelpScope {
+                        parent: help.as_deref().unwrap_or(""),
+                        ..scope
+                    };
+                    self.build_command(word, &scope, help.as_deref(), command, successors)
+                }
+                None => CommandNode::new(
+                    word,
+                    None,
+                    Hidden::Never,
+                    Priority::command(),
+                    successors,
+                    None,
+                    vec![],
+                ),
+            };
+            node.node.path = path.clone();
+            let node = Rc::new(Node::Command(node));
+            index.insert(path, Rc::clone(&node));
+            nodes.push(node);
+        }
+        nodes
+    }
+
+    fn build_command(
+        &self,
+        name: &str,
+        scope: &HelpScope,
+        help_text: Option<&str>,
+        command: &Command,
+        mut successors: Vec<Rc<Node>>,
+    ) -> CommandNode {
+        let path = scope.command;
         let mut parameters: Vec<Rc<Node>> = vec![];
-        let mut successors: Vec<Rc<Node>> = vec![];
+        let mut negations: Vec<Rc<Node>> = vec![];
         for parameter in &command.parameters {
             match parameter.kind {
                 ParameterKind::Flag => {
-                    self.build_flag_parameter(parameter, &mut parameters, &mut successors);
+                    self.build_flag_parameter(scope, parameter, &mut parameters, &mut successors);
+                    if parameter.negatable {
+                        negations.push(self.build_negation(scope, parameter));
+                    }
                 }
                 ParameterKind::Named => {
-                    self.build_named_parameter(parameter, &mut parameters, &mut successors);
+                    self.build_named_parameter(scope, parameter, &mut parameters, &mut successors);
                 }
                 ParameterKind::Simple => {
-                    self.build_simple_parameter(parameter, &mut parameters, &mut successors);
+                    self.build_simple_parameter(scope, parameter, &mut parameters, &mut successors);
                 }
             };
         }
-        // We'll want to find the right node for the wrapped_root
-        // and pass it along here.
-        CommandNode::new(
-            command.name,
-            command.help_text,
-            command.hidden,
+        if let Some(first) = negations.first() {
+            // A single `no` node leads to each of the negated flags.
+            let mut n = ParameterNameNode::new(
+                "no",
+                Hidden::Never,
+                Priority::command(),
+                negations.clone(),
+                true,
+                None,
+                Rc::clone(first),
+            );
+            n.node.path = format!("{path} no");
+            n.node.help_symbol = "no <flag>".to_string();
+            n.node.help_text = "Negate a flag".to_string();
+            successors.push(Rc::new(Node::ParameterName(n)));
+        }
+        let mut node = CommandNode::new(
+            name,
+            help_text,
+            command.hidden.clone(),
             command.priority,
             successors,
-            None,
+            command.handler.clone(),
             parameters,
-        )
+        );
+        node.groups = command.groups.clone();
+        node.node.custom_completion = command.custom_completion.clone();
+        node.epilogue = command.epilogue.clone();
+        node.node.deprecated = command.deprecated.map(str::to_string);
+        node.tags = command.tags.clone();
+        node
     }
 
     fn build_flag_parameter(
         &self,
+        scope: &HelpScope,
         parameter: &Parameter,
         parameters: &mut Vec<Rc<Node>>,
         successors: &mut Vec<Rc<Node>>,
     ) {
-        let p = ParameterNode::new(
+        let mut p = ParameterNode::new(
             parameter.name,
             parameter.help_text,
-            parameter.hidden,
-            parameter.priority.unwrap_or(PRIORITY_DEFAULT),
+            parameter.hidden.clone(),
+            parameter.priority.unwrap_or(Priority::command()),
             vec![],
             parameter.repeatable,
             None,
             parameter.kind,
             parameter.required,
         );
+        configure_parameter(&mut p, scope, parameter);
         let p = Rc::new(Node::Parameter(p));
         parameters.push(Rc::clone(&p));
         successors.push(p);
     }
 
+    /// Build the `no <name>` form of a negatable flag.
+    fn build_negation(&self, scope: &HelpScope, parameter: &Parameter) -> Rc<Node> {
+        let mut p = ParameterNode::new(
+            parameter.name,
+            parameter.help_text,
+            parameter.hidden.clone(),
+            parameter.priority.unwrap_or(Priority::command()),
+            vec![],
+            false,
+            None,
+            ParameterKind::Flag,
+            false,
+        );
+        p.node.path = format!("{} no {}", scope.command, parameter.name);
+        if let Some(help_text) = scope.render(parameter.name, parameter.help_text) {
+            p.node.help_text = help_text;
+        }
+        p.negation = true;
+        Rc::new(Node::Parameter(p))
+    }
+
     fn build_named_parameter(
         &self,
+        scope: &HelpScope,
         parameter: &Parameter,
         parameters: &mut Vec<Rc<Node>>,
         successors: &mut Vec<Rc<Node>>,
     ) {
-        let p = ParameterNode::new(
+        let mut p = ParameterNode::new(
             parameter.name,
             parameter.help_text,
-            parameter.hidden,
-            parameter.priority.unwrap_or(PRIORITY_PARAMETER),
+            parameter.hidden.clone(),
+            parameter.priority.unwrap_or(Priority::parameter()),
             vec![],
             parameter.repeatable,
             None,
             parameter.kind,
             parameter.required,
         );
+        configure_parameter(&mut p, scope, parameter);
         let p = Rc::new(Node::Parameter(p));
         parameters.push(Rc::clone(&p));
-        let n = ParameterNameNode::new(
+        let mut n = ParameterNameNode::new(
             parameter.name,
-            parameter.hidden,
-            PRIORITY_DEFAULT,
+            parameter.hidden.clone(),
+            Priority::command(),
             vec![Rc::clone(&p)],
             parameter.repeatable,
             Some(Rc::clone(&p)),
             Rc::clone(&p),
         );
+        n.node.path = format!("{} {}", scope.command, parameter.name);
         successors.push(Rc::new(Node::ParameterName(n)));
         for alias in &parameter.aliases {
-            let a = ParameterNameNode::new(
+            let mut a = ParameterNameNode::new(
                 alias,
-                parameter.hidden,
-                PRIORITY_DEFAULT,
+                parameter.hidden.clone(),
+                Priority::command(),
                 vec![Rc::clone(&p)],
                 parameter.repeatable,
                 Some(Rc::clone(&p)),
                 Rc::clone(&p),
             );
+            a.node.path = format!("{} {alias}", scope.command);
             successors.push(Rc::new(Node::ParameterName(a)));
         }
     }
 
     fn build_simple_parameter(
         &self,
+        scope: &HelpScope,
         parameter: &Parameter,
         parameters: &mut Vec<Rc<Node>>,
         successors: &mut Vec<Rc<Node>>,
     ) {
-        let p = ParameterNode::new(
+        let mut p = ParameterNode::new(
             parameter.name,
             parameter.help_text,
-            parameter.hidden,
-            parameter.priority.unwrap_or(PRIORITY_PARAMETER),
+            parameter.hidden.clone(),
+            parameter.priority.unwrap_or(Priority::parameter()),
             vec![],
             parameter.repeatable,
             None,
             parameter.kind,
             parameter.required,
         );
+        configure_parameter(&mut p, scope, parameter);
         let p = Rc::new(Node::Parameter(p));
         parameters.push(Rc::clone(&p));
         successors.push(Rc::clone(&p));
     }
 }
 
+fn configure_parameter(p: &mut ParameterNode, scope: &HelpScope, parameter: &Parameter) {
+    p.node.path = format!("{} {}", scope.command, parameter.name);
+    if let Some(help_text) = scope.render(parameter.name, parameter.help_text) {
+        p.node.help_text = help_text;
+    }
+    p.completer = parameter.completer.clone();
+    p.choices = parameter.choices.iter().map(|c| c.to_string()).collect();
+    p.negatable = parameter.negatable && parameter.kind == ParameterKind::Flag;
+    p.normalizations = parameter.normalizations.clone();
+    p.node.custom_completion = parameter.custom_completion.clone();
+    p.node.deprecated = parameter.deprecated.map(str::to_string);
+    if parameter.kind != ParameterKind::Flag {
+        p.set_value_types(&parameter.value_types);
+    }
+}
+
+/// What the placeholders in help text refer to while building
+/// the nodes for a command or its parameters.
+#[derive(Clone, Copy)]
+struct HelpScope<'s> {
+    fragments: &'s HashMap<String, String>,
+    command: &'s str,
+    parent: &'s str,
+}
+
+impl HelpScope<'_> {
+    /// Replace the placeholders in the help text for the command
+    /// word or parameter called `name`.
+    fn render(&self, name: &str, help_text: Option<&str>) -> Option<String> {
+        let help_text = replace_placeholders(help_text?, |key| self.fragments.get(key).cloned());
+        Some(replace_placeholders(&help_text, |key| match key {
+            "command" => Some(self.command.to_string()),
+            "name" => Some(name.to_string()),
+            "parent" => Some(self.parent.to_string()),
+            _ => None,
+        }))
+    }
+}
+
+/// Replace each `{key}` in `text` with its value, if `value` gives
+/// one for the key.
+fn replace_placeholders<F>(text: &str, value: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut result = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '-'));
+        match end.filter(|&end| after[end..].starts_with('}')) {
+            Some(end) => match value(&after[..end]) {
+                Some(replacement) => result.push_str(&replacement),
+                None => result.push_str(&rest[start..start + end + 2]),
+            },
+            None => {
+                result.push('{');
+                rest = after;
+                continue;
+            }
+        }
+        rest = &after[end.unwrap_or(0) + 1..];
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Write the `StaticParameter` describing `parameter`, as part of
+/// [`CommandTree::static_source`].
+fn static_parameter_source(parameter: &Parameter) -> String {
+    let kind = match parameter.kind {
+        ParameterKind::Flag => "Flag",
+        ParameterKind::Named => "Named",
+        ParameterKind::Simple => "Simple",
+    };
+    let priority = match parameter.priority {
+        Some(p) => format!("Some(::commands::parser::Priority::new({}))", p.value()),
+        None => "None".to_string(),
+    };
+    let list = |items: Vec<String>| items.join(", ");
+    let value_types = list(
+        parameter
+            .value_types
+            .iter()
+            .map(|t| format!("::commands::parser::ValueType::{t:?}"))
+            .collect(),
+    );
+    let normalizations = list(
+        parameter
+            .normalizations
+            .iter()
+            .map(|n| format!("::commands::parser::Normalization::{n:?}"))
+            .collect(),
+    );
+    let quoted = |items: &[&str]| list(items.iter().map(|i| format!("{i:?}")).collect());
+    let fields = [
+        format!("name: {:?}", parameter.name),
+        format!("help_text: {:?}", parameter.help_text),
+        format!("hidden: {}", parameter.hidden.may_hide()),
+        format!("priority: {priority}"),
+        format!("kind: ::commands::parser::ParameterKind::{kind}"),
+        format!("required: {}", parameter.required),
+        format!("repeatable: {}", parameter.repeatable),
+        format!("aliases: &[{}]", quoted(&parameter.aliases)),
+        format!("value_types: &[{value_types}]"),
+        format!("choices: &[{}]", quoted(&parameter.choices)),
+        format!("negatable: {}", parameter.negatable),
+        format!("normalizations: &[{normalizations}]"),
+        format!("deprecated: {:?}", parameter.deprecated),
+    ];
+    let mut source = "            ::commands::parser::StaticParameter {\n".to_string();
+    for field in fields {
+        source.push_str(&format!("                {field},\n"));
+    }
+    source.push_str("            },\n");
+    source
+}
+
 /// Description of a command to be added to the [`CommandTree`].
 ///
 /// The lifetime parameter `'a` refers to the lifetime
 /// of the strings used for command names and help text.
 pub struct Command<'a> {
-    hidden: bool,
-    priority: i32,
+    hidden: Hidden,
+    priority: Priority,
     name: &'a str,
     help_text: Option<&'a str>,
     parameters: Vec<Parameter<'a>>,
+    handler: Option<CommandHandler>,
+    groups: Vec<ParameterGroup>,
     wrapped_root: Option<String>,
+    custom_completion: Option<Rc<dyn Complete>>,
+    epilogue: Option<CompletionEpilogue>,
+    deprecated: Option<&'a str>,
+    tags: Vec<CommandTag>,
 }
 
 impl<'a> Command<'a> {
     /// Construct a default (blank) command with the given `name`.
+    ///
+    /// The `name` may consist of multiple words, separated by
+    /// whitespace, like `show interface`.
     pub fn new(name: &'a str) -> Self {
         Command {
-            hidden: false,
-            priority: PRIORITY_DEFAULT,
+            hidden: Hidden::Never,
+            priority: Priority::command(),
             name,
             help_text: None,
             parameters: vec![],
+            handler: None,
+            groups: vec![],
             wrapped_root: None,
+            custom_completion: None,
+            epilogue: None,
+            deprecated: None,
+            tags: vec![],
         }
     }
 
     /// Mark the command as hidden. Hidden commands will match
     /// within the parser, but are not listed during completion.
     pub fn hidden(mut self, hidden: bool) -> Self {
-        self.hidden = hidden;
+        self.hidden = hidden.into();
+        self
+    }
+
+    /// Hide the command whenever `predicate` returns `true` for the
+    /// parser which is completing it. Like other hidden commands,
+    /// it will still match.
+    pub fn hidden_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Parser) -> bool + 'static,
+    {
+        self.hidden = Hidden::When(Rc::new(predicate));
+        self
+    }
+
+    /// Tag the command with what it does, so that an
+    /// [`ExecutionProfile`] can block it. See
+    /// [`CommandTree::execution_profile`].
+    pub fn tag(mut self, tag: CommandTag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Mark the command as deprecated, with a hint of what to use
+    /// instead, like `use "show interface" instead`. The command
+    /// still works, but is listed by [`deprecations`].
+    ///
+    /// [`deprecations`]: crate::parser::deprecations
+    pub fn deprecated(mut self, replacement: &'a str) -> Self {
+        self.deprecated = Some(replacement);
         self
     }
 
@@ -200,8 +890,56 @@ impl<'a> Command<'a> {
     /// out conflicts during matching and completion.
     ///
     /// This is not commonly needed.
-    pub fn priority(mut self, priority: i32) -> Self {
-        self.priority = priority;
+    pub fn priority<P: Into<Priority>>(mut self, priority: P) -> Self {
+        self.priority = priority.into();
+        self
+    }
+
+    /// Install custom completion logic for the command, replacing
+    /// the completion to its name.
+    pub fn complete_with<C: Complete + 'static>(mut self, complete: C) -> Self {
+        self.custom_completion = Some(Rc::new(complete));
+        self
+    }
+
+    /// Supply extra completions for the command, which are offered
+    /// after those from its grammar once all of its required
+    /// parameters have been given. This lets a frontend advertise
+    /// what may follow the command, like a `|` to filter its output.
+    ///
+    /// The `epilogue` is given the parameters parsed so far. The
+    /// options of its completions are filtered by the token being
+    /// completed, as for the completions of nodes.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Completion, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show log").epilogue(|_| {
+    ///     vec![Completion::new(
+    ///         "| include <pattern>".to_string(),
+    ///         "Show only the matching lines".to_string(),
+    ///         None,
+    ///         true,
+    ///         &["|"],
+    ///         &[],
+    ///     )]
+    /// }));
+    /// let mut parser = Parser::new(tree.finalize());
+    ///
+    /// if let Ok(tokens) = tokenize("show log") {
+    ///     parser.parse(tokens).unwrap();
+    ///     let completions = parser.complete(None);
+    ///     assert_eq!(completions.len(), 1);
+    ///     assert_eq!(completions[0].help_symbol, "| include <pattern>");
+    /// }
+    /// ```
+    pub fn epilogue<F>(mut self, epilogue: F) -> Self
+    where
+        F: Fn(&ParsedParameters) -> Vec<Completion<'static>> + 'static,
+    {
+        self.epilogue = Some(Rc::new(epilogue));
         self
     }
 
@@ -217,10 +955,77 @@ impl<'a> Command<'a> {
         self
     }
 
-    /// The `wrapped_root` signifies the path to the command that should
-    /// be wrapped by this command. This is used for the `help` command.
+    /// Declare that at most one of the parameters with the given
+    /// `names` may be given, like `brief` and `detail`.
+    pub fn exclusive(mut self, names: &[&str]) -> Self {
+        self.groups.push(ParameterGroup {
+            kind: GroupKind::Exclusive,
+            names: names.iter().map(|n| n.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Declare that exactly one of the parameters with the given
+    /// `names` must be given, like `src` and `interface`.
+    pub fn one_of(mut self, names: &[&str]) -> Self {
+        self.groups.push(ParameterGroup {
+            kind: GroupKind::OneOf,
+            names: names.iter().map(|n| n.to_string()).collect(),
+        });
+        self
+    }
+
+    /// Supply the handler which is run when the command is
+    /// executed by [`Parser::execute`]. This may be a function
+    /// or a closure.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree};
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    ///
+    /// let count = Rc::new(RefCell::new(0));
+    /// let counter = Rc::clone(&count);
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("count").handler(move |_| {
+    ///     *counter.borrow_mut() += 1;
+    ///     Ok(())
+    /// }));
+    /// ```
     ///
-    /// [`CommandNode`]: crate::parser::CommandNode
+    /// [`Parser::execute`]: crate::parser::Parser::execute
+    pub fn handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&ExecContext) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        self.handler = Some(Rc::new(handler));
+        self
+    }
+
+    /// The `wrapped_root` signifies the path to the command whose
+    /// subcommands are wrapped by this command, or an empty path
+    /// for all of the commands. The wrapped commands are completed
+    /// and parsed following this command.
+    ///
+    /// When executed, the wrapping command's handler is run and it
+    /// can find the wrapped command with [`ExecContext::wrapped`].
+    /// If there is no command at the path, nothing is wrapped.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("explain").wraps(String::new()));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("explain show interface") {
+    ///     assert!(parser.parse(tokens).is_ok());
+    /// }
+    /// ```
+    ///
+    /// [`ExecContext::wrapped`]: crate::parser::ExecContext::wrapped
     pub fn wraps(mut self, wrapped_root: String) -> Self {
         self.wrapped_root = Some(wrapped_root);
         self
@@ -233,21 +1038,28 @@ impl<'a> Command<'a> {
 /// of the strings used for parameter names, aliases and
 /// help text.
 pub struct Parameter<'a> {
-    hidden: bool,
-    priority: Option<i32>,
+    hidden: Hidden,
+    priority: Option<Priority>,
     name: &'a str,
     repeatable: bool,
     aliases: Vec<&'a str>,
     help_text: Option<&'a str>,
     kind: ParameterKind,
     required: bool,
+    completer: Option<ValueCompleter>,
+    value_types: Vec<ValueType>,
+    choices: Vec<&'a str>,
+    negatable: bool,
+    normalizations: Vec<Normalization>,
+    custom_completion: Option<Rc<dyn Complete>>,
+    deprecated: Option<&'a str>,
 }
 
 impl<'a> Parameter<'a> {
     /// Construct a default (blank) parameter with the given `name`.
     pub fn new(name: &'a str) -> Self {
         Parameter {
-            hidden: false,
+            hidden: Hidden::Never,
             priority: None,
             name,
             repeatable: false,
@@ -255,26 +1067,54 @@ impl<'a> Parameter<'a> {
             help_text: None,
             kind: ParameterKind::Simple,
             required: false,
+            completer: None,
+            value_types: vec![ValueType::String],
+            choices: vec![],
+            negatable: false,
+            normalizations: vec![],
+            custom_completion: None,
+            deprecated: None,
         }
     }
 
     /// Mark the parameter as hidden. Hidden parameters will match
     /// within the parser, but are not listed during completion.
     pub fn hidden(mut self, hidden: bool) -> Self {
-        self.hidden = hidden;
+        self.hidden = hidden.into();
+        self
+    }
+
+    /// Hide the parameter whenever `predicate` returns `true` for
+    /// the parser which is completing it.
+    pub fn hidden_when<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Parser) -> bool + 'static,
+    {
+        self.hidden = Hidden::When(Rc::new(predicate));
+        self
+    }
+
+    /// Mark the parameter as deprecated, with a hint of what to use
+    /// instead. The parameter still works, but is listed by
+    /// [`deprecations`].
+    ///
+    /// [`deprecations`]: crate::parser::deprecations
+    pub fn deprecated(mut self, replacement: &'a str) -> Self {
+        self.deprecated = Some(replacement);
         self
     }
 
     /// Give the parameter a priority. This is used when sorting
     /// out conflicts during matching and completion.
     ///
-    /// The `priority` of a `Parameter` defaults to `PRIORITY_PARAMETER`
-    /// except for when the `kind` is `ParameterKind::Flag` in which
-    /// case, the default will be `PRIORITY_DEFAULT`.
+    /// The `priority` of a `Parameter` defaults to
+    /// [`Priority::parameter`] except for when the `kind` is
+    /// `ParameterKind::Flag` in which case, the default will be
+    /// [`Priority::command`].
     ///
     /// This is not commonly needed.
-    pub fn priority(mut self, priority: i32) -> Self {
-        self.priority = Some(priority);
+    pub fn priority<P: Into<Priority>>(mut self, priority: P) -> Self {
+        self.priority = Some(priority.into());
         self
     }
 
@@ -307,6 +1147,131 @@ impl<'a> Parameter<'a> {
         self
     }
 
+    /// Install custom completion logic for the parameter, replacing
+    /// the completion of its values, or of its name for a flag.
+    /// This takes the place of any `completer` or `choices` when
+    /// completing.
+    pub fn complete_with<C: Complete + 'static>(mut self, complete: C) -> Self {
+        self.custom_completion = Some(Rc::new(complete));
+        self
+    }
+
+    /// Supply the values to offer when completing the parameter.
+    /// The `completer` is given the text entered so far.
+    ///
+    /// This is used for named and simple parameters.
+    pub fn completer<F>(mut self, completer: F) -> Self
+    where
+        F: Fn(&str) -> Vec<String> + 'static,
+    {
+        self.completer = Some(Rc::new(completer));
+        self
+    }
+
+    /// Set the type of value accepted by the parameter. Tokens which
+    /// aren't valid values of the type don't match the parameter.
+    ///
+    /// This is used for named and simple parameters.
+    pub fn value_type(mut self, value_type: ValueType) -> Self {
+        self.value_types = vec![value_type];
+        self
+    }
+
+    /// Set the types of value accepted by the parameter, when it
+    /// accepts more than one. A value is detected as the first of
+    /// these which accepts it, and that is recorded as the
+    /// `value_type` of the [`ParameterValue`].
+    ///
+    /// This is used for named and simple parameters.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser, ValueType};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("interface").parameter(
+    ///         Parameter::new("id").value_types(&[ValueType::Integer, ValueType::String]),
+    ///     ),
+    /// );
+    /// let root = tree.finalize();
+    ///
+    /// let mut parser = Parser::new(root);
+    /// if let Ok(tokens) = tokenize("interface 0x10") {
+    ///     parser.parse(tokens).unwrap();
+    ///     let id = &parser.parameters().values("id")[0];
+    ///     assert_eq!(id.value, "16");
+    ///     assert_eq!(id.value_type, Some(ValueType::Integer));
+    /// }
+    /// if let Ok(tokens) = tokenize("interface eth0") {
+    ///     parser.reset();
+    ///     parser.parse(tokens).unwrap();
+    ///     let id = &parser.parameters().values("id")[0];
+    ///     assert_eq!(id.value_type, Some(ValueType::String));
+    /// }
+    /// ```
+    ///
+    /// [`ParameterValue`]: crate::parser::ParameterValue
+    pub fn value_types(mut self, value_types: &[ValueType]) -> Self {
+        if !value_types.is_empty() {
+            self.value_types = value_types.to_vec();
+        }
+        self
+    }
+
+    /// Limit the parameter to the given values. Other tokens don't
+    /// match the parameter and completion offers only these.
+    ///
+    /// This is used for named and simple parameters.
+    pub fn choices(mut self, choices: &[&'a str]) -> Self {
+        self.choices = choices.to_vec();
+        self
+    }
+
+    /// Establish whether or not this flag can be negated by giving
+    /// it as `no <name>`, as with `no shutdown`. The value of a
+    /// negatable flag is `true` when given and `false` when negated.
+    ///
+    /// This is used for flag parameters.
+    pub fn negatable(mut self, negatable: bool) -> Self {
+        self.negatable = negatable;
+        self
+    }
+
+    /// Transform the values of the parameter before they are
+    /// validated and stored, so that handlers receive them in a
+    /// canonical form. This can be given more than once and the
+    /// transformations are applied in order.
+    ///
+    /// This is used for named and simple parameters.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Normalization, Parameter, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("set mode").parameter(
+    ///         Parameter::new("mode")
+    ///             .normalize(Normalization::Trim)
+    ///             .normalize(Normalization::Lowercase)
+    ///             .choices(&["fast", "slow"]),
+    ///     ),
+    /// );
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("set mode \" FAST \"") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// let mode = parser.parameters().value("mode").unwrap();
+    /// assert_eq!(mode.as_str(), "fast");
+    /// assert_eq!(mode.raw, "\" FAST \"");
+    /// ```
+    pub fn normalize(mut self, normalization: Normalization) -> Self {
+        self.normalizations.push(normalization);
+        self
+    }
+
     /// Set which type of [`ParameterNode`] is supposed to be created
     /// to represent this parameter.
     ///
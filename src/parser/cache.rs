@@ -0,0 +1,122 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+/// Caches the candidates which parameters complete to when they
+/// come from an external source, such as the interfaces of a
+/// device, which is too slow to ask on every completion.
+///
+/// The cache has a generation, which the application advances
+/// with [`invalidate`] when the data of its sources changes. Each
+/// [`completer`] loads its candidates the first time that it is
+/// used within a generation and reuses them until the next, so a
+/// long-lived session doesn't keep offering stale values.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, CompletionCache, Parameter, Parser};
+/// use std::cell::RefCell;
+/// use std::rc::Rc;
+///
+/// let interfaces = Rc::new(RefCell::new(vec!["eth0".to_string()]));
+/// let cache = Rc::new(CompletionCache::new());
+/// let source = Rc::clone(&interfaces);
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("show interface")
+///         .parameter(Parameter::new("name").completer(cache.completer(move || source.borrow().clone()))),
+/// );
+/// let root = tree.finalize();
+/// let candidates = || {
+///     let mut parser = Parser::new(Rc::clone(&root));
+///     let completion = parser.complete_line("show interface ", 15);
+///     let options = completion.completions[0].options.iter();
+///     options.filter(|o| o.complete).map(|o| o.option_string.clone()).collect::<Vec<_>>()
+/// };
+///
+/// assert_eq!(candidates(), ["eth0"]);
+/// interfaces.borrow_mut().push("eth1".to_string());
+/// assert_eq!(candidates(), ["eth0"]);
+/// cache.invalidate();
+/// assert_eq!(candidates(), ["eth0", "eth1"]);
+/// ```
+///
+/// [`completer`]: CompletionCache::completer
+/// [`invalidate`]: CompletionCache::invalidate
+#[derive(Debug, Default)]
+pub struct CompletionCache {
+    generation: Cell<u64>,
+}
+
+impl CompletionCache {
+    /// Construct a `CompletionCache` at its first generation.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// The current generation, which starts at 0.
+    pub fn generation(&self) -> u64 {
+        self.generation.get()
+    }
+
+    /// Advance to the next generation, so that each completer loads
+    /// its candidates again when it is next used.
+    pub fn invalidate(&self) {
+        self.generation.set(self.generation.get() + 1);
+    }
+
+    /// A completer for `Parameter::completer` or
+    /// `CommandTree::value_completer` which offers the candidates
+    /// given by `load`, loading them at most once a generation.
+    pub fn completer<F>(self: &Rc<Self>, load: F) -> impl Fn(&str) -> Vec<String> + 'static
+    where
+        F: Fn() -> Vec<String> + 'static,
+    {
+        let cache = Rc::clone(self);
+        let loaded: RefCell<Option<(u64, Vec<String>)>> = RefCell::new(None);
+        move |_| {
+            let generation = cache.generation();
+            let mut loaded = loaded.borrow_mut();
+            match *loaded {
+                Some((g, ref candidates)) if g == generation => candidates.clone(),
+                _ => {
+                    let candidates = load();
+                    *loaded = Some((generation, candidates.clone()));
+                    candidates
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn generations() {
+        let cache = Rc::new(CompletionCache::new());
+        let loads = Rc::new(Cell::new(0));
+        let counter = Rc::clone(&loads);
+        let complete = cache.completer(move || {
+            counter.set(counter.get() + 1);
+            vec![format!("v{}", counter.get())]
+        });
+        let other = cache.completer(|| vec!["x".to_string()]);
+
+        assert_eq!(complete(""), ["v1"]);
+        assert_eq!(complete("v"), ["v1"]);
+        assert_eq!(other(""), ["x"]);
+        assert_eq!(loads.get(), 1);
+        cache.invalidate();
+        assert_eq!(cache.generation(), 1);
+        assert_eq!(loads.get(), 1);
+        assert_eq!(complete(""), ["v2"]);
+        assert_eq!(complete(""), ["v2"]);
+        assert_eq!(loads.get(), 2);
+    }
+}
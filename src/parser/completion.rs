@@ -4,14 +4,87 @@
 // option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
-use crate::tokenizer::Token;
+use super::nodes::Node;
+use crate::tokenizer::{quote, unquote_partial, Token};
 use crate::util::longest_common_prefix;
+use std::borrow::Cow;
+use std::ops::Range;
+use std::rc::Rc;
+
+/// Custom completion logic for a node, replacing what the node
+/// would complete to itself. This is installed for a parameter with
+/// [`Parameter::complete_with`] or for a command with
+/// [`Command::complete_with`].
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, Complete, Completion, Node, Parameter, Parser};
+/// use commands::tokenizer::{tokenize, Token};
+///
+/// struct Users(Vec<&'static str>);
+///
+/// impl Complete for Users {
+///     fn complete<'text>(&self, node: &Node, token: Option<Token<'text>>) -> Completion<'text> {
+///         let help_symbol = node.node().help_symbol.clone();
+///         let help_text = node.node().help_text.clone();
+///         Completion::new(help_symbol, help_text, token, true, &self.0, &[])
+///     }
+/// }
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("finger")
+///         .parameter(Parameter::new("user").complete_with(Users(vec!["alice", "bob"]))),
+/// );
+/// let mut parser = Parser::new(tree.finalize());
+///
+/// if let Ok(tokens) = tokenize("finger ") {
+///     parser.parse(tokens[..1].to_vec()).unwrap();
+///     let completions = parser.complete(None);
+///     assert!(completions[0].exhaustive);
+///     assert_eq!(completions[0].options[0].option_string, "alice");
+///     assert_eq!(completions[0].options[1].option_string, "bob");
+/// }
+/// ```
+///
+/// [`Command::complete_with`]: crate::parser::Command::complete_with
+/// [`Parameter::complete_with`]: crate::parser::Parameter::complete_with
+pub trait Complete {
+    /// Complete the `node`, given the `token` which hints the
+    /// completion, if any.
+    fn complete<'text>(&self, node: &Node, token: Option<Token<'text>>) -> Completion<'text>;
+
+    /// Complete the `node` with at most `limit` options, as when
+    /// `TreeConfig::max_completion_options` is set.
+    ///
+    /// By default, this completes the node and then [truncates] the
+    /// options. A source with very many values, such as one which
+    /// queries a database, can give just the first of them along
+    /// with how many there are, using [`Completion::truncated`], to
+    /// avoid listing all of them.
+    ///
+    /// [truncates]: Completion::truncate
+    fn complete_limited<'text>(
+        &self,
+        node: &Node,
+        token: Option<Token<'text>>,
+        limit: usize,
+    ) -> Completion<'text> {
+        let mut completion = self.complete(node, token);
+        completion.truncate(limit, TRUNCATED_HINT);
+        completion
+    }
+}
+
+/// The hint given with the options of a completion which has been
+/// truncated by the parser.
+pub(crate) const TRUNCATED_HINT: &str = "Type more to narrow down the options.";
 
 /// Represents a single option returned by `complete`.
 ///
 /// An option may be `complete`, which means that it represents
 /// a syntactically complete parameter value which can be used
 /// as-is, whereas incomplete options are not valid values.
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct CompletionOption {
     /// String for this option.
     pub option_string: String,
@@ -40,23 +113,75 @@ impl CompletionOption {
 /// The lifetime parameter `'text` refers to the lifetime of the
 /// body of text which generated the [`Token`].
 ///
+/// With the `serde` feature, completions can be serialized, as to
+/// send them to a graphical or web frontend. The fields are given
+/// under the same names, with the hint token, replacement range,
+/// total count and hint omitted when there are none. In JSON, a
+/// completion looks like:
+///
+/// ```text
+/// {
+///   "help_symbol": "interface",
+///   "help_text": "Show interfaces",
+///   "token": {
+///     "text": "in",
+///     "token_type": "word",
+///     "location": {
+///       "start": { "byte": 5, "char": 5, "line": 0, "column": 5 },
+///       "end": { "byte": 6, "char": 6, "line": 0, "column": 6 }
+///     }
+///   },
+///   "replace": { "start": 5, "end": 7 },
+///   "exhaustive": true,
+///   "required": false,
+///   "options": [{ "option_string": "interface", "complete": true }]
+/// }
+/// ```
+///
 /// [`Token`]: crate::tokenizer::Token
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Completion<'text> {
     /// Value placeholder for help.
     pub help_symbol: String,
     /// Main help text.
     pub help_text: String,
     /// Token used to hint the completion, if provided.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
     pub token: Option<Token<'text>>,
+    /// The range of characters within the body of text which an
+    /// option replaces: that of the hint token. This is `None`
+    /// without a hint token, when an option is inserted instead.
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub replace: Option<Range<usize>>,
     /// Was this completion exhaustive? If yes, then only
     /// the given completion options are valid.
     pub exhaustive: bool,
+    /// True if this completion is for a required parameter which
+    /// hasn't been given yet, so that it can be shown as something
+    /// which must still be entered.
+    pub required: bool,
     /// The actual completion options.
     pub options: Vec<CompletionOption>,
+    /// The number of options there are, when only some of them are
+    /// given because there are too many. See [`Completion::truncate`].
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub total_count: Option<usize>,
+    /// How to narrow down the options, to be shown to the user when
+    /// only some of them are given, like `Type more of the name.`
+    #[cfg_attr(feature = "serde", serde(skip_serializing_if = "Option::is_none"))]
+    pub hint: Option<String>,
 }
 
 impl<'text> Completion<'text> {
     /// Construct a new Completion.
+    ///
+    /// The options are compared with the value of the hint token,
+    /// without its quotes or escaping backslashes, so a token which
+    /// is still being typed, like `"My Doc`, is compared as `My Doc`.
+    /// The options are then written in the same way as the token:
+    /// quoted with its quote, or with whitespace, quotes and
+    /// backslashes escaped. Incomplete options are left without a
+    /// closing quote, so that more can be typed.
     pub fn new(
         help_symbol: String,
         help_text: String,
@@ -65,28 +190,29 @@ impl<'text> Completion<'text> {
         complete_options: &[&str],
         other_options: &[&str],
     ) -> Completion<'text> {
-        // Preserve all of the options while still &str so that
+        let (typed, open) = token.map_or((Cow::Borrowed(""), None), |t| {
+            unquote_partial(t.text, t.smart_quoted)
+        });
+        // Preserve the matching options while still &str so that
         // we can use this with longest_common_prefix later.
         let mut all_options = complete_options.to_vec();
         all_options.extend(other_options.iter().cloned());
-
-        // Convert to String...
-        let mut complete_options = complete_options
+        all_options.retain(|o| o.starts_with(&*typed));
+        // Apply token restrictions and quote the options.
+        let complete_options = complete_options
             .iter()
-            .map(|o| o.to_string())
+            .filter(|o| o.starts_with(&*typed))
+            .map(|o| quote(o, open, true))
             .collect::<Vec<_>>();
         let mut other_options = other_options
             .iter()
-            .map(|o| o.to_string())
+            .filter(|o| o.starts_with(&*typed))
+            .map(|o| quote(o, open, false))
             .collect::<Vec<_>>();
-        // Apply token restrictions
         if let Some(t) = token {
-            // Filter options using token.
-            let token_text = t.text.to_string();
-            complete_options.retain(|o| o.starts_with(t.text));
-            other_options.retain(|o| o.starts_with(t.text));
             // If not exhaustive, then add the current token as
             // an incomplete option.
+            let token_text = t.text.to_string();
             if !exhaustive
                 && !complete_options.contains(&token_text)
                 && !other_options.contains(&token_text)
@@ -94,35 +220,260 @@ impl<'text> Completion<'text> {
                 other_options.push(token_text);
             }
         }
-        // Add longest common prefix as an incomplete options, but
-        // filter it against the existing options and the token.
-        let lcp = longest_common_prefix(&all_options).to_string();
-        if !complete_options.contains(&lcp) && !other_options.contains(&lcp) {
-            match token {
-                Some(t) => {
-                    if lcp != t.text {
-                        other_options.push(lcp);
-                    }
-                }
-                None => other_options.push(lcp),
+        // Add the longest common prefix of the matching options as an
+        // incomplete option, when it extends what has been typed, but
+        // filter it against the existing options.
+        let lcp = longest_common_prefix(&all_options);
+        let extends = token.is_none() || lcp.len() > typed.len();
+        if extends && !all_options.contains(&lcp) {
+            let lcp = quote(lcp, open, false);
+            if !other_options.contains(&lcp) {
+                other_options.push(lcp);
             }
         }
         // Convert options to CompletionOption.
         let mut options = complete_options
-            .iter()
-            .map(|o| CompletionOption::new(o.clone(), true))
+            .into_iter()
+            .map(|o| CompletionOption::new(o, true))
             .collect::<Vec<_>>();
         options.extend(
             other_options
-                .iter()
-                .map(|o| CompletionOption::new(o.clone(), false)),
+                .into_iter()
+                .map(|o| CompletionOption::new(o, false)),
         );
-        Completion {
+        let mut completion = Completion {
             help_symbol,
             help_text,
             token,
+            replace: token.map(|t| t.location.char_range()),
             exhaustive,
+            required: false,
             options,
+            total_count: None,
+            hint: None,
+        };
+        completion.sort_options();
+        completion
+    }
+
+    /// Mark the options as only some of the `total_count` options,
+    /// which a completion source found too many to give, with a
+    /// `hint` telling the user how to narrow them down.
+    ///
+    /// ```
+    /// use commands::parser::{Complete, Completion, Node};
+    /// use commands::tokenizer::Token;
+    ///
+    /// struct Hosts;
+    ///
+    /// impl Complete for Hosts {
+    ///     fn complete<'text>(&self, node: &Node, token: Option<Token<'text>>) -> Completion<'text> {
+    ///         self.complete_limited(node, token, usize::MAX)
+    ///     }
+    ///
+    ///     fn complete_limited<'text>(
+    ///         &self,
+    ///         node: &Node,
+    ///         token: Option<Token<'text>>,
+    ///         limit: usize,
+    ///     ) -> Completion<'text> {
+    ///         // Only the first `limit` hosts are fetched.
+    ///         let hosts = (0..limit.min(20_000)).map(|i| format!("host{}", i)).collect::<Vec<_>>();
+    ///         let hosts = hosts.iter().map(String::as_str).collect::<Vec<_>>();
+    ///         let node = node.node();
+    ///         let completion = Completion::new(
+    ///             node.help_symbol.clone(),
+    ///             node.help_text.clone(),
+    ///             token,
+    ///             true,
+    ///             &hosts,
+    ///             &[],
+    ///         );
+    ///         if limit < 20_000 {
+    ///             completion.truncated(20_000, "Type more of the host name.")
+    ///         } else {
+    ///             completion
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn truncated(mut self, total_count: usize, hint: &str) -> Self {
+        self.total_count = Some(total_count);
+        self.hint = Some(hint.to_string());
+        self
+    }
+
+    /// Keep only the first `limit` options, in the order given by
+    /// [`Completion::sort_options`], when there are more than that,
+    /// recording how many there were and a `hint` telling the user
+    /// how to narrow them down, unless it already has one.
+    pub fn truncate(&mut self, limit: usize, hint: &str) {
+        if self.options.len() > limit {
+            self.total_count = Some(self.total_count.unwrap_or(0).max(self.options.len()));
+            self.hint.get_or_insert_with(|| hint.to_string());
+            self.options.truncate(limit);
         }
     }
+
+    /// Whether or not only some of the options are given.
+    pub fn is_truncated(&self) -> bool {
+        self.total_count.is_some()
+    }
+
+    /// This completion, hinted by the `token` instead, so that
+    /// only its options matching the token remain. This is `None`
+    /// when none of them match.
+    pub(crate) fn rehint(self, token: Option<Token<'text>>) -> Option<Completion<'text>> {
+        let token = match token {
+            Some(token) => token,
+            None => return Some(Completion { token, ..self }),
+        };
+        let options = |complete| {
+            self.options
+                .iter()
+                .filter(|o| o.complete == complete)
+                .map(|o| o.option_string.as_str())
+                .collect::<Vec<_>>()
+        };
+        let completion = Completion::new(
+            self.help_symbol.clone(),
+            self.help_text.clone(),
+            Some(token),
+            true,
+            &options(true),
+            &options(false),
+        );
+        if completion.options.is_empty() {
+            None
+        } else {
+            Some(completion)
+        }
+    }
+
+    /// Sort the options so that an option which is exactly the
+    /// text of the hint token comes first, then the complete
+    /// options and then the incomplete ones, each alphabetically.
+    pub fn sort_options(&mut self) {
+        let token = self.token.map(|t| t.text);
+        self.options.sort_by(|a, b| {
+            let key = |o: &CompletionOption| (Some(o.option_string.as_str()) != token, !o.complete);
+            key(a)
+                .cmp(&key(b))
+                .then_with(|| a.option_string.cmp(&b.option_string))
+        });
+    }
+
+    /// The range of bytes within `text`, the body of text which
+    /// the hint token came from, which an option replaces. This
+    /// allows a line editor to splice an option into its buffer.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show"));
+    /// let mut parser = Parser::new(tree.finalize());
+    ///
+    /// let line = "\u{3000}sh";
+    /// let tokens = tokenize(line).unwrap();
+    /// let completions = parser.complete(Some(tokens[1]));
+    /// assert_eq!(completions[0].replace, Some(1..3));
+    /// let replace = completions[0].replace_bytes(line).unwrap();
+    /// assert_eq!(replace, 3..5);
+    ///
+    /// let mut line = line.to_string();
+    /// line.replace_range(replace, "show");
+    /// assert_eq!(line, "\u{3000}show");
+    /// ```
+    pub fn replace_bytes(&self, text: &str) -> Option<Range<usize>> {
+        let replace = self.replace.as_ref()?;
+        let byte = |char| text.char_indices().nth(char).map_or(text.len(), |(i, _)| i);
+        Some(byte(replace.start)..byte(replace.end))
+    }
+}
+
+/// The result of asking for help with a `?` at the end of a line,
+/// as returned by [`Parser::contextual_help`].
+///
+/// The `?` is not a part of the command, so it should not stay on
+/// the line: a REPL should not echo it, but list the `completions`
+/// and then redraw the prompt with the original `line`.
+///
+/// [`Parser::contextual_help`]: crate::parser::Parser::contextual_help
+pub struct ContextualHelp<'text> {
+    /// The line without the `?`, to restore after showing help.
+    pub line: &'text str,
+    /// The completions at the point where help was requested.
+    pub completions: Vec<Completion<'text>>,
+    /// True if the `?` should not be echoed.
+    pub suppress_echo: bool,
+}
+
+/// The completions for the token at the cursor within a line, as
+/// returned by [`Parser::complete_line`].
+///
+/// [`Parser::complete_line`]: crate::parser::Parser::complete_line
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct LineCompletion<'text> {
+    /// The range of bytes within the line which a completion
+    /// option replaces: from the start of the token being completed
+    /// up to the cursor. This is empty, at the cursor, when the
+    /// cursor isn't within or at the end of a token.
+    pub replace: Range<usize>,
+    /// The completions for the token.
+    pub completions: Vec<Completion<'text>>,
+}
+
+/// An option found by fuzzy matching, as returned by
+/// [`Parser::complete_fuzzy`].
+///
+/// [`Parser::complete_fuzzy`]: crate::parser::Parser::complete_fuzzy
+pub struct FuzzyMatch {
+    /// The words to enter, like `show interface`.
+    pub option_string: String,
+    /// How well the text matched the option. Higher is better.
+    pub score: i32,
+    /// The node for the last word of the option.
+    pub node: Rc<Node>,
+}
+
+/// A line to insert for a command, with placeholders for the values
+/// of its required parameters, as returned by
+/// [`CommandNode::template`].
+///
+/// [`CommandNode::template`]: crate::parser::CommandNode::template
+pub struct CommandTemplate {
+    /// The line, like `show route src <A.B.C.D>`.
+    pub line: String,
+    /// The placeholders within the line, in order.
+    pub placeholders: Vec<Placeholder>,
+}
+
+/// A placeholder for the value of a parameter within a
+/// [`CommandTemplate`], to be replaced by the user.
+pub struct Placeholder {
+    /// The name of the parameter.
+    pub name: String,
+    /// The range of bytes within the line which the placeholder
+    /// covers.
+    pub range: Range<usize>,
+}
+
+/// A prompt for a required parameter which hasn't been given, as
+/// returned by [`Parser::prompts`].
+///
+/// A frontend can ask the user for a value for each prompt and
+/// give the answer to [`Parser::supply`].
+///
+/// [`Parser::prompts`]: crate::parser::Parser::prompts
+/// [`Parser::supply`]: crate::parser::Parser::supply
+pub struct ParameterPrompt {
+    /// The name of the parameter.
+    pub name: String,
+    /// The help symbol, help text and possible values of the
+    /// parameter.
+    pub completion: Completion<'static>,
+    /// The node for the parameter.
+    pub node: Rc<Node>,
 }
@@ -0,0 +1,361 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::ScoreWeights;
+use crate::tokenizer::TokenizerConfig;
+use std::borrow::Cow;
+use std::time::{Duration, Instant};
+
+/// Configuration shared by a command tree and the parsers using it.
+///
+/// A `TreeConfig` is supplied to the [`CommandTree`] while building
+/// and is stored in the resulting [`RootNode`]. A [`Parser`] created
+/// with that root node uses the configuration.
+///
+/// ```
+/// use commands::parser::{CommandTree, TreeConfig};
+///
+/// let mut tree = CommandTree::new();
+/// tree.config(TreeConfig::new().max_repeated_values(16));
+/// ```
+///
+/// [`CommandTree`]: crate::parser::CommandTree
+/// [`Parser`]: crate::parser::Parser
+/// [`RootNode`]: crate::parser::RootNode
+#[derive(Clone, Debug, Default)]
+pub struct TreeConfig {
+    /// The weights used to rank the nodes matching a token.
+    pub weights: ScoreWeights,
+    /// Whether or not tokens may match node names fuzzily.
+    /// See [`MatchQuality::Fuzzy`].
+    ///
+    /// [`MatchQuality::Fuzzy`]: crate::parser::MatchQuality::Fuzzy
+    pub fuzzy_matching: bool,
+    /// The maximum number of values that a repeatable parameter
+    /// may be given within a single command.
+    pub max_repeated_values: Option<usize>,
+    /// Whether or not parsers should record a [`TokenProfile`]
+    /// for each token that they match.
+    ///
+    /// [`TokenProfile`]: crate::parser::TokenProfile
+    pub profile: bool,
+    /// How the values of `Integer` and `Float` parameters are
+    /// written.
+    pub number_format: NumberFormat,
+    /// The maximum number of alternative interpretations that a
+    /// parser may try while resolving ambiguous matches.
+    pub max_backtracking: Option<usize>,
+    /// Words with a meaning of their own to the application, like
+    /// `|`, `no` or `help`, which commands and parameters may not
+    /// be named and which values are quoted as.
+    pub reserved_words: Vec<String>,
+    /// The maximum number of options in each completion. See
+    /// [`TreeConfig::max_completion_options`].
+    pub max_completion_options: Option<usize>,
+    /// Whether or not the results of parsers are reproducible, for
+    /// snapshot tests. See [`TreeConfig::deterministic`].
+    pub deterministic: bool,
+    /// How parsers tokenize the lines given to them. See
+    /// [`TreeConfig::tokenizer`].
+    pub tokenizer: TokenizerConfig,
+}
+
+impl TreeConfig {
+    /// Construct a default `TreeConfig`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Supply the weights used to rank the nodes matching a token.
+    pub fn weights(mut self, weights: ScoreWeights) -> Self {
+        self.weights = weights;
+        self
+    }
+
+    /// Allow tokens to match node names fuzzily.
+    pub fn fuzzy_matching(mut self, fuzzy_matching: bool) -> Self {
+        self.fuzzy_matching = fuzzy_matching;
+        self
+    }
+
+    /// Limit the number of values that a repeatable parameter may
+    /// be given. Exceeding this results in `ParseError::TooManyValues`.
+    pub fn max_repeated_values(mut self, max_repeated_values: usize) -> Self {
+        self.max_repeated_values = Some(max_repeated_values);
+        self
+    }
+
+    /// Record timing and candidate counts while matching tokens.
+    /// These are available from `Parser::profile` after parsing.
+    pub fn profile(mut self, profile: bool) -> Self {
+        self.profile = profile;
+        self
+    }
+
+    /// Limit the number of alternative interpretations tried while
+    /// parsing a line. Input with many ambiguous tokens can have
+    /// exponentially many interpretations. Exceeding this results
+    /// in `ParseError::TooComplex`.
+    pub fn max_backtracking(mut self, max_backtracking: usize) -> Self {
+        self.max_backtracking = Some(max_backtracking);
+        self
+    }
+
+    /// Limit the number of options in each completion, so that a
+    /// parameter whose values come from a large source doesn't
+    /// produce tens of thousands of them. A completion with more
+    /// options keeps the first of them, with the number that there
+    /// were as its `total_count` and a `hint` to type more of the
+    /// value. Custom completions are asked for only this many with
+    /// `Complete::complete_limited`.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser, TreeConfig};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.config(TreeConfig::new().max_completion_options(2));
+    /// tree.command(Command::new("ping").parameter(
+    ///     Parameter::new("host").completer(|_| (0..50).map(|i| format!("h{:02}", i)).collect()),
+    /// ));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// let completion = &parser.complete_line("ping h0", 7).completions[0];
+    /// assert_eq!(completion.options.len(), 2);
+    /// assert_eq!(completion.total_count, Some(11));
+    /// assert!(completion.hint.is_some());
+    /// ```
+    pub fn max_completion_options(mut self, max_completion_options: usize) -> Self {
+        self.max_completion_options = Some(max_completion_options);
+        self
+    }
+
+    /// Reserve the given `words`, which have a meaning of their own
+    /// to the application, like `|`, `no` or `help`.
+    ///
+    /// [`CommandTree::try_finalize`] fails when a command word, or
+    /// the name or alias of a named parameter or flag, is reserved.
+    /// [`Parser::canonical_line`] quotes values which are reserved,
+    /// so that the line can't be mistaken for using them.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser, TreeConfig};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.config(TreeConfig::new().reserved_words(&["|", "no", "help"]));
+    /// tree.command(Command::new("grep").parameter(Parameter::new("pattern")));
+    ///
+    /// let mut parser = Parser::new(tree.try_finalize().unwrap());
+    /// if let Ok(tokens) = tokenize("grep help") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// assert_eq!(parser.canonical_line(), "grep \"help\"");
+    ///
+    /// tree.command(Command::new("show help"));
+    /// assert!(tree.try_finalize().is_err());
+    /// ```
+    ///
+    /// [`CommandTree::try_finalize`]: crate::parser::CommandTree::try_finalize
+    /// [`Parser::canonical_line`]: crate::parser::Parser::canonical_line
+    pub fn reserved_words(mut self, words: &[&str]) -> Self {
+        self.reserved_words = words.iter().map(|w| w.to_string()).collect();
+        self
+    }
+
+    /// Whether or not `word` is one of the `reserved_words`.
+    pub fn is_reserved(&self, word: &str) -> bool {
+        self.reserved_words.iter().any(|w| w == word)
+    }
+
+    /// Make the results of parsers reproducible, so that tests can
+    /// compare completions, help and suggestions with snapshots
+    /// taken on another machine.
+    ///
+    /// Completions, help, suggestions and parameter names are always
+    /// given in a stable order, and nothing is random. What remains
+    /// is the state of the tree and the clock. In this mode, the
+    /// usage counts of the nodes aren't used to score matches, so
+    /// executing a command doesn't change how later lines parse.
+    /// The times recorded in each `TokenProfile` and by
+    /// `complete_corpus` are always zero.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser, TreeConfig};
+    /// use commands::tokenizer::tokenize;
+    /// use std::time::Duration;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.config(TreeConfig::new().profile(true).deterministic(true));
+    /// tree.command(Command::new("show"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("show") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// assert_eq!(parser.profile().unwrap()[0].elapsed, Duration::ZERO);
+    /// ```
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// The time since `start`, or zero when `deterministic`.
+    pub(crate) fn elapsed_since(&self, start: Instant) -> Duration {
+        if self.deterministic {
+            Duration::ZERO
+        } else {
+            start.elapsed()
+        }
+    }
+
+    /// Tokenize the lines given to `Parser::complete_line`,
+    /// `Parser::hint`, `Parser::contextual_help` and
+    /// `Parser::line_status` with `config`, which should be how the
+    /// application tokenizes the lines that it parses, so that they
+    /// agree about its separators, quotes and other rules.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser, TreeConfig};
+    /// use commands::tokenizer::TokenizerConfig;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.config(TreeConfig::new().tokenizer(TokenizerConfig::new().separator(',')));
+    /// tree.command(Command::new("show interface"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// let completion = parser.complete_line("show,i", 6);
+    /// assert_eq!(completion.completions[0].options[0].option_string, "interface");
+    /// ```
+    pub fn tokenizer(mut self, config: TokenizerConfig) -> Self {
+        self.tokenizer = config;
+        self
+    }
+
+    /// Accept numbers written in the given format, such as with
+    /// a decimal comma, for `Integer` and `Float` parameters.
+    pub fn number_format(mut self, number_format: NumberFormat) -> Self {
+        self.number_format = number_format;
+        self
+    }
+}
+
+/// How numbers are written, so that the values of `Integer` and
+/// `Float` parameters can be given as users in different locales
+/// expect. Values are stored in the usual form, with a `.` as the
+/// decimal separator and without separators between groups of
+/// digits, so they can still be parsed with `str::parse`.
+///
+/// ```
+/// use commands::parser::{
+///     Command, CommandTree, NumberFormat, Parameter, Parser, TreeConfig, ValueType,
+/// };
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.config(TreeConfig::new().number_format(NumberFormat::new(',', Some('.'))));
+/// tree.command(
+///     Command::new("limit").parameter(Parameter::new("rate").value_type(ValueType::Float)),
+/// );
+///
+/// let mut parser = Parser::new(tree.finalize());
+/// if let Ok(tokens) = tokenize("limit 1.234,5") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// assert_eq!(parser.parameters().get::<f64>("rate").unwrap(), 1234.5);
+/// ```
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct NumberFormat {
+    /// The character between the integer and fractional parts.
+    pub decimal_separator: char,
+    /// The character between groups of three digits in the
+    /// integer part, if any. The digits don't have to be grouped.
+    pub group_separator: Option<char>,
+}
+
+impl Default for NumberFormat {
+    fn default() -> Self {
+        NumberFormat::new('.', None)
+    }
+}
+
+impl NumberFormat {
+    /// Construct a `NumberFormat` with the given separators.
+    pub fn new(decimal_separator: char, group_separator: Option<char>) -> Self {
+        NumberFormat {
+            decimal_separator,
+            group_separator,
+        }
+    }
+
+    /// Rewrite the number in `text` in the usual form, returning
+    /// `None` when it isn't written correctly in this format. Only
+    /// the separators are checked, not that `text` is a number.
+    pub fn canonicalize<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
+        if *self == NumberFormat::default() {
+            return Some(Cow::Borrowed(text));
+        }
+        let unsigned = text.trim_start_matches(['-', '+']);
+        let sign = &text[..text.len() - unsigned.len()];
+        let (integer, fraction) = match unsigned.split_once(self.decimal_separator) {
+            Some((integer, fraction)) => (integer, Some(fraction)),
+            None => (unsigned, None),
+        };
+        let mut number = sign.to_string();
+        match self.group_separator {
+            Some(separator) if integer.contains(separator) => {
+                for (i, group) in integer.split(separator).enumerate() {
+                    let valid = if i == 0 {
+                        (1..=3).contains(&group.len())
+                    } else {
+                        group.len() == 3
+                    };
+                    if !valid {
+                        return None;
+                    }
+                    number.push_str(group);
+                }
+            }
+            _ => number.push_str(integer),
+        }
+        if !number[sign.len()..].bytes().all(|b| b.is_ascii_digit()) {
+            return None;
+        }
+        if let Some(fraction) = fraction {
+            let separators = ['.', self.decimal_separator];
+            if fraction.contains(separators)
+                || self.group_separator.is_some_and(|s| fraction.contains(s))
+            {
+                return None;
+            }
+            number.push('.');
+            number.push_str(fraction);
+        }
+        Some(Cow::Owned(number))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn canonicalize_numbers() {
+        let format = NumberFormat::new(',', Some('.'));
+        assert_eq!(format.canonicalize("1.234.567,89").unwrap(), "1234567.89");
+        assert_eq!(format.canonicalize("-1234,5").unwrap(), "-1234.5");
+        assert_eq!(format.canonicalize("12").unwrap(), "12");
+        assert!(format.canonicalize("1.23").is_none());
+        assert!(format.canonicalize("1234.567").is_none());
+        assert!(format.canonicalize("1,2,3").is_none());
+
+        let format = NumberFormat::new('.', Some(','));
+        assert_eq!(format.canonicalize("1,000.5").unwrap(), "1000.5");
+        assert!(format.canonicalize("1,00").is_none());
+
+        let format = NumberFormat::default();
+        assert_eq!(format.canonicalize("1e3").unwrap(), "1e3");
+    }
+}
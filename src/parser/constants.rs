@@ -16,9 +16,144 @@ pub enum ParameterKind {
     Simple,
 }
 
-/// Minimum priority.
-pub const PRIORITY_MINIMUM: i32 = -10000;
-/// The default priority for a parameter.
-pub const PRIORITY_PARAMETER: i32 = -10;
-/// The default priority.
-pub const PRIORITY_DEFAULT: i32 = 0;
+/// Indicate how the parameters in a [`ParameterGroup`] relate
+/// to each other.
+///
+/// [`ParameterGroup`]: crate::parser::ParameterGroup
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum GroupKind {
+    /// At most one of the parameters may be given.
+    Exclusive,
+    /// Exactly one of the parameters must be given.
+    OneOf,
+}
+
+/// A transformation applied to the values of a parameter before
+/// they are validated and stored.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Normalization {
+    /// Convert the value to lowercase.
+    Lowercase,
+    /// Convert the value to uppercase.
+    Uppercase,
+    /// Remove leading and trailing whitespace from the value.
+    Trim,
+}
+
+/// What a command does, so that an [`ExecutionProfile`] can
+/// block it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CommandTag {
+    /// The command changes or removes something in a way which
+    /// can't easily be undone, like erasing the configuration.
+    Destructive,
+    /// The command reads or writes files, or redirects output to
+    /// one, like the `source` built-in.
+    FileAccess,
+}
+
+/// Which commands a [`Parser`] may execute, according to their
+/// [`CommandTag`]s. The profile is shared with the tree as an
+/// `Rc<Cell<ExecutionProfile>>` by [`CommandTree::execution_profile`],
+/// so that an application can switch to a read-only or demo mode
+/// while it runs, without building another tree.
+///
+/// [`Parser`]: crate::parser::Parser
+/// [`CommandTree::execution_profile`]: crate::parser::CommandTree::execution_profile
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ExecutionProfile {
+    /// Every command may be executed.
+    #[default]
+    Full,
+    /// Commands tagged as `Destructive` or as `FileAccess` are
+    /// blocked.
+    Safe,
+}
+
+impl ExecutionProfile {
+    /// Whether or not a command with the given `tags` may be
+    /// executed.
+    pub fn allows(&self, tags: &[CommandTag]) -> bool {
+        match *self {
+            ExecutionProfile::Full => true,
+            ExecutionProfile::Safe => !tags
+                .iter()
+                .any(|t| matches!(t, CommandTag::Destructive | CommandTag::FileAccess)),
+        }
+    }
+}
+
+/// The maximum number of alternative interpretations tried for
+/// untrusted input when no `TreeConfig::max_backtracking` is
+/// configured.
+pub const UNTRUSTED_MAX_BACKTRACKING: usize = 1024;
+
+/// The priority of a node, used when sorting out conflicts during
+/// matching and completion. When more than one node matches a
+/// token, the priority is a part of the score of each node. When
+/// completing without a token, nodes with a higher priority are
+/// listed first.
+///
+/// Besides the priorities that nodes are given by default, an
+/// application can define bands of its own:
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, Parser, Priority};
+///
+/// const PREFERRED: Priority = Priority::command().raised(10);
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show"));
+/// tree.command(Command::new("set").priority(PREFERRED));
+/// let parser = Parser::new(tree.finalize());
+///
+/// let completions = parser.complete(None);
+/// assert_eq!(completions[0].help_symbol, "set");
+/// assert!(PREFERRED > Priority::command());
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Priority(i32);
+
+impl Priority {
+    /// A priority with the given `value`.
+    pub const fn new(value: i32) -> Self {
+        Priority(value)
+    }
+
+    /// The lowest priority that is used.
+    pub const fn minimum() -> Self {
+        Priority(-10000)
+    }
+
+    /// The default priority of a parameter, other than a flag.
+    pub const fn parameter() -> Self {
+        Priority(-10)
+    }
+
+    /// The default priority of a command, a flag or the name
+    /// of a named parameter.
+    pub const fn command() -> Self {
+        Priority(0)
+    }
+
+    /// The value of the priority.
+    pub const fn value(self) -> i32 {
+        self.0
+    }
+
+    /// A priority `amount` higher than this one.
+    pub const fn raised(self, amount: i32) -> Self {
+        Priority(self.0.saturating_add(amount))
+    }
+
+    /// A priority `amount` lower than this one.
+    pub const fn lowered(self, amount: i32) -> Self {
+        Priority(self.0.saturating_sub(amount))
+    }
+}
+
+impl From<i32> for Priority {
+    fn from(value: i32) -> Self {
+        Priority(value)
+    }
+}
@@ -0,0 +1,161 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::nodes::{CommandNode, Node};
+use super::{CommandInvocation, CommandOutput, FromParsedArguments};
+use super::{ParameterError, ParameterValue, ParsedParameters};
+use crate::tokenizer::Token;
+use std::cell::{RefCell, RefMut};
+use std::rc::Rc;
+
+/// The context in which a command handler is executed.
+///
+/// This gives a [`CommandHandler`] access to what was accepted
+/// by the [`Parser`]: the command node, the tokens and the values
+/// of the parameters. Handlers write their [`CommandOutput`] to
+/// the context as well.
+///
+/// [`CommandHandler`]: crate::parser::CommandHandler
+/// [`Parser`]: crate::parser::Parser
+pub struct ExecContext<'p, 'text> {
+    root: &'p Rc<Node>,
+    node: &'p Rc<Node>,
+    command: &'p CommandNode,
+    tokens: &'p [Token<'text>],
+    parameters: &'p ParsedParameters,
+    wrapped: Option<&'p Rc<Node>>,
+    wrapped_tokens: &'p [Token<'text>],
+    input: Option<&'p CommandOutput>,
+    filters: &'p [CommandInvocation],
+    output: RefCell<CommandOutput>,
+}
+
+impl<'p, 'text> ExecContext<'p, 'text> {
+    pub(crate) fn new(
+        root: &'p Rc<Node>,
+        node: &'p Rc<Node>,
+        command: &'p CommandNode,
+        tokens: &'p [Token<'text>],
+        parameters: &'p ParsedParameters,
+    ) -> Self {
+        ExecContext {
+            root,
+            node,
+            command,
+            tokens,
+            parameters,
+            wrapped: None,
+            wrapped_tokens: &[],
+            input: None,
+            filters: &[],
+            output: RefCell::new(CommandOutput::new()),
+        }
+    }
+
+    pub(crate) fn with_wrapped(
+        mut self,
+        wrapped: Option<&'p Rc<Node>>,
+        wrapped_tokens: &'p [Token<'text>],
+    ) -> Self {
+        self.wrapped = wrapped;
+        self.wrapped_tokens = wrapped_tokens;
+        self
+    }
+
+    pub(crate) fn with_input(mut self, input: Option<&'p CommandOutput>) -> Self {
+        self.input = input;
+        self
+    }
+
+    pub(crate) fn with_filters(mut self, filters: &'p [CommandInvocation]) -> Self {
+        self.filters = filters;
+        self
+    }
+
+    pub(crate) fn into_output(self) -> CommandOutput {
+        self.output.into_inner()
+    }
+
+    /// The node that the parser started from, usually the root
+    /// of the command tree. Commands which run other commands
+    /// can use this to construct a [`Parser`](crate::parser::Parser).
+    pub fn root(&self) -> &Rc<Node> {
+        self.root
+    }
+
+    /// The node for the command being executed.
+    pub fn node(&self) -> &Rc<Node> {
+        self.node
+    }
+
+    /// The command being executed.
+    pub fn command(&self) -> &CommandNode {
+        self.command
+    }
+
+    /// The tokens which were accepted by the parser.
+    pub fn tokens(&self) -> &[Token<'text>] {
+        self.tokens
+    }
+
+    /// When the command being executed wraps other commands, the
+    /// wrapped command which was given, if any.
+    pub fn wrapped(&self) -> Option<&Rc<Node>> {
+        self.wrapped
+    }
+
+    /// When the command being executed wraps other commands, the
+    /// tokens of the wrapped command which was given, following
+    /// any parameters of the command being executed.
+    pub fn wrapped_tokens(&self) -> &[Token<'text>] {
+        self.wrapped_tokens
+    }
+
+    /// The values given for each parameter, by parameter name.
+    pub fn parameters(&self) -> &ParsedParameters {
+        self.parameters
+    }
+
+    /// The output of the command before this one in a pipeline,
+    /// if there is one. Filters work with this.
+    pub fn input(&self) -> Option<&CommandOutput> {
+        self.input
+    }
+
+    /// The filters which follow the command in a pipeline, in the
+    /// order that its output will pass through them, as with
+    /// `show running-config | include ip | count`. A handler can
+    /// use these to avoid producing output that would be dropped.
+    pub fn filters(&self) -> &[CommandInvocation] {
+        self.filters
+    }
+
+    /// The output of the command.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the output is already borrowed.
+    pub fn output(&self) -> RefMut<'_, CommandOutput> {
+        self.output.borrow_mut()
+    }
+
+    /// The first value given for the parameter `name`, if any.
+    pub fn parameter(&self, name: &str) -> Option<&str> {
+        self.parameters.value(name).map(ParameterValue::as_str)
+    }
+
+    /// Build a `T` from the values given for the parameters.
+    pub fn extract<T: FromParsedArguments>(&self) -> Result<T, ParameterError> {
+        T::from_parsed_arguments(self.parameters)
+    }
+
+    /// All of the values given for the parameter `name`, along
+    /// with the text and location that they came from. This is
+    /// empty when the parameter was not given.
+    pub fn values(&self, name: &str) -> &[ParameterValue] {
+        self.parameters.values(name)
+    }
+}
@@ -0,0 +1,106 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::nodes::Node;
+use std::rc::Rc;
+
+/// List the deprecated commands and parameters of a finalized
+/// command tree, with what to use instead and how many times each
+/// has been used, so that an application can tell its users about
+/// the deprecated commands which they are still using.
+///
+/// The uses are the usage counts which the parser keeps as it
+/// executes commands, so they cover the commands executed with
+/// the tree since it was finalized.
+///
+/// The deprecations are given in the order that the nodes are
+/// found while walking the tree from the `root`, with each command
+/// followed by its parameters.
+///
+/// ```
+/// use commands::parser::{deprecations, Command, CommandTree, Parameter, Parser};
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("show interface")
+///         .handler(|_| Ok(()))
+///         .parameter(Parameter::new("brief").deprecated("use \"summary\" instead")),
+/// );
+/// tree.command(
+///     Command::new("show interfaces")
+///         .deprecated("use \"show interface\" instead")
+///         .handler(|_| Ok(())),
+/// );
+/// let root = tree.finalize();
+///
+/// let mut parser = Parser::new(root.clone());
+/// if let Ok(tokens) = tokenize("show interfaces") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// parser.execute().unwrap();
+///
+/// let report = deprecations(&root);
+/// assert_eq!(report.len(), 2);
+/// assert_eq!(report[0].path, "show interface brief");
+/// assert!(report[0].parameter);
+/// assert_eq!(report[1].path, "show interfaces");
+/// assert_eq!(report[1].replacement, "use \"show interface\" instead");
+/// let used = report.iter().filter(|d| d.is_used()).collect::<Vec<_>>();
+/// assert_eq!(used.len(), 1);
+/// assert_eq!(used[0].uses, 1);
+/// ```
+pub fn deprecations(root: &Rc<Node>) -> Vec<Deprecation> {
+    let mut found = vec![];
+    deprecations_within(root, &mut found);
+    found
+}
+
+fn deprecations_within(node: &Rc<Node>, found: &mut Vec<Deprecation>) {
+    for successor in node.successors() {
+        if let Node::Command(ref command) = **successor {
+            let mut found_in = |node: &Rc<Node>, parameter| {
+                let node = node.node();
+                if let Some(ref replacement) = node.deprecated {
+                    found.push(Deprecation {
+                        path: node.path.clone(),
+                        parameter,
+                        replacement: replacement.clone(),
+                        uses: node.usage.get(),
+                    });
+                }
+            };
+            found_in(successor, false);
+            for parameter in &command.parameters {
+                found_in(parameter, true);
+            }
+            deprecations_within(successor, found);
+        }
+    }
+}
+
+/// A deprecated command or parameter, as listed by [`deprecations`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Deprecation {
+    /// The full path of the command, or of the command followed by
+    /// the name of the parameter.
+    pub path: String,
+    /// Whether this is a parameter rather than a command.
+    pub parameter: bool,
+    /// What to use instead, as given to `deprecated`.
+    pub replacement: String,
+    /// The number of times that it has been part of an executed
+    /// command.
+    pub uses: u32,
+}
+
+impl Deprecation {
+    /// Whether or not it has been used.
+    pub fn is_used(&self) -> bool {
+        self.uses > 0
+    }
+}
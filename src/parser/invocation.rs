@@ -0,0 +1,413 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::constants::ParameterKind;
+use super::nodes::{Node, NodeOps};
+use super::output::CommandOutput;
+use super::{ExecuteError, Parser, VerifyError};
+use crate::tokenizer::{quote, unquote, SourceLocation, SourceOffset, Token, TokenType};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// A command invocation given by the path of the command and the
+/// values of its parameters rather than as text, for when a menu
+/// item, key binding or button runs a command that can also be
+/// typed.
+///
+/// The command and parameter names must be given in full. The values
+/// are checked as they would be when typed, so they must be valid
+/// for the `ValueType` and `choices` of their parameters, and the
+/// handler sees the same `ParsedParameters` either way. There is no
+/// need to quote values which contain spaces.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, InvocationBuilder, Parameter, ParameterKind};
+/// use commands::parser::{Parser, ValueType};
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("interface set")
+///         .parameter(Parameter::new("name").required(true))
+///         .parameter(Parameter::new("mtu").kind(ParameterKind::Named).value_type(ValueType::Integer))
+///         .parameter(Parameter::new("up").kind(ParameterKind::Flag)),
+/// );
+/// let root = tree.finalize();
+///
+/// let invocation = InvocationBuilder::new("interface set")
+///     .parameter("name", "eth 0")
+///     .parameter("mtu", 1500)
+///     .flag("up");
+/// let mut parser = Parser::new(root);
+/// invocation.parse(&mut parser).unwrap();
+/// assert_eq!(parser.canonical_line(), "interface set \"eth 0\" mtu 1500 up");
+/// assert_eq!(parser.parameters().value("name").unwrap(), "eth 0");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct InvocationBuilder {
+    path: String,
+    arguments: Vec<(String, Option<String>)>,
+}
+
+impl InvocationBuilder {
+    /// Construct an invocation of the command with the given `path`,
+    /// like `"show interface"`.
+    pub fn new(path: &str) -> Self {
+        InvocationBuilder {
+            path: path.to_string(),
+            arguments: vec![],
+        }
+    }
+
+    /// Give a `value` for the simple or named parameter with the
+    /// given `name`. A repeatable parameter may be given more than
+    /// once.
+    pub fn parameter<V: ToString>(mut self, name: &str, value: V) -> Self {
+        self.arguments
+            .push((name.to_string(), Some(quote_value(&value.to_string()))));
+        self
+    }
+
+    /// Set the flag with the given `name`.
+    pub fn flag(mut self, name: &str) -> Self {
+        self.arguments.push((name.to_string(), None));
+        self
+    }
+
+    /// Accept the command and its parameters into the `parser`, as
+    /// [`Parser::parse`] would for the equivalent line. The parser
+    /// can then be verified and executed, or turned into a
+    /// `CommandInvocation`.
+    ///
+    /// The parameters are accepted in the order that the command
+    /// declares them, so the order they were given in doesn't
+    /// matter.
+    pub fn parse<'a>(&'a self, parser: &mut Parser<'a>) -> Result<(), InvocationError> {
+        for word in self.path.split_whitespace() {
+            let successors = parser.successors();
+            let node = successors
+                .iter()
+                .find(|n| matches!(***n, Node::Command(ref c) if c.node.name == word))
+                .cloned()
+                .ok_or_else(|| InvocationError::UnknownCommand(self.path.clone()))?;
+            drop(successors);
+            parser
+                .accept_node(word_token(word), &node)
+                .map_err(|_| InvocationError::UnknownCommand(self.path.clone()))?;
+        }
+        let command = match parser.commands.last() {
+            Some(node) if Rc::ptr_eq(node, &parser.current_node) => Rc::clone(node),
+            _ => return Err(InvocationError::UnknownCommand(self.path.clone())),
+        };
+        let command = match *command {
+            Node::Command(ref command) => command,
+            _ => return Err(InvocationError::UnknownCommand(self.path.clone())),
+        };
+        let mut arguments = vec![];
+        for (name, value) in &self.arguments {
+            let (position, node) = command
+                .parameters
+                .iter()
+                .enumerate()
+                .find(|(_, node)| match ***node {
+                    Node::Parameter(ref p) => !p.negation && p.node.name == *name,
+                    ref other => other.node().name == *name,
+                })
+                .ok_or_else(|| InvocationError::UnknownParameter(name.clone()))?;
+            // A tree assembled from nodes rather than built with a
+            // `CommandTree` may have other nodes among the parameters.
+            let param = match **node {
+                Node::Parameter(ref param) => param,
+                _ => {
+                    let path = node.node().path.clone();
+                    return Err(InvocationError::Internal(path));
+                }
+            };
+            arguments.push((position, node, param, name, value));
+        }
+        arguments.sort_by_key(|(position, ..)| *position);
+        for (_, node, param, name, value) in arguments {
+            if !node.acceptable(parser, node) {
+                return Err(InvocationError::AlreadySpecified(name.clone()));
+            }
+            let text = match (param.kind, value) {
+                (ParameterKind::Flag, None) => name.as_str(),
+                (ParameterKind::Flag, Some(_)) | (_, None) => {
+                    return Err(InvocationError::InvalidValue(name.clone()))
+                }
+                (_, Some(value)) => {
+                    if param
+                        .detect(&unquote(value, false), &parser.config)
+                        .is_none()
+                    {
+                        return Err(InvocationError::InvalidValue(name.clone()));
+                    }
+                    value.as_str()
+                }
+            };
+            if param.kind == ParameterKind::Named {
+                let name_node = command.node.successors.iter().find(|n| {
+                    matches!(***n, Node::ParameterName(ref n) if Rc::ptr_eq(&n.parameter, node))
+                });
+                if let Some(name_node) = name_node {
+                    parser
+                        .accept_node(word_token(name), name_node)
+                        .map_err(|_| InvocationError::AlreadySpecified(name.clone()))?;
+                }
+            }
+            parser
+                .accept_node(word_token(text), node)
+                .map_err(|_| InvocationError::InvalidValue(name.clone()))?;
+        }
+        Ok(())
+    }
+
+    /// The command line which is equivalent to the invocation, for
+    /// showing what a menu item or button does or copying it as a
+    /// command. Values are quoted where needed so that the line
+    /// parses back to the same invocation.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, InvocationBuilder, Parameter, ParameterKind};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("commit")
+    ///         .parameter(Parameter::new("all").kind(ParameterKind::Flag))
+    ///         .parameter(Parameter::new("message").kind(ParameterKind::Named)),
+    /// );
+    /// let root = tree.finalize();
+    ///
+    /// let invocation = InvocationBuilder::new("commit")
+    ///     .parameter("message", "Fix \"quoting\"")
+    ///     .flag("all");
+    /// assert_eq!(
+    ///     invocation.to_line(&root).unwrap(),
+    ///     "commit all message \"Fix \\\"quoting\\\"\"",
+    /// );
+    /// ```
+    pub fn to_line(&self, root: &Rc<Node>) -> Result<String, InvocationError> {
+        let mut parser = Parser::new(Rc::clone(root));
+        self.parse(&mut parser)?;
+        Ok(parser.canonical_line())
+    }
+
+    /// Accept the invocation into a new parser for the tree with the
+    /// given `root`, verify it and execute the command.
+    pub fn execute(&self, root: &Rc<Node>) -> Result<CommandOutput, InvocationError> {
+        let mut parser = Parser::new(Rc::clone(root));
+        self.parse(&mut parser)?;
+        parser.verify().map_err(InvocationError::Verify)?;
+        parser.execute().map_err(InvocationError::Execute)
+    }
+}
+
+/// Write `value` as a single word, in double quotes if it is empty,
+/// has characters which would otherwise need escaping or has pipe,
+/// separator, help or comment characters, which would otherwise be
+/// tokens of their own.
+fn quote_value(value: &str) -> String {
+    let special = value.contains(['|', ';', '#']) || value.starts_with('?');
+    if !value.is_empty() && !special && quote(value, None, false) == value {
+        value.to_string()
+    } else {
+        quote(value, Some('"'), true)
+    }
+}
+
+/// A token for `text` which wasn't typed, so it has no location.
+fn word_token(text: &str) -> Token<'_> {
+    let offset = SourceOffset::new(0, 0, 0, 0);
+    Token::new(text, TokenType::Word, SourceLocation::new(offset, offset))
+}
+
+/// Errors that an [`InvocationBuilder`] can raise.
+#[derive(Debug)]
+pub enum InvocationError {
+    /// There is no command with the given path.
+    UnknownCommand(String),
+    /// The command has no parameter with the given name.
+    UnknownParameter(String),
+    /// The value given for the named parameter is not valid for it,
+    /// a flag was given a value or a parameter was given without
+    /// one.
+    InvalidValue(String),
+    /// The named parameter was given more than once, but isn't
+    /// repeatable.
+    AlreadySpecified(String),
+    /// The invocation failed verification.
+    Verify(VerifyError),
+    /// Executing the command failed.
+    Execute(ExecuteError),
+    /// The command tree is malformed, as when a node which isn't a
+    /// parameter is among the parameters of a command. This has the
+    /// path of the node.
+    Internal(String),
+}
+
+impl Error for InvocationError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            InvocationError::Verify(ref err) => Some(err),
+            InvocationError::Execute(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for InvocationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            InvocationError::UnknownCommand(_) => "Unknown command.",
+            InvocationError::UnknownParameter(_) => "Unknown parameter.",
+            InvocationError::InvalidValue(_) => "Invalid value.",
+            InvocationError::AlreadySpecified(_) => "Already specified.",
+            InvocationError::Verify(_) => "Verification failed.",
+            InvocationError::Execute(_) => "Execution failed.",
+            InvocationError::Internal(_) => "Internal error: the command tree is malformed.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{
+        Command, CommandNode, CommandTree, Hidden, Parameter, Priority, RootNode, ValueType,
+    };
+
+    #[test]
+    fn invocations() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("host").required(true))
+                .parameter(
+                    Parameter::new("count")
+                        .kind(ParameterKind::Named)
+                        .value_type(ValueType::Integer),
+                )
+                .parameter(Parameter::new("quiet").kind(ParameterKind::Flag))
+                .handler(|context| {
+                    let host = context.parameter("host").unwrap_or("");
+                    let count = context.parameter("count").unwrap_or("");
+                    context.output().line(format!("{host} {count}"));
+                    Ok(())
+                }),
+        );
+        let root = tree.finalize();
+
+        let output = InvocationBuilder::new("ping")
+            .parameter("count", 3)
+            .parameter("host", "a \"b\"")
+            .execute(&root)
+            .unwrap();
+        assert_eq!(output.lines(), ["a \"b\" 3"]);
+
+        let err = |invocation: InvocationBuilder| invocation.execute(&root).unwrap_err();
+        assert!(matches!(
+            err(InvocationBuilder::new("pin")),
+            InvocationError::UnknownCommand(_)
+        ));
+        assert!(matches!(
+            err(InvocationBuilder::new("ping").parameter("size", 1)),
+            InvocationError::UnknownParameter(_)
+        ));
+        assert!(matches!(
+            err(InvocationBuilder::new("ping").parameter("count", "x")),
+            InvocationError::InvalidValue(_)
+        ));
+        assert!(matches!(
+            err(InvocationBuilder::new("ping").parameter("quiet", "x")),
+            InvocationError::InvalidValue(_)
+        ));
+        assert!(matches!(
+            err(InvocationBuilder::new("ping").flag("quiet").flag("quiet")),
+            InvocationError::AlreadySpecified(_)
+        ));
+        assert!(matches!(
+            err(InvocationBuilder::new("ping").flag("quiet")),
+            InvocationError::Verify(_)
+        ));
+    }
+
+    #[test]
+    fn malformed_trees() {
+        // A command whose parameters include a command.
+        let text = Rc::new(Node::Command(CommandNode::new(
+            "text",
+            None,
+            Hidden::Never,
+            Priority::command(),
+            vec![],
+            None,
+            vec![],
+        )));
+        let echo = Rc::new(Node::Command(CommandNode::new(
+            "echo",
+            None,
+            Hidden::Never,
+            Priority::command(),
+            vec![],
+            None,
+            vec![text],
+        )));
+        let root = Rc::new(Node::Root(RootNode::new(vec![echo])));
+        match InvocationBuilder::new("echo")
+            .parameter("text", "hi")
+            .execute(&root)
+        {
+            Err(err @ InvocationError::Internal(_)) => {
+                assert_eq!(
+                    err.to_string(),
+                    "Internal error: the command tree is malformed."
+                );
+            }
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn lines_parse_back() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("echo").parameter(Parameter::new("text").repeatable(true)));
+        let root = tree.finalize();
+        let values = [
+            "plain",
+            "",
+            "two words",
+            "it's",
+            "back\\slash",
+            "\"quoted\"",
+            "a|b",
+            "a;b",
+            "?",
+            "#1",
+            "a?",
+        ];
+        let invocation = values.iter().fold(InvocationBuilder::new("echo"), |i, v| {
+            i.parameter("text", v)
+        });
+        let line = invocation.to_line(&root).unwrap();
+        assert_eq!(
+            line,
+            "echo plain \"\" \"two words\" \"it's\" \"back\\\\slash\" \"\\\"quoted\\\"\" \
+             \"a|b\" \"a;b\" \"?\" \"#1\" a?"
+        );
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = crate::tokenizer::tokenize(&line) {
+            parser.parse(tokens).unwrap();
+        } else {
+            panic!("Tokenize failed.")
+        }
+        let parsed = parser.parameters().values("text");
+        assert_eq!(
+            parsed.iter().map(|v| v.as_str()).collect::<Vec<_>>(),
+            values
+        );
+    }
+}
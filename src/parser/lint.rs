@@ -0,0 +1,199 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::constants::ParameterKind;
+use super::nodes::Node;
+use std::fmt;
+use std::rc::Rc;
+
+/// The number of required simple parameters that a command may
+/// have before [`lint`] reports `LintKind::ManyRequiredPositionals`.
+pub const LINT_MAX_REQUIRED_POSITIONALS: usize = 3;
+
+/// Check a finalized command tree for problems which make it harder
+/// to use, even though it works. An application can report these
+/// while it is being developed, or fail a test when there are any.
+///
+/// The findings are given in the order that the nodes are found
+/// while walking the tree from the `root`.
+///
+/// ```
+/// use commands::parser::{lint, Command, CommandTree, LintKind, Parameter};
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show").help("Show information"));
+/// tree.command(Command::new("s").help("Save the configuration"));
+/// tree.command(
+///     Command::new("reload")
+///         .help("Restart the system")
+///         .parameter(Parameter::new("reason").help("Why").hidden(true).required(true)),
+/// );
+///
+/// let findings = lint(&tree.finalize());
+/// assert_eq!(findings.len(), 2);
+/// assert_eq!(findings[0].path, "s");
+/// assert_eq!(findings[0].kind, LintKind::AbbreviationCollision("show".to_string()));
+/// assert_eq!(findings[1].path, "reload reason");
+/// assert_eq!(findings[1].kind, LintKind::HiddenRequired);
+/// ```
+pub fn lint(root: &Rc<Node>) -> Vec<Lint> {
+    let mut findings = vec![];
+    lint_successors(root, &mut findings);
+    findings
+}
+
+fn lint_successors(node: &Rc<Node>, findings: &mut Vec<Lint>) {
+    // The names which can be typed at this point, so abbreviations
+    // of them can collide.
+    let names = node
+        .successors()
+        .iter()
+        .filter(|n| !is_simple_parameter(n))
+        .map(|n| n.node().name.as_str())
+        .collect::<Vec<_>>();
+    for successor in node.successors() {
+        if let Node::Command(_) = **successor {
+            lint_command(successor, &names, findings);
+            lint_successors(successor, findings);
+        }
+    }
+}
+
+fn lint_command(node: &Rc<Node>, names: &[&str], findings: &mut Vec<Lint>) {
+    let command = match **node {
+        Node::Command(ref command) => command,
+        _ => return,
+    };
+    let mut finding = |path: &str, kind| {
+        findings.push(Lint {
+            path: path.to_string(),
+            kind,
+        })
+    };
+    let name = command.node.name.as_str();
+    if name.chars().count() == 1 {
+        if let Some(other) = names.iter().find(|n| **n != name && n.starts_with(name)) {
+            finding(
+                &command.node.path,
+                LintKind::AbbreviationCollision(other.to_string()),
+            );
+        }
+    }
+    if command.node.help_text.is_empty() || command.node.help_text == "Command" {
+        finding(&command.node.path, LintKind::MissingHelp);
+    }
+    let positionals = command
+        .parameters
+        .iter()
+        .filter(|p| is_simple_parameter(p) && is_required(p))
+        .count();
+    if positionals > LINT_MAX_REQUIRED_POSITIONALS {
+        finding(
+            &command.node.path,
+            LintKind::ManyRequiredPositionals(positionals),
+        );
+    }
+    for parameter in &command.parameters {
+        let p = match **parameter {
+            Node::Parameter(ref p) if !p.negation => p,
+            _ => continue,
+        };
+        if p.node.help_text.is_empty()
+            || p.node.help_text == "Parameter"
+            || p.node.help_text == "Flag"
+        {
+            finding(&p.node.path, LintKind::MissingHelp);
+        }
+        if p.node.hidden.may_hide() && p.required {
+            finding(&p.node.path, LintKind::HiddenRequired);
+        }
+    }
+}
+
+fn is_simple_parameter(node: &Rc<Node>) -> bool {
+    matches!(**node, Node::Parameter(ref p) if p.kind == ParameterKind::Simple)
+}
+
+fn is_required(node: &Rc<Node>) -> bool {
+    matches!(**node, Node::Parameter(ref p) if p.required)
+}
+
+/// A problem found by [`lint`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Lint {
+    /// The full path of the command or parameter with the problem.
+    pub path: String,
+    /// What the problem is.
+    pub kind: LintKind,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        write!(f, "{}: {}", self.path, self.kind)
+    }
+}
+
+/// The kinds of problems found by [`lint`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LintKind {
+    /// The command has more than [`LINT_MAX_REQUIRED_POSITIONALS`]
+    /// required simple parameters, which are hard to give in the
+    /// right order. This has the number of them.
+    ManyRequiredPositionals(usize),
+    /// The name of the command is a single character which is also
+    /// how another name at the same point would be abbreviated, so
+    /// that abbreviation can't be used. This has the other name.
+    AbbreviationCollision(String),
+    /// The command or parameter wasn't given any help text.
+    MissingHelp,
+    /// The parameter is hidden, so it isn't completed, but it
+    /// must be given.
+    HiddenRequired,
+}
+
+impl fmt::Display for LintKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            LintKind::ManyRequiredPositionals(_) => "Too many required positional parameters.",
+            LintKind::AbbreviationCollision(_) => "Name collides with an abbreviation.",
+            LintKind::MissingHelp => "No help text was given.",
+            LintKind::HiddenRequired => "A required parameter is hidden.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, Parameter};
+
+    #[test]
+    fn lint_findings() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        tree.command(Command::new("show i").help("Interfaces"));
+        tree.command(Command::new("show ip").help("IP"));
+        let mut add = Command::new("add").help("Add");
+        for name in ["a", "b", "c", "d"] {
+            add = add.parameter(Parameter::new(name).help("Value").required(true));
+        }
+        tree.command(add.parameter(Parameter::new("e")));
+        let findings = lint(&tree.finalize())
+            .into_iter()
+            .map(|l| l.to_string())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            findings,
+            [
+                "show: No help text was given.",
+                "show i: Name collides with an abbreviation.",
+                "add: Too many required positional parameters.",
+                "add e: No help text was given.",
+            ]
+        );
+    }
+}
@@ -30,7 +30,8 @@
 //!
 //! * Simple: Just a value that is present in the command line. For
 //!   example: `show interface eth0` where `eth0` is a simple
-//!   parameter `name` which will have the value `eth0`.
+//!   parameter `name` which will have the value `eth0`. Simple
+//!   parameters are given in the order that they were added.
 //! * Named: A name that precedes the value in the command line. For
 //!   example: `show route src <ip> dst <ip>` where `src <ip>` and
 //!   `dst <ip>` are both named parameters to a command `show route`.
@@ -109,24 +110,74 @@
 //! [`RootNode`]: crate::parser::RootNode
 //! [three kinds of parameters]: crate::parser::ParameterKind
 
+mod availability;
+mod benchmark;
 mod builder;
+mod cache;
 mod completion;
+mod config;
 mod constants;
+mod context;
+mod deprecation;
+mod invocation;
+mod lint;
+mod network;
 mod nodes;
+mod output;
+mod pipeline;
+mod pool;
+mod record;
+mod scoring;
+mod static_tree;
+mod value;
+mod value_type;
 
 // Re-export public API
+pub use self::availability::{
+    Availability, AvailabilityChange, AvailabilityListener, SubscriptionId,
+};
+pub use self::benchmark::{complete_corpus, CompletionReport, CompletionSample};
 pub use self::builder::{Command, CommandTree, Parameter};
-pub use self::completion::{Completion, CompletionOption};
-pub use self::constants::ParameterKind;
-pub use self::constants::{PRIORITY_DEFAULT, PRIORITY_MINIMUM, PRIORITY_PARAMETER};
-pub use self::nodes::{CommandNode, ParameterNameNode, ParameterNode, RootNode};
-pub use self::nodes::{Node, NodeOps, TreeNode};
-
-use crate::tokenizer::{Token, TokenType};
-use std::collections::HashMap;
+pub use self::cache::CompletionCache;
+pub use self::completion::{CommandTemplate, FuzzyMatch, LineCompletion};
+pub use self::completion::{Complete, Completion, CompletionOption, ContextualHelp};
+pub use self::completion::{ParameterPrompt, Placeholder};
+pub use self::config::{NumberFormat, TreeConfig};
+pub use self::constants::Priority;
+pub use self::constants::UNTRUSTED_MAX_BACKTRACKING;
+pub use self::constants::{CommandTag, ExecutionProfile};
+pub use self::constants::{GroupKind, Normalization, ParameterKind};
+pub use self::context::ExecContext;
+pub use self::deprecation::{deprecations, Deprecation};
+pub use self::invocation::{InvocationBuilder, InvocationError};
+pub use self::lint::{lint, Lint, LintKind, LINT_MAX_REQUIRED_POSITIONALS};
+pub use self::network::{MacAddress, RangeList, ValueError};
+pub use self::nodes::HomeDirProvider;
+pub use self::nodes::{CommandHandler, CommandNode, CompletionEpilogue, FallbackHandler};
+pub use self::nodes::{Hidden, HiddenPredicate};
+pub use self::nodes::{Node, NodeOps, ParameterGroup, TreeNode};
+pub use self::nodes::{ParameterNameNode, ParameterNode, RootNode, ValueCompleter};
+pub use self::output::CommandOutput;
+pub use self::pipeline::{execute_pipeline, PipelineError};
+pub use self::pool::ParserPool;
+pub use self::record::{InvocationOutcome, InvocationRecord, InvocationSink, RecordedValue};
+pub use self::scoring::{MatchQuality, ScoreWeights};
+pub use self::static_tree::{StaticCommand, StaticParameter};
+pub use self::value::{FromParsedArguments, ParameterError, ParameterValue, ParsedParameters};
+pub use self::value_type::ValueType;
+
+use self::completion::TRUNCATED_HINT;
+use crate::tokenizer::{quote, tokenize_partial_with, tokenize_with, unquote_partial};
+use crate::tokenizer::{SourceLocation, Token};
+use crate::tokenizer::{TokenType, TokenizerConfig, TokenizerError, Unfinished};
+use crate::util::{edit_distance, longest_common_prefix};
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::error::Error;
 use std::fmt;
 use std::rc::Rc;
+use std::slice;
+use std::time::{Duration, Instant};
 
 /// Command parser
 ///
@@ -153,28 +204,408 @@ use std::rc::Rc;
 ///
 /// [`CommandTree`]: crate::parser::CommandTree
 /// ['RootNode`]: crate::parser::RootNode
+#[derive(Clone)]
 pub struct Parser<'text> {
+    root: Rc<Node>,
     current_node: Rc<Node>,
     /// The nodes which have been accepted during `parse` or `advance`.
     pub nodes: Vec<Rc<Node>>,
     /// The tokens which have been accepted during `parse` or `advance`.
     pub tokens: Vec<Token<'text>>,
     commands: Vec<Rc<Node>>,
-    parameters: HashMap<String, String>,
+    parameters: ParsedParameters,
+    pub(crate) config: Rc<TreeConfig>,
+    profile: Vec<TokenProfile<'text>>,
+    fallback: Option<FallbackHandler>,
+    value_completer: Option<ValueCompleter>,
+    home_dir: Option<HomeDirProvider>,
+    invocation_sink: Option<Rc<dyn InvocationSink>>,
+    execution_profile: Option<Rc<Cell<ExecutionProfile>>>,
+    unmatched: Vec<Token<'text>>,
+    trials: Rc<Cell<usize>>,
 }
 
-impl<'text> Parser<'text> {
-    /// Construct a parser with a root node.
-    pub fn new(initial_node: Rc<Node>) -> Parser<'text> {
+/// Timing and match information recorded for a single token.
+///
+/// These are recorded by a [`Parser`] when `TreeConfig::profile`
+/// is enabled and can be used to find the tokens (and so, the
+/// nodes within a command tree) which are expensive to match.
+#[derive(Clone, Copy, Debug)]
+pub struct TokenProfile<'text> {
+    /// The token which was matched.
+    pub token: Token<'text>,
+    /// The number of successor nodes which were candidates.
+    pub candidates: usize,
+    /// The number of candidates which matched the token.
+    pub matches: usize,
+    /// The time spent finding the matching nodes.
+    pub elapsed: Duration,
+}
+
+/// Configure and construct [`Parser`]s.
+///
+/// By default, a parser uses the configuration and handlers of
+/// the [`RootNode`] that it starts from. A `ParserBuilder` can
+/// replace any of these for the parsers that it builds. It can be
+/// kept and used to cheaply build a parser for each line of input.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, ParserBuilder, TreeConfig};
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show"));
+/// tree.command(Command::new("set"));
+///
+/// let builder = ParserBuilder::new(tree.finalize())
+///     .config(TreeConfig::new().profile(true))
+///     .home_dir(|| Some("/home/user".to_string()));
+///
+/// for line in &["show", "set"] {
+///     let mut parser = builder.build();
+///     if let Ok(tokens) = tokenize(line) {
+///         parser.parse(tokens).unwrap();
+///     }
+///     assert_eq!(parser.profile().unwrap().len(), 1);
+/// }
+/// ```
+///
+/// [`RootNode`]: crate::parser::RootNode
+#[derive(Clone)]
+pub struct ParserBuilder {
+    root: Rc<Node>,
+    config: Option<Rc<TreeConfig>>,
+    fallback: Option<FallbackHandler>,
+    value_completer: Option<ValueCompleter>,
+    home_dir: Option<HomeDirProvider>,
+    invocation_sink: Option<Rc<dyn InvocationSink>>,
+    execution_profile: Option<Rc<Cell<ExecutionProfile>>>,
+}
+
+impl ParserBuilder {
+    /// Construct a builder for parsers starting from `root`.
+    pub fn new(root: Rc<Node>) -> Self {
+        ParserBuilder {
+            root,
+            config: None,
+            fallback: None,
+            value_completer: None,
+            home_dir: None,
+            invocation_sink: None,
+            execution_profile: None,
+        }
+    }
+
+    /// Use `config` instead of the [`TreeConfig`] of the tree.
+    pub fn config(mut self, config: TreeConfig) -> Self {
+        self.config = Some(Rc::new(config));
+        self
+    }
+
+    /// Use `handler` instead of the fallback handler of the tree.
+    /// See [`CommandTree::fallback`].
+    ///
+    /// [`CommandTree::fallback`]: crate::parser::CommandTree::fallback
+    pub fn fallback<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(&[Token]) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        self.fallback = Some(Rc::new(handler));
+        self
+    }
+
+    /// Use `completer` instead of the value completer of the tree.
+    /// See [`CommandTree::value_completer`].
+    ///
+    /// [`CommandTree::value_completer`]: crate::parser::CommandTree::value_completer
+    pub fn value_completer<F>(mut self, completer: F) -> Self
+    where
+        F: Fn(&str) -> Vec<String> + 'static,
+    {
+        self.value_completer = Some(Rc::new(completer));
+        self
+    }
+
+    /// Use `provider` instead of the home directory provider of the
+    /// tree. See [`CommandTree::home_dir`].
+    ///
+    /// [`CommandTree::home_dir`]: crate::parser::CommandTree::home_dir
+    pub fn home_dir<F>(mut self, provider: F) -> Self
+    where
+        F: Fn() -> Option<String> + 'static,
+    {
+        self.home_dir = Some(Rc::new(provider));
+        self
+    }
+
+    /// Use `sink` instead of the invocation sink of the tree.
+    /// See [`CommandTree::invocation_sink`].
+    ///
+    /// [`CommandTree::invocation_sink`]: crate::parser::CommandTree::invocation_sink
+    pub fn invocation_sink<S: InvocationSink + 'static>(mut self, sink: S) -> Self {
+        self.invocation_sink = Some(Rc::new(sink));
+        self
+    }
+
+    /// Use `profile` instead of the execution profile of the tree.
+    /// See [`CommandTree::execution_profile`].
+    ///
+    /// [`CommandTree::execution_profile`]: crate::parser::CommandTree::execution_profile
+    pub fn execution_profile(mut self, profile: Rc<Cell<ExecutionProfile>>) -> Self {
+        self.execution_profile = Some(profile);
+        self
+    }
+
+    /// Construct a `Parser` with this configuration.
+    pub fn build<'text>(&self) -> Parser<'text> {
+        let (config, fallback, value_completer, home_dir, invocation_sink, execution_profile) =
+            match *self.root {
+                Node::Root(ref root) => (
+                    Rc::clone(&root.config),
+                    root.fallback.clone(),
+                    root.value_completer.clone(),
+                    root.home_dir.clone(),
+                    root.invocation_sink.clone(),
+                    root.execution_profile.clone(),
+                ),
+                _ => (Rc::new(TreeConfig::default()), None, None, None, None, None),
+            };
         Parser {
-            current_node: initial_node,
+            root: Rc::clone(&self.root),
+            current_node: Rc::clone(&self.root),
             nodes: vec![],
             tokens: vec![],
             commands: vec![],
-            parameters: HashMap::new(),
+            parameters: ParsedParameters::new(),
+            config: self.config.clone().unwrap_or(config),
+            profile: vec![],
+            fallback: self.fallback.clone().or(fallback),
+            value_completer: self.value_completer.clone().or(value_completer),
+            home_dir: self.home_dir.clone().or(home_dir),
+            invocation_sink: self.invocation_sink.clone().or(invocation_sink),
+            execution_profile: self.execution_profile.clone().or(execution_profile),
+            unmatched: vec![],
+            trials: Rc::new(Cell::new(0)),
+        }
+    }
+}
+
+impl<'text> Parser<'text> {
+    /// Construct a parser with a root node, using the configuration
+    /// and handlers of the tree. Use a [`ParserBuilder`] to replace
+    /// them.
+    pub fn new(initial_node: Rc<Node>) -> Parser<'text> {
+        ParserBuilder::new(initial_node).build()
+    }
+
+    /// Return to the root node, forgetting everything accepted
+    /// so far, so that the parser can be used for another line
+    /// without being constructed again. The configuration and
+    /// handlers are kept.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show"));
+    /// tree.command(Command::new("set"));
+    ///
+    /// let lines = ["show", "set"];
+    /// let mut parser = Parser::new(tree.finalize());
+    /// for line in &lines {
+    ///     parser.reset();
+    ///     if let Ok(tokens) = tokenize(line) {
+    ///         parser.parse(tokens).unwrap();
+    ///     }
+    ///     assert_eq!(parser.nodes.len(), 1);
+    /// }
+    /// ```
+    pub fn reset(&mut self) {
+        self.current_node = Rc::clone(&self.root);
+        self.nodes.clear();
+        self.tokens.clear();
+        self.commands.clear();
+        self.parameters.clear();
+        self.profile.clear();
+        self.unmatched.clear();
+        self.trials.set(0);
+    }
+
+    /// Reset the parser for text with another lifetime, keeping
+    /// the buffers that it has allocated. The buffers of tokens are
+    /// only kept where [`recycle`] can reuse them.
+    pub(crate) fn recycle<'other>(mut self) -> Parser<'other> {
+        self.reset();
+        Parser {
+            root: self.root,
+            current_node: self.current_node,
+            nodes: self.nodes,
+            tokens: recycle(self.tokens),
+            commands: self.commands,
+            parameters: self.parameters,
+            config: self.config,
+            profile: recycle(self.profile),
+            fallback: self.fallback,
+            value_completer: self.value_completer,
+            home_dir: self.home_dir,
+            invocation_sink: self.invocation_sink,
+            execution_profile: self.execution_profile,
+            unmatched: recycle(self.unmatched),
+            trials: self.trials,
         }
     }
 
+    /// The accepted command and its parameters as a value which
+    /// doesn't depend upon the parser or the text that it parsed,
+    /// so that it can be queued or sent to another thread to be
+    /// dispatched. This is `None` when no command was accepted.
+    ///
+    /// This doesn't `verify` the command first.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// let line = String::from("show int eth0");
+    /// if let Ok(tokens) = tokenize(&line) {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// let invocation = parser.into_invocation().unwrap();
+    /// drop(line);
+    /// assert_eq!(invocation.command_path, "show interface");
+    /// assert_eq!(invocation.parameters.value("name").unwrap().as_str(), "eth0");
+    /// assert_eq!(invocation.tokens, ["show", "int", "eth0"]);
+    /// ```
+    pub fn into_invocation(self) -> Option<CommandInvocation> {
+        let command = self.commands.last()?;
+        Some(CommandInvocation {
+            command_path: command.node().path.clone(),
+            parameters: self.parameters,
+            tokens: self.tokens.iter().map(|t| t.text.to_string()).collect(),
+        })
+    }
+
+    /// The home directory used for path parameters, from the
+    /// provider given to [`CommandTree::home_dir`] or else from
+    /// the `HOME` environment variable.
+    ///
+    /// [`CommandTree::home_dir`]: crate::parser::CommandTree::home_dir
+    pub fn home_dir(&self) -> Option<String> {
+        match self.home_dir {
+            Some(ref provider) => provider(),
+            None => std::env::var("HOME").ok(),
+        }
+    }
+
+    /// The values given for each parameter while parsing.
+    pub fn parameters(&self) -> &ParsedParameters {
+        &self.parameters
+    }
+
+    /// The accepted input in canonical form, with each abbreviated
+    /// command, parameter name or flag expanded to its full name.
+    /// Values are kept as they were given, except that those which
+    /// are among the `TreeConfig::reserved_words` are quoted.
+    ///
+    /// Abbreviations are accepted when they match only one node, as
+    /// with `sh int` for `show interface`, so this is the form to
+    /// record in a history or to echo back.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+    /// tree.command(Command::new("set"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("sh int \"eth 0\"") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// assert_eq!(parser.canonical_line(), "show interface \"eth 0\"");
+    /// ```
+    pub fn canonical_line(&self) -> String {
+        self.nodes
+            .iter()
+            .zip(&self.tokens)
+            .map(|(node, token)| match **node {
+                Node::Parameter(ref p) if p.kind != ParameterKind::Flag => {
+                    if self.config.is_reserved(token.text) {
+                        Cow::Owned(quote(token.text, Some('"'), true))
+                    } else {
+                        Cow::Borrowed(token.text)
+                    }
+                }
+                _ => Cow::Borrowed(node.node().name.as_str()),
+            })
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The [`TokenProfile`]s recorded while parsing, if
+    /// `TreeConfig::profile` is enabled.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser, TreeConfig};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.config(TreeConfig::new().profile(true));
+    /// tree.command(Command::new("show"));
+    /// tree.command(Command::new("set"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("show") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// let profile = parser.profile().unwrap();
+    /// assert_eq!(profile.len(), 1);
+    /// assert_eq!(profile[0].candidates, 2);
+    /// assert_eq!(profile[0].matches, 1);
+    /// ```
+    pub fn profile(&self) -> Option<&[TokenProfile<'text>]> {
+        if self.config.profile {
+            Some(&self.profile)
+        } else {
+            None
+        }
+    }
+
+    /// The possible successors of the current node.
+    ///
+    /// Once a parameter value has been accepted, parsing continues
+    /// with the successors of the command that it belongs to, so
+    /// that further parameters can be supplied.
+    fn successors(&self) -> Cow<'_, [Rc<Node>]> {
+        let mut node = &self.current_node;
+        if let Node::Parameter(_) = **node {
+            if let Some(command) = self
+                .nodes
+                .iter()
+                .rev()
+                .find(|n| matches!(***n, Node::Command(_)))
+            {
+                node = command;
+            }
+        }
+        // A command which wraps a subtree is followed by its own
+        // parameters and the successors of the wrapped node.
+        if let Node::Command(ref command) = **node {
+            if let Some(wrapped) = command.wrapped() {
+                let mut successors = node.successors().clone();
+                successors.extend(wrapped.successors().iter().cloned());
+                return Cow::Owned(successors);
+            }
+        }
+        Cow::Borrowed(node.successors())
+    }
+
     /// Given an optional token, get the possible valid completions
     /// for the current parser state.
     ///
@@ -182,14 +613,36 @@ impl<'text> Parser<'text> {
     /// are not `hidden`, are `acceptable`, and which match the token,
     /// if one has been provided.
     ///
-    /// Nodes may customize the `Complete` trait to customize the
-    /// [`Completion`] and [`CompletionOption`]s which are generated
-    /// for that node.
+    /// Named and simple parameters also complete to the values from
+    /// [`CommandTree::value_completer`].
+    ///
+    /// [`CommandTree::value_completer`]: crate::parser::CommandTree::value_completer
+    ///
+    /// When a token has been provided, the completions are ordered
+    /// by the score of their node as described by [`ScoreWeights`].
+    ///
+    /// Nodes may have custom completion logic, implementing the
+    /// [`Complete`] trait, to customize the [`Completion`] and
+    /// [`CompletionOption`]s which are generated for that node. This
+    /// replaces the completion of the node and any values from
+    /// [`CommandTree::value_completer`].
     ///
     /// Each valid successor node will have one [`Completion`] in the
     /// result vector. Each [`Completion`] will have one or more
     /// [`CompletionOption`] for each valid way that the value may be
-    /// entered.
+    /// entered. The [`Completion`]s for required parameters which
+    /// haven't been given are marked as `required`.
+    ///
+    /// The completions from the [`Command::epilogue`] of the accepted
+    /// command follow those of the nodes.
+    ///
+    /// [`Command::epilogue`]: crate::parser::Command::epilogue
+    ///
+    /// The [`Completion`]s are ordered by the priority of their
+    /// nodes, highest first, then by how well their nodes match the
+    /// token, best first, and then alphabetically by the names of
+    /// their nodes. The options within each are ordered as given by
+    /// [`Completion::sort_options`].
     ///
     /// ```
     /// use commands::parser::{Command, CommandTree, Parser};
@@ -227,214 +680,3460 @@ impl<'text> Parser<'text> {
     ///
     /// [`Completion`]: crate::parser::Completion
     /// [`CompletionOption`]: crate::parser::CompletionOption
-    pub fn complete(&self, token: Option<Token<'text>>) -> Vec<Completion> {
-        self.current_node
-            .successors()
+    pub fn complete(&self, token: Option<Token<'text>>) -> Vec<Completion<'text>> {
+        let successors = self.successors();
+        let mut nodes = successors
             .iter()
             .filter(|n| {
                 // To be a possible completion, the node should not be
                 // hidden, it should be acceptable, and if there's a token,
-                // it should be a valid match for the node.
-                !n.node().hidden
-                    && n.acceptable(self, n)
+                // it should be a valid match for the node or the start of
+                // one of its choices.
+                !n.node().hidden.is_hidden(self)
+                    && self.acceptable(n)
                     && if let Some(t) = token {
                         n.matches(self, t)
+                            || matches!(***n, Node::Parameter(ref p)
+                                if p.choices.iter().any(|c| c.starts_with(&*unquote_partial(t.text, t.smart_quoted).0)))
                     } else {
                         true
                     }
             })
-            .map(|n| n.complete(token))
-            .collect::<Vec<_>>()
+            .collect::<Vec<_>>();
+        nodes.sort_by(|a, b| {
+            let score = |n: &Rc<Node>| token.map_or(0, |t| self.score(n, t));
+            b.node()
+                .priority
+                .cmp(&a.node().priority)
+                .then_with(|| score(b).cmp(&score(a)))
+                .then_with(|| a.node().name.cmp(&b.node().name))
+        });
+        nodes
+            .into_iter()
+            .map(|n| {
+                let limit = self.config.max_completion_options;
+                let mut completion = match (&n.node().custom_completion, limit) {
+                    (Some(custom), Some(limit)) => custom.complete_limited(n, token, limit),
+                    _ => n.complete(token),
+                };
+                completion.required = self.missing_required(n);
+                let custom = n.node().custom_completion.is_some();
+                if let (Node::Parameter(ref p), Some(completer)) = (&**n, &self.value_completer) {
+                    if p.kind != ParameterKind::Flag && !custom {
+                        let (text, open) = token.map_or((Cow::Borrowed(""), None), |t| {
+                            unquote_partial(t.text, t.smart_quoted)
+                        });
+                        completion.options.extend(
+                            completer(&text)
+                                .into_iter()
+                                .filter(|v| v.starts_with(&*text))
+                                .map(|v| CompletionOption::new(quote(&v, open, true), true)),
+                        );
+                        completion.sort_options();
+                    }
+                }
+                if let Some(limit) = limit {
+                    completion.truncate(limit, TRUNCATED_HINT);
+                }
+                completion
+            })
+            .chain(self.epilogue(token))
+            .collect()
     }
 
-    /// Parse a vector of tokens, advancing through the
-    /// node hierarchy.
+    /// The completions from the epilogue of the accepted command,
+    /// if all of its required parameters have been given and no
+    /// parameter is waiting for its value.
+    fn epilogue(&self, token: Option<Token<'text>>) -> Vec<Completion<'text>> {
+        let command = match self.commands.last().map(|n| &**n) {
+            Some(Node::Command(command)) => command,
+            _ => return vec![],
+        };
+        let epilogue = match command.epilogue {
+            Some(ref epilogue) => epilogue,
+            None => return vec![],
+        };
+        if matches!(*self.current_node, Node::ParameterName(_)) || !self.prompts().is_empty() {
+            return vec![];
+        }
+        epilogue(&self.parameters)
+            .into_iter()
+            .filter_map(|c| c.rehint(token))
+            .collect()
+    }
+
+    /// Complete `text` fuzzily, as an alternative to [`complete`]
+    /// for interactive frontends. An option matches when the
+    /// characters of `text` appear, in order, within it, ignoring
+    /// case, so that `shint` matches `show interface`.
+    ///
+    /// The options are the names of the nodes which may follow
+    /// the current node, other than parameter values, along with
+    /// the subcommands of commands, written as all of their words.
+    /// They are ordered by their score, best first, and then
+    /// alphabetically.
     ///
     /// ```
     /// use commands::parser::{Command, CommandTree, Parser};
-    /// use commands::tokenizer::tokenize;
     ///
     /// let mut tree = CommandTree::new();
     /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("show ip interface"));
+    /// tree.command(Command::new("set"));
     ///
-    /// let mut parser = Parser::new(tree.finalize());
-    ///
-    /// if let Ok(tokens) = tokenize("show interface") {
-    ///     parser.parse(tokens);
-    /// }
+    /// let parser = Parser::new(tree.finalize());
+    /// let matches = parser.complete_fuzzy("shint");
+    /// assert_eq!(matches.len(), 2);
+    /// assert_eq!(matches[0].option_string, "show interface");
+    /// assert_eq!(matches[1].option_string, "show ip interface");
     /// ```
-    pub fn parse(&mut self, tokens: Vec<Token<'text>>) -> Result<(), ParseError<'text>> {
-        for token in tokens {
-            match token.token_type {
-                TokenType::Whitespace => {}
-                TokenType::Word => self.advance(token)?,
+    ///
+    /// [`complete`]: Parser::complete
+    pub fn complete_fuzzy(&self, text: &str) -> Vec<FuzzyMatch> {
+        let successors = self.successors();
+        let mut candidates = vec![];
+        for node in successors.iter() {
+            if node.node().hidden.is_hidden(self) || !self.acceptable(node) {
+                continue;
+            }
+            match **node {
+                Node::Command(_) => fuzzy_candidates(self, node, String::new(), &mut candidates),
+                Node::ParameterName(_) => candidates.push((node.node().name.clone(), node)),
+                Node::Parameter(ref p) if p.kind == ParameterKind::Flag => {
+                    candidates.push((node.node().name.clone(), node))
+                }
+                _ => {}
             }
         }
-        Ok(())
+        let mut matches = candidates
+            .into_iter()
+            .filter_map(|(option_string, node)| {
+                scoring::fuzzy_score(&option_string, text).map(|score| FuzzyMatch {
+                    option_string,
+                    score,
+                    node: Rc::clone(node),
+                })
+            })
+            .collect::<Vec<_>>();
+        matches.sort_by(|a, b| {
+            b.score
+                .cmp(&a.score)
+                .then_with(|| a.option_string.cmp(&b.option_string))
+        });
+        matches
     }
 
-    /// Parse a single token, advancing through the node hierarchy.
-    pub fn advance(&mut self, token: Token<'text>) -> Result<(), ParseError<'text>> {
-        let matches = self
-            .current_node
-            .successors()
-            .iter()
-            .filter(|n| n.acceptable(self, n) && n.matches(self, token))
-            .cloned()
-            .collect::<Vec<_>>();
-        match matches.len() {
-            1 => {
-                let matching_node = &matches[0];
-                matching_node.accept(self, token, matching_node);
-                self.current_node = Rc::clone(matching_node);
-                self.nodes.push(Rc::clone(matching_node));
-                self.tokens.push(token);
-                Ok(())
+    /// Complete the token at the `cursor`, a byte offset within the
+    /// `line`. The tokens before it are parsed first, so that the
+    /// completions are those which may follow them. The text after
+    /// the cursor is ignored. The parser is reset first, so it can
+    /// complete one line after another. The line is tokenized with
+    /// `TreeConfig::tokenizer`.
+    ///
+    /// The completions are empty when the text before the cursor
+    /// can't be parsed or when the cursor isn't at a character
+    /// boundary.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("show ip"));
+    ///
+    /// let line = "show int";
+    /// let mut parser = Parser::new(tree.finalize());
+    /// let completion = parser.complete_line(line, line.len());
+    /// assert_eq!(completion.replace, 5..8);
+    /// assert_eq!(completion.completions.len(), 1);
+    /// assert_eq!(completion.completions[0].options[0].option_string, "interface");
+    /// ```
+    pub fn complete_line(&mut self, line: &'text str, cursor: usize) -> LineCompletion<'text> {
+        self.reset();
+        let mut completion = LineCompletion {
+            replace: cursor..cursor,
+            completions: vec![],
+        };
+        let before = match line.get(..cursor) {
+            Some(before) => before,
+            None => return completion,
+        };
+        let (mut tokens, unfinished) = tokenize_partial_with(&self.config.tokenizer, before);
+        if let Some(Unfinished::Invalid(_)) = unfinished {
+            return completion;
+        }
+        let partial = match tokens.last() {
+            Some(token) if token.token_type == TokenType::Word => tokens.pop(),
+            _ => None,
+        };
+        let special = |t: &Token| matches!(t.token_type, TokenType::Pipe | TokenType::Separator);
+        if let Some(last) = tokens.iter().rposition(special) {
+            if tokens[last].token_type == TokenType::Pipe {
+                self.enter_filters();
             }
-            0 => Err(ParseError::NoMatches(
-                token,
-                self.current_node
-                    .successors()
-                    .iter()
-                    .filter(|n| n.acceptable(self, n))
-                    .cloned()
-                    .collect::<Vec<_>>(),
-            )),
-            _ => Err(ParseError::AmbiguousMatch(token, matches)),
+            tokens.drain(..=last);
+        }
+        if let Some(token) = partial {
+            completion.replace.start = token.location.start.byte;
         }
+        if self.parse(tokens).is_ok() {
+            completion.completions = self.complete(partial);
+        }
+        completion
     }
 
-    /// Execute the command that has been accepted by the parser.
+    /// The single best continuation of the `line` at the `cursor`,
+    /// suitable for showing after the cursor as ghost text which
+    /// can be accepted with a key.
     ///
-    /// * XXX: This should be returning a Result probably.
-    pub fn execute(&self) {
-        if !self.commands.is_empty() {
-            unimplemented!();
-            // self.commands[0].execute(self)
+    /// When only one option completes the token at the cursor, this
+    /// is the rest of that option. When several do, it is the rest
+    /// of the prefix that they share, as far as it is unambiguous.
+    /// There is no hint unless the cursor is at the end of the line,
+    /// or when there is nothing to add. Without a token, there is
+    /// only a hint when there is just one option.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("show interfaces"));
+    /// let root = tree.finalize();
+    ///
+    /// let hint = |line: &str| Parser::new(root.clone()).hint(line, line.len());
+    /// assert_eq!(hint("sh").as_deref(), Some("ow"));
+    /// assert_eq!(hint("show i").as_deref(), Some("nterface"));
+    /// assert_eq!(hint("show interface"), None);
+    /// ```
+    pub fn hint(&mut self, line: &'text str, cursor: usize) -> Option<String> {
+        if cursor != line.len() {
+            return None;
         }
+        let completion = self.complete_line(line, cursor);
+        let typed = &line[completion.replace.clone()];
+        let mut options = completion
+            .completions
+            .iter()
+            .flat_map(|c| c.options.iter().filter(|o| o.complete))
+            .map(|o| o.option_string.as_str())
+            .collect::<Vec<_>>();
+        options.sort_unstable();
+        options.dedup();
+        let best = match options[..] {
+            [] => return None,
+            [option] => option,
+            _ if typed.is_empty() => return None,
+            _ => longest_common_prefix(&options),
+        };
+        best.strip_prefix(typed)
+            .filter(|rest| !rest.is_empty())
+            .map(str::to_string)
     }
 
-    /// Verify that the parser is in a valid state with
-    /// respect to having accepted a command and all
-    /// required parameters.
-    pub fn verify(&self) -> Result<(), VerifyError> {
-        if let Some(Node::Command(command)) = self.commands.first().map(|n| &**n) {
-            for expected in &command.parameters {
-                if let Node::Parameter(ref param) = **expected {
-                    let name = &param.node.name;
-                    if param.required && !self.parameters.contains_key(name) {
-                        return Err(VerifyError::MissingParameter(name.clone()));
-                    }
-                } else {
-                    unreachable!();
+    /// Prompts for the required parameters of the accepted command
+    /// which haven't been given, in the order that the parameters
+    /// were declared. This is empty when no command has been
+    /// accepted.
+    ///
+    /// After `verify` fails with `VerifyError::MissingParameter`,
+    /// a frontend can use these to ask for each missing value and
+    /// give the answers to [`supply`].
+    ///
+    /// [`supply`]: Parser::supply
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, ParameterKind, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("ping").parameter(
+    ///         Parameter::new("host")
+    ///             .kind(ParameterKind::Named)
+    ///             .required(true)
+    ///             .help("The host to ping"),
+    ///     ),
+    /// );
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("ping") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// assert!(parser.verify().is_err());
+    ///
+    /// for prompt in parser.prompts() {
+    ///     assert_eq!(prompt.completion.help_text, "The host to ping");
+    ///     // Ask the user for the value...
+    ///     let answer = tokenize("example.com").unwrap();
+    ///     parser.supply(&prompt, answer[0]).unwrap();
+    /// }
+    /// assert!(parser.verify().is_ok());
+    /// ```
+    pub fn prompts(&self) -> Vec<ParameterPrompt> {
+        let command = match self.commands.last().map(|n| &**n) {
+            Some(Node::Command(command)) => command,
+            _ => return vec![],
+        };
+        command
+            .parameters
+            .iter()
+            .filter(|n| self.missing_required(n))
+            .map(|n| {
+                let mut completion = n.complete(None);
+                completion.required = true;
+                ParameterPrompt {
+                    name: n.node().name.clone(),
+                    completion,
+                    node: Rc::clone(n),
                 }
-            }
-            Ok(())
-        } else {
-            Err(VerifyError::NoCommandAccepted)
-        }
+            })
+            .collect()
     }
-}
 
-/// Errors that calling `parse` on the `Parser` can raise.
-#[derive(Clone)]
-pub enum ParseError<'text> {
-    /// There were no matches for the token.
-    NoMatches(Token<'text>, Vec<Rc<Node>>),
-    /// There was more than 1 possible match for the token.
-    AmbiguousMatch(Token<'text>, Vec<Rc<Node>>),
-}
+    /// Whether or not the `node` is a required parameter, or the
+    /// name of one, which hasn't been given.
+    fn missing_required(&self, node: &Node) -> bool {
+        match *node {
+            Node::Parameter(ref p) => p.required && !self.parameters.contains(&p.node.name),
+            Node::ParameterName(ref name) => self.missing_required(&name.parameter),
+            _ => false,
+        }
+    }
 
-impl fmt::Debug for ParseError<'_> {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        match *self {
-            ParseError::NoMatches(token, _) => write!(f, "NoMatches({token:?}, ...)"),
-            ParseError::AmbiguousMatch(token, _) => write!(f, "AmbiguousMatch({token:?}, ...)"),
+    /// Give the `value` entered in answer to a `prompt` from
+    /// [`prompts`], as if it had been given on the command line.
+    ///
+    /// `ParseError::NoMatches` is returned when the value isn't
+    /// valid for the parameter or the parameter can't be given.
+    ///
+    /// [`prompts`]: Parser::prompts
+    pub fn supply(
+        &mut self,
+        prompt: &ParameterPrompt,
+        value: Token<'text>,
+    ) -> Result<(), ParseError<'text>> {
+        let node = &prompt.node;
+        let valid = match **node {
+            Node::Parameter(ref p) if p.kind == ParameterKind::Flag => true,
+            Node::Parameter(ref p) => p.detect(&value.value(), &self.config).is_some(),
+            _ => false,
+        };
+        if valid && node.acceptable(self, node) {
+            self.accept_node(value, node)
+        } else {
+            Err(ParseError::NoMatches(value, vec![Rc::clone(node)]))
         }
     }
-}
 
-impl Error for ParseError<'_> {
-    fn description(&self) -> &str {
-        match *self {
-            ParseError::NoMatches(_, _) => "No match.",
-            ParseError::AmbiguousMatch(_, _) => "Ambiguous match.",
+    /// Provide help for a `line` ending with a `?`, which is the key
+    /// used to ask for help while entering a command.
+    ///
+    /// The line before the `?` is parsed and the completions at its
+    /// end are returned. If the `?` directly follows a word, then the
+    /// word is completed. This is `None` when the line doesn't end
+    /// with a `?` or when the `?` is quoted or escaped, in which case
+    /// it is just a part of the line. The parser is reset first, and
+    /// the line is tokenized with `TreeConfig::tokenizer`.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("show ip"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// let help = parser.contextual_help("show i?").unwrap();
+    /// assert_eq!(help.line, "show i");
+    /// assert!(help.suppress_echo);
+    /// assert_eq!(help.completions.len(), 2);
+    /// ```
+    pub fn contextual_help(&mut self, line: &'text str) -> Option<ContextualHelp<'text>> {
+        let line = line.strip_suffix('?')?;
+        let (mut tokens, unfinished) = tokenize_partial_with(&self.config.tokenizer, line);
+        if unfinished.is_some() {
+            return None;
         }
+        let partial = match tokens.last() {
+            Some(token) if token.token_type == TokenType::Word => tokens.pop(),
+            _ => None,
+        };
+        Some(ContextualHelp {
+            line,
+            completions: self.help_completions(tokens, partial),
+            suppress_echo: true,
+        })
     }
-}
+
+    /// The completions for help after the `tokens`, which complete
+    /// the `partial` word directly before the `?`, if there is one.
+    /// These are empty when the tokens can't be parsed. The parser
+    /// is reset first.
+    fn help_completions(
+        &mut self,
+        tokens: Vec<Token<'text>>,
+        partial: Option<Token<'text>>,
+    ) -> Vec<Completion<'text>> {
+        self.reset();
+        match self.parse(tokens) {
+            Ok(()) => self.complete(partial),
+            Err(_) => vec![],
+        }
+    }
+
+    /// Provide help for `tokens` which end with a `?`, as when
+    /// `show ip ?` is entered. This is `None` unless the last token
+    /// other than whitespace is a `TokenType::Help` token or a word
+    /// ending with a `?`.
+    ///
+    /// The tokens before the `?` are parsed and the completions
+    /// which may follow them are returned, with the help text of
+    /// each node. When the `?` directly follows a word, as with
+    /// `show i?`, only the nodes which that word matches are given.
+    /// The completions are empty when the tokens can't be parsed.
+    /// The parser is reset first.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show ip").help("Show IP settings"));
+    /// tree.command(Command::new("show interface").help("Show interfaces"));
+    /// let root = tree.finalize();
+    ///
+    /// if let Ok(tokens) = tokenize("show ?") {
+    ///     let help = Parser::new(root).help(tokens).unwrap();
+    ///     assert_eq!(help.len(), 2);
+    ///     assert_eq!(help[0].help_symbol, "interface");
+    ///     assert_eq!(help[0].help_text, "Show interfaces");
+    /// }
+    /// ```
+    pub fn help(&mut self, mut tokens: Vec<Token<'text>>) -> Option<Vec<Completion<'text>>> {
+        while tokens
+            .last()
+            .is_some_and(|t| t.token_type == TokenType::Whitespace)
+        {
+            tokens.pop();
+        }
+        let last = tokens.pop()?;
+        let partial = match last.token_type {
+            TokenType::Help => None,
+            TokenType::Word => {
+                let text = last.text.strip_suffix('?').filter(|t| !t.is_empty())?;
+                let mut end = last.location.end;
+                end.byte -= text.chars().next_back().map_or(0, char::len_utf8);
+                end.char -= 1;
+                end.column -= 1;
+                let location = SourceLocation::new(last.location.start, end);
+                Some(Token::new(text, TokenType::Word, location))
+            }
+            _ => return None,
+        };
+        Some(self.help_completions(tokens, partial))
+    }
+
+    /// Parse a vector of tokens, advancing through the
+    /// node hierarchy.
+    ///
+    /// A token matches a command, parameter name or flag when it is
+    /// a prefix of the name, so an unambiguous abbreviation like
+    /// `sh int` is accepted as `show interface`. The full nodes are
+    /// recorded in `nodes` and [`canonical_line`] gives the command
+    /// with the abbreviations expanded.
+    ///
+    /// Unlike with [`advance`], the tokens which follow each token
+    /// are known while parsing, so they are used to resolve
+    /// what would otherwise be an ambiguous match:
+    ///
+    /// * A named parameter only matches when there is a value
+    ///   following its name.
+    /// * When more than one node matches a token, nodes after which
+    ///   the following token can't be matched are not considered.
+    /// * When more than one node still matches a token, each of them
+    ///   is tried against the remaining tokens. If only one of them
+    ///   results in a successful parse, that one is used. If several
+    ///   succeed, the one with the highest score is used. Otherwise,
+    ///   `ParseError::AmbiguousMatch` is returned with the nodes which
+    ///   succeeded or, if none did, all of the matching nodes.
+    ///
+    /// Pipes, separators and help requests are never taken as values.
+    /// They result in `ParseError::UnexpectedToken`.
+    ///
+    /// [`advance`]: Parser::advance
+    /// [`canonical_line`]: Parser::canonical_line
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+    /// tree.command(Command::new("show ip"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    ///
+    /// if let Ok(tokens) = tokenize("sh int eth0") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// let names = parser.nodes.iter().map(|n| n.node().name.as_str());
+    /// assert_eq!(names.collect::<Vec<_>>(), ["show", "interface", "name"]);
+    /// ```
+    pub fn parse(&mut self, tokens: Vec<Token<'text>>) -> Result<(), ParseError<'text>> {
+        let mut words = vec![];
+        for token in tokens {
+            match token.token_type {
+                TokenType::Whitespace => {}
+                TokenType::Word | TokenType::HereDocument => words.push(token),
+                TokenType::Pipe | TokenType::Separator | TokenType::Help => {
+                    return Err(ParseError::UnexpectedToken(token));
+                }
+            }
+        }
+        self.trials.set(0);
+        let result = self.parse_words(&words);
+        if let Err(ParseError::NoMatches(_, _)) = result {
+            if self.nodes.is_empty() {
+                self.unmatched = words;
+            }
+        }
+        result
+    }
+
+    /// Tokenize and parse a `line` of input from an untrusted
+    /// source, such as a network client.
+    ///
+    /// The work done is bounded by the limits of the tokenizer
+    /// `config` and by `TreeConfig::max_backtracking`. When these
+    /// are not set, `UNTRUSTED_MAX_INPUT_LENGTH`, `UNTRUSTED_MAX_TOKENS`
+    /// and `UNTRUSTED_MAX_BACKTRACKING` are used instead. Any input
+    /// which can't be parsed within them results in an error rather
+    /// than a panic or excessive work.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, LineError, Parser};
+    /// use commands::tokenizer::{TokenizerConfig, TokenizerError};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show"));
+    ///
+    /// let config = TokenizerConfig::new().max_input_length(16);
+    /// let mut parser = Parser::new(tree.finalize());
+    /// assert!(parser.parse_untrusted("show", &config).is_ok());
+    /// assert!(matches!(
+    ///     parser.parse_untrusted("show show show show", &config),
+    ///     Err(LineError::Tokenize(TokenizerError::InputTooLong(16)))
+    /// ));
+    /// ```
+    pub fn parse_untrusted(
+        &mut self,
+        line: &'text str,
+        config: &TokenizerConfig,
+    ) -> Result<(), LineError<'text>> {
+        let tokens = tokenize_with(&config.bounded(), line).map_err(LineError::Tokenize)?;
+        let result = if self.config.max_backtracking.is_none() {
+            let mut bounded = (*self.config).clone();
+            bounded.max_backtracking = Some(UNTRUSTED_MAX_BACKTRACKING);
+            let config = std::mem::replace(&mut self.config, Rc::new(bounded));
+            let result = self.parse(tokens);
+            self.config = config;
+            result
+        } else {
+            self.parse(tokens)
+        };
+        result.map_err(LineError::Parse)
+    }
+
+    /// Parse the non-whitespace tokens in `words`.
+    fn parse_words(&mut self, words: &[Token<'text>]) -> Result<(), ParseError<'text>> {
+        for (i, token) in words.iter().enumerate() {
+            let rest = &words[i + 1..];
+            match self.advance_with_lookahead(*token, Some(rest)) {
+                Err(ParseError::AmbiguousMatch(token, matches)) => {
+                    return self.backtrack(token, matches, rest);
+                }
+                result => result?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Try each of the `matches` for the `token` against the `rest`
+    /// of the tokens, keeping the state of the only interpretation
+    /// which succeeds.
+    fn backtrack(
+        &mut self,
+        token: Token<'text>,
+        matches: Vec<Rc<Node>>,
+        rest: &[Token<'text>],
+    ) -> Result<(), ParseError<'text>> {
+        let mut successes = vec![];
+        for node in &matches {
+            if let Some(max) = self.config.max_backtracking {
+                if self.trials.get() >= max {
+                    return Err(ParseError::TooComplex(token));
+                }
+            }
+            self.trials.set(self.trials.get() + 1);
+            let mut trial = self.clone();
+            match trial
+                .accept_node(token, node)
+                .and_then(|_| trial.parse_words(rest))
+            {
+                Ok(()) => successes.push((Rc::clone(node), trial)),
+                Err(err @ ParseError::TooComplex(_)) => return Err(err),
+                Err(_) => {}
+            }
+        }
+        if successes.len() > 1 {
+            let best = successes
+                .iter()
+                .map(|(node, _)| self.score(node, token))
+                .max();
+            successes.retain(|(node, _)| Some(self.score(node, token)) == best);
+        }
+        if successes.len() == 1 {
+            if let Some((_, trial)) = successes.pop() {
+                *self = trial;
+                return Ok(());
+            }
+        }
+        if successes.is_empty() {
+            Err(ParseError::AmbiguousMatch(token, matches))
+        } else {
+            let nodes = successes.into_iter().map(|(node, _)| node).collect();
+            Err(ParseError::AmbiguousMatch(token, nodes))
+        }
+    }
+
+    /// Parse a single token, advancing through the node hierarchy.
+    pub fn advance(&mut self, token: Token<'text>) -> Result<(), ParseError<'text>> {
+        self.advance_with_lookahead(token, None)
+    }
+
+    /// Parse a single token, given the tokens which follow it
+    /// (if they are known).
+    fn advance_with_lookahead(
+        &mut self,
+        token: Token<'text>,
+        lookahead: Option<&[Token<'text>]>,
+    ) -> Result<(), ParseError<'text>> {
+        let matches = if self.config.profile {
+            let start = Instant::now();
+            let matches = self.matching_nodes(token, lookahead);
+            self.profile.push(TokenProfile {
+                token,
+                candidates: self.successors().len(),
+                matches: matches.len(),
+                elapsed: self.config.elapsed_since(start),
+            });
+            matches
+        } else {
+            self.matching_nodes(token, lookahead)
+        };
+        match matches.len() {
+            1 => self.accept_node(token, &matches[0]),
+            0 => Err(self.no_match(token)),
+            _ => Err(ParseError::AmbiguousMatch(token, matches)),
+        }
+    }
+
+    /// The error for a `token` which matches no acceptable node. When
+    /// it names a parameter or command which can't be accepted
+    /// because it has already been given, that is reported rather
+    /// than there being no match at all.
+    fn no_match(&self, token: Token<'text>) -> ParseError<'text> {
+        let successors = self.successors();
+        let given = successors.iter().find(|n| {
+            let named = !matches!(***n, Node::Parameter(ref p) if p.kind != ParameterKind::Flag);
+            named && !self.acceptable(n) && n.matches(self, token)
+        });
+        if let Some(node) = given {
+            return ParseError::AlreadySpecified(token, Rc::clone(node));
+        }
+        ParseError::NoMatches(
+            token,
+            successors
+                .iter()
+                .filter(|n| n.acceptable(self, n))
+                .cloned()
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Accept the `node` for the `token` and make it the current node.
+    fn accept_node(
+        &mut self,
+        token: Token<'text>,
+        node: &Rc<Node>,
+    ) -> Result<(), ParseError<'text>> {
+        self.check_repeated_values(token, node)?;
+        node.accept(self, token, node);
+        self.current_node = Rc::clone(node);
+        self.nodes.push(Rc::clone(node));
+        self.tokens.push(token);
+        Ok(())
+    }
+
+    /// The successors of the current node which are acceptable
+    /// and match the `token`.
+    ///
+    /// When the `lookahead` tokens are known, a named parameter
+    /// requires a following value and the next token is used to
+    /// discard matches which can't be followed by it.
+    fn matching_nodes(
+        &self,
+        token: Token<'text>,
+        lookahead: Option<&[Token<'text>]>,
+    ) -> Vec<Rc<Node>> {
+        let matches = self.candidate_nodes(token, lookahead);
+        if matches.len() > 1 {
+            if let Some((next, rest)) = lookahead.and_then(|l| l.split_first()) {
+                let viable = matches
+                    .iter()
+                    .filter(|n| {
+                        let mut trial = self.clone();
+                        trial.accept_node(token, n).is_ok()
+                            && !trial.candidate_nodes(*next, Some(rest)).is_empty()
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>();
+                if !viable.is_empty() {
+                    return viable;
+                }
+            } else if lookahead.is_none() {
+                return self.best_scoring(token, matches);
+            }
+        }
+        matches
+    }
+
+    /// The score of the `node` when matching the `token`, using
+    /// the `TreeConfig::weights`.
+    fn score(&self, node: &Rc<Node>, token: Token<'text>) -> i32 {
+        let weights = &self.config.weights;
+        let quality = node
+            .match_quality(self, token)
+            .map_or(0, |q| weights.quality(q));
+        // Usage depends upon the commands executed before, so it is
+        // left out when the results must be reproducible.
+        let usage = if self.config.deterministic {
+            0
+        } else {
+            i32::try_from(node.node().usage.get()).unwrap_or(i32::MAX)
+        };
+        quality
+            .saturating_add(
+                node.node()
+                    .priority
+                    .value()
+                    .saturating_mul(weights.priority),
+            )
+            .saturating_add(usage.saturating_mul(weights.usage))
+    }
+
+    /// The `nodes` which have the highest score for the `token`.
+    fn best_scoring(&self, token: Token<'text>, mut nodes: Vec<Rc<Node>>) -> Vec<Rc<Node>> {
+        let best = nodes.iter().map(|n| self.score(n, token)).max();
+        nodes.retain(|n| Some(self.score(n, token)) == best);
+        nodes
+    }
+
+    /// The successors of the current node which are acceptable
+    /// and match the `token`, without looking beyond the next token.
+    fn candidate_nodes(
+        &self,
+        token: Token<'text>,
+        lookahead: Option<&[Token<'text>]>,
+    ) -> Vec<Rc<Node>> {
+        let has_value = lookahead.is_none_or(|l| !l.is_empty());
+        self.successors()
+            .iter()
+            .filter(|n| self.acceptable(n) && n.matches(self, token))
+            .filter(|n| has_value || !matches!(***n, Node::ParameterName(_)))
+            .cloned()
+            .collect::<Vec<_>>()
+    }
+
+    /// Whether or not the `node` is acceptable. Simple parameters
+    /// are given in the order in which they were added to their
+    /// command, so one is only acceptable once those before it
+    /// have been accepted.
+    fn acceptable(&self, node: &Rc<Node>) -> bool {
+        if !node.acceptable(self, node) {
+            return false;
+        }
+        if let Node::Parameter(ParameterNode {
+            kind: ParameterKind::Simple,
+            ..
+        }) = **node
+        {
+            return self
+                .successors()
+                .iter()
+                .take_while(|n| !Rc::ptr_eq(n, node))
+                .all(|n| match **n {
+                    Node::Parameter(ref p) if p.kind == ParameterKind::Simple => {
+                        self.nodes.contains(n)
+                    }
+                    _ => true,
+                });
+        }
+        true
+    }
+
+    /// Enforce `TreeConfig::max_repeated_values` before accepting
+    /// another value for a repeatable parameter.
+    fn check_repeated_values(
+        &self,
+        token: Token<'text>,
+        node: &Rc<Node>,
+    ) -> Result<(), ParseError<'text>> {
+        if let (Node::Parameter(ref param), Some(max)) = (&**node, self.config.max_repeated_values)
+        {
+            let count = self.parameters.values(&param.node.name).len();
+            if param.node.repeatable && count >= max {
+                return Err(ParseError::TooManyValues(token, Rc::clone(node)));
+            }
+        }
+        Ok(())
+    }
+
+    /// Execute the command that has been accepted by the parser.
+    ///
+    /// This runs the handler of the last command accepted, with an
+    /// [`ExecContext`] for the tokens and parameters, so that
+    /// for `show interface eth0`, the handler for `show interface`
+    /// is run rather than one for `show`.
+    ///
+    /// The usage count of each accepted node is incremented, which
+    /// raises its score when matching tokens later.
+    ///
+    /// When `parse` failed because the input didn't match any
+    /// command, the fallback handler supplied with
+    /// [`CommandTree::fallback`] is run instead.
+    ///
+    /// [`CommandTree::fallback`]: crate::parser::CommandTree::fallback
+    ///
+    /// The [`CommandOutput`] written by the handler is returned.
+    ///
+    /// An `ExecuteError` is returned when no command has been
+    /// accepted, when the execution profile blocks the command or
+    /// a command that it wraps, when the command has no handler or
+    /// when the handler itself fails.
+    pub fn execute(&self) -> Result<CommandOutput, ExecuteError> {
+        self.execute_with_input(None)
+    }
+
+    /// Execute the command that has been accepted by the parser,
+    /// giving it the `input` from the command before it in a
+    /// pipeline. See [`execute_pipeline`].
+    pub fn execute_with_input(
+        &self,
+        input: Option<&CommandOutput>,
+    ) -> Result<CommandOutput, ExecuteError> {
+        self.execute_with(input, &[])
+    }
+
+    /// Execute the command as with `execute_with_input`, giving it
+    /// the `filters` that its output will pass through.
+    fn execute_with(
+        &self,
+        input: Option<&CommandOutput>,
+        filters: &[CommandInvocation],
+    ) -> Result<CommandOutput, ExecuteError> {
+        let wrapper = self.wrapper();
+        match wrapper.or(self.commands.last()).map(|n| (n, &**n)) {
+            Some((node, Node::Command(command))) => {
+                let profile = self
+                    .execution_profile
+                    .as_ref()
+                    .map_or(ExecutionProfile::Full, |p| p.get());
+                let blocked = self.commands.iter().find_map(|n| match **n {
+                    Node::Command(ref c) if !profile.allows(&c.tags) => Some(c.node.path.clone()),
+                    _ => None,
+                });
+                if let Some(path) = blocked {
+                    return Err(ExecuteError::Blocked(path));
+                }
+                for n in &self.nodes {
+                    let usage = &n.node().usage;
+                    usage.set(usage.get().saturating_add(1));
+                }
+                let handler = command.handler.borrow().clone();
+                match handler {
+                    Some(handler) => {
+                        let context = ExecContext::new(
+                            &self.root,
+                            node,
+                            command,
+                            &self.tokens,
+                            &self.parameters,
+                        );
+                        let context = match wrapper {
+                            Some(wrapper) => {
+                                let wrapped = self.commands.last().filter(|c| *c != wrapper);
+                                // The wrapped command starts with the first command
+                                // accepted after the wrapper's own parameters.
+                                let start =
+                                    self.nodes.iter().position(|n| n == wrapper).and_then(|i| {
+                                        self.nodes
+                                            .iter()
+                                            .skip(i + 1)
+                                            .position(|n| matches!(**n, Node::Command(_)))
+                                            .map(|j| i + 1 + j)
+                                    });
+                                let tokens = start.map_or(&[][..], |s| &self.tokens[s..]);
+                                context.with_wrapped(wrapped, tokens)
+                            }
+                            None => context,
+                        }
+                        .with_input(input)
+                        .with_filters(filters);
+                        let result = handler(&context);
+                        self.record_invocation(command, &result);
+                        result.map_err(ExecuteError::HandlerFailed)?;
+                        Ok(context.into_output())
+                    }
+                    None => Err(ExecuteError::NoHandler(command.node.path.clone())),
+                }
+            }
+            _ => match self.fallback {
+                Some(ref fallback) if !self.unmatched.is_empty() => {
+                    fallback(&self.unmatched).map_err(ExecuteError::HandlerFailed)?;
+                    Ok(CommandOutput::new())
+                }
+                _ => Err(ExecuteError::NoCommandAccepted),
+            },
+        }
+    }
+
+    /// Give a record of the execution of the `command` to the
+    /// invocation sink, if there is one.
+    fn record_invocation(&self, command: &CommandNode, result: &Result<(), Box<dyn Error>>) {
+        let sink = match self.invocation_sink {
+            Some(ref sink) => sink,
+            None => return,
+        };
+        let mut parameters = vec![];
+        for parameter in &command.parameters {
+            let name = &parameter.node().name;
+            // A flag's value is the text it was given as, which may
+            // be abbreviated, so record its name instead.
+            let flag = matches!(**parameter, Node::Parameter(ref p)
+                if p.kind == ParameterKind::Flag && !p.negatable);
+            for value in self.parameters.values(name) {
+                parameters.push(RecordedValue {
+                    name: name.clone(),
+                    value: if flag {
+                        name.clone()
+                    } else {
+                        value.value.clone()
+                    },
+                    value_type: value.value_type,
+                });
+            }
+        }
+        sink.record(&InvocationRecord {
+            path: command.node.path.clone(),
+            line: self.canonical_line(),
+            parameters,
+            outcome: match *result {
+                Ok(()) => InvocationOutcome::Succeeded,
+                Err(ref err) => InvocationOutcome::Failed(err.to_string()),
+            },
+        });
+    }
+
+    /// Continue from the filter commands of the tree, for the input
+    /// after a `|`. When the tree has no filters, its own commands
+    /// are used.
+    fn enter_filters(&mut self) {
+        if let Node::Root(ref root) = *self.root {
+            if let Some(ref filters) = root.filters {
+                self.current_node = Rc::clone(filters);
+            }
+        }
+    }
+
+    /// The first command accepted which wraps other commands. This
+    /// is the command to execute rather than the command it wraps.
+    fn wrapper(&self) -> Option<&Rc<Node>> {
+        self.commands
+            .iter()
+            .find(|n| matches!(***n, Node::Command(ref c) if c.wrapped().is_some()))
+    }
+
+    /// Whether the input parsed so far is a command which can be
+    /// executed as-is or whether more input is required, so that
+    /// a REPL can decide whether Enter should execute the command
+    /// or continue prompting.
+    ///
+    /// More input is required when no command has been given, when
+    /// the command has no handler but has subcommands, when a named
+    /// parameter is missing its value, or when required parameters
+    /// are missing.
+    pub fn input_status(&self) -> InputStatus {
+        if let Node::ParameterName(_) = *self.current_node {
+            return InputStatus::Incomplete;
+        }
+        let wrapper = self.wrapper();
+        match wrapper.or(self.commands.last()).map(|n| &**n) {
+            Some(Node::Command(command)) => {
+                if command.handler.borrow().is_none() && !command.node.successors.is_empty() {
+                    return InputStatus::Incomplete;
+                }
+            }
+            _ => return InputStatus::Incomplete,
+        }
+        match self.verify() {
+            Ok(()) => InputStatus::Complete,
+            Err(err) => {
+                let missing = err.errors().iter().all(|err| match *err {
+                    VerifyError::MissingParameter(_) => true,
+                    VerifyError::GroupViolation(ref group) if group.kind == GroupKind::OneOf => {
+                        !group.names.iter().any(|n| self.parameters.contains(n))
+                    }
+                    _ => false,
+                });
+                if missing {
+                    InputStatus::Incomplete
+                } else {
+                    InputStatus::Invalid
+                }
+            }
+        }
+    }
+
+    /// Parse a `line` which is still being entered and give its
+    /// [`InputStatus`].
+    ///
+    /// The parser is reset first, so it can check the line again as
+    /// it is edited. The line is tokenized with `TreeConfig::tokenizer`.
+    /// A line ending within a quote, or within a here document when
+    /// the tokenizer config enables them, or with a backslash
+    /// escaping a character or continuing the line onto the next,
+    /// requires more input. As the line may end
+    /// with the name of a named parameter, the last word is accepted
+    /// without the value that would normally follow it.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, InputStatus, Parameter, ParameterKind, Parser};
+    /// use commands::parser::ValueType;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("ping")
+    ///         .handler(|_| Ok(()))
+    ///         .parameter(Parameter::new("count").kind(ParameterKind::Named))
+    ///         .parameter(Parameter::new("host").required(true).value_type(ValueType::Ipv4)),
+    /// );
+    /// let root = tree.finalize();
+    ///
+    /// let status = |line| Parser::new(root.clone()).line_status(line);
+    /// assert_eq!(status("ping 10.0.0.1"), InputStatus::Complete);
+    /// assert_eq!(status("ping"), InputStatus::Incomplete);
+    /// assert_eq!(status("ping count"), InputStatus::Incomplete);
+    /// assert_eq!(status("ping \"local"), InputStatus::Incomplete);
+    /// assert_eq!(status("ping \\\n"), InputStatus::Incomplete);
+    /// assert_eq!(status("ping \\\n  10.0.0.1"), InputStatus::Complete);
+    /// assert_eq!(status("pong"), InputStatus::Invalid);
+    /// ```
+    pub fn line_status(&mut self, line: &'text str) -> InputStatus {
+        self.reset();
+        let (mut tokens, unfinished) = tokenize_partial_with(&self.config.tokenizer, line);
+        match unfinished {
+            Some(Unfinished::Invalid(_)) => return InputStatus::Invalid,
+            Some(_) => return InputStatus::Incomplete,
+            None => {}
+        }
+        let start = self.clone();
+        if self.parse(tokens.clone()).is_err() {
+            *self = start;
+            while tokens
+                .last()
+                .is_some_and(|t| t.token_type == TokenType::Whitespace)
+            {
+                tokens.pop();
+            }
+            let accepted = match tokens.pop() {
+                Some(last) => self.parse(tokens).is_ok() && self.advance(last).is_ok(),
+                None => false,
+            };
+            if !accepted {
+                return InputStatus::Invalid;
+            }
+        }
+        self.input_status()
+    }
+
+    /// Verify that the parser is in a valid state with
+    /// respect to having accepted a command and all
+    /// required parameters, and that the values of the
+    /// parameters are valid for their `ValueType` and
+    /// `choices`, and that no group of parameters is violated.
+    ///
+    /// Every problem is reported: when there is more than one,
+    /// they are returned together as `VerifyError::Multiple`.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter, Parser, VerifyError};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(
+    ///     Command::new("ping")
+    ///         .parameter(Parameter::new("host").required(true))
+    ///         .parameter(Parameter::new("count").required(true)),
+    /// );
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("ping") {
+    ///     parser.parse(tokens).unwrap();
+    /// }
+    /// let err = parser.verify().unwrap_err();
+    /// assert_eq!(err.errors().len(), 2);
+    /// ```
+    pub fn verify(&self) -> Result<(), VerifyError> {
+        let mut errors = vec![];
+        if let Some(Node::Command(command)) = self.commands.last().map(|n| &**n) {
+            for expected in &command.parameters {
+                if let Node::Parameter(ref param) = **expected {
+                    let name = &param.node.name;
+                    if param.required && !self.parameters.contains(name) {
+                        errors.push(VerifyError::MissingParameter(name.clone()));
+                    }
+                    if param.kind != ParameterKind::Flag
+                        && !self
+                            .parameters
+                            .values(name)
+                            .iter()
+                            .all(|v| param.accepts(&v.value))
+                    {
+                        errors.push(VerifyError::InvalidValue(name.clone()));
+                    }
+                }
+            }
+            for group in command.groups.iter().filter(|g| !g.check(&self.parameters)) {
+                errors.push(VerifyError::GroupViolation(group.clone()));
+            }
+        } else {
+            errors.push(VerifyError::NoCommandAccepted);
+        }
+        match errors.len() {
+            0 => Ok(()),
+            1 => Err(errors.remove(0)),
+            _ => Err(VerifyError::Multiple(errors)),
+        }
+    }
+}
+
+/// Convert an empty vector to one of another type. As the types
+/// that this is used for differ only in lifetimes, the standard
+/// library currently collects in place and reuses the allocation,
+/// but it doesn't promise to, so this is only best-effort.
+fn recycle<T, U>(mut values: Vec<T>) -> Vec<U> {
+    values.clear();
+    values.into_iter().filter_map(|_| None).collect()
+}
+
+/// A command accepted by a parser along with its parameters, as
+/// returned by [`Parser::into_invocation`].
+#[derive(Clone, Debug)]
+pub struct CommandInvocation {
+    /// The path of the command, like `show interface`.
+    pub command_path: String,
+    /// The values given for each parameter.
+    pub parameters: ParsedParameters,
+    /// The text of each token that was accepted, as it was given.
+    pub tokens: Vec<String>,
+}
+
+/// Whether a command line is complete, as given by
+/// [`Parser::input_status`].
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum InputStatus {
+    /// The command can be executed as-is.
+    Complete,
+    /// More input is required before the command can be executed.
+    Incomplete,
+    /// The input is not valid and more input won't fix that.
+    Invalid,
+}
+
+/// Add the words leading to the command `node` and to each of its
+/// visible subcommands to the `candidates` for fuzzy completion.
+fn fuzzy_candidates<'n>(
+    parser: &Parser,
+    node: &'n Rc<Node>,
+    prefix: String,
+    candidates: &mut Vec<(String, &'n Rc<Node>)>,
+) {
+    let words = prefix + &node.node().name;
+    for successor in node.successors() {
+        if let Node::Command(_) = **successor {
+            if !successor.node().hidden.is_hidden(parser) {
+                fuzzy_candidates(parser, successor, words.clone() + " ", candidates);
+            }
+        }
+    }
+    candidates.push((words, node));
+}
+
+/// Errors that calling `parse` on the `Parser` can raise.
+#[derive(Clone)]
+pub enum ParseError<'text> {
+    /// There were no matches for the token.
+    NoMatches(Token<'text>, Vec<Rc<Node>>),
+    /// The token names a parameter (or command) which has already
+    /// been given and can't be given again.
+    AlreadySpecified(Token<'text>, Rc<Node>),
+    /// There was more than 1 possible match for the token.
+    AmbiguousMatch(Token<'text>, Vec<Rc<Node>>),
+    /// The token would exceed `TreeConfig::max_repeated_values` for
+    /// the repeatable parameter.
+    TooManyValues(Token<'text>, Rc<Node>),
+    /// Resolving the ambiguous match for the token would exceed
+    /// `TreeConfig::max_backtracking`.
+    TooComplex(Token<'text>),
+    /// The token is a pipe, a separator or a help request, which
+    /// must be handled before parsing, as by [`execute_pipeline`]
+    /// and [`Parser::help`].
+    UnexpectedToken(Token<'text>),
+}
+
+impl<'text> ParseError<'text> {
+    /// The token which could not be parsed.
+    pub fn token(&self) -> Token<'text> {
+        match *self {
+            ParseError::NoMatches(token, _)
+            | ParseError::AlreadySpecified(token, _)
+            | ParseError::AmbiguousMatch(token, _)
+            | ParseError::TooManyValues(token, _)
+            | ParseError::TooComplex(token)
+            | ParseError::UnexpectedToken(token) => token,
+        }
+    }
+
+    /// The location of the token which could not be parsed, so
+    /// that it can be highlighted.
+    pub fn location(&self) -> SourceLocation {
+        self.token().location
+    }
+
+    /// For `NoMatches`, the names of the acceptable commands and
+    /// parameters which are similar to the token, as a "did you
+    /// mean" list. These are ordered with the most similar first.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parser};
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show"));
+    /// tree.command(Command::new("shutdown"));
+    ///
+    /// let mut parser = Parser::new(tree.finalize());
+    /// if let Ok(tokens) = tokenize("shwo") {
+    ///     let err = parser.parse(tokens).unwrap_err();
+    ///     assert_eq!(err.suggestions(), ["show"]);
+    ///     assert_eq!(err.location().start.char, 0);
+    /// }
+    /// ```
+    pub fn suggestions(&self) -> Vec<String> {
+        let (token, nodes) = match *self {
+            ParseError::NoMatches(token, ref nodes) => (token, nodes),
+            _ => return vec![],
+        };
+        let limit = token.text.chars().count().div_ceil(3).max(1);
+        let mut suggestions = nodes
+            .iter()
+            .filter(|n| !n.node().hidden.may_hide())
+            .filter(|n| match ***n {
+                Node::Command(_) | Node::ParameterName(_) => true,
+                Node::Parameter(ref p) => p.kind == ParameterKind::Flag,
+                Node::Root(_) => false,
+            })
+            .map(|n| (edit_distance(token.text, &n.node().name), &n.node().name))
+            .filter(|(distance, _)| *distance <= limit)
+            .collect::<Vec<_>>();
+        suggestions.sort();
+        suggestions.dedup_by(|a, b| a.1 == b.1);
+        suggestions
+            .into_iter()
+            .map(|(_, name)| name.clone())
+            .collect()
+    }
+}
+
+impl fmt::Debug for ParseError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseError::NoMatches(token, _) => write!(f, "NoMatches({token:?}, ...)"),
+            ParseError::AlreadySpecified(token, _) => {
+                write!(f, "AlreadySpecified({token:?}, ...)")
+            }
+            ParseError::AmbiguousMatch(token, _) => write!(f, "AmbiguousMatch({token:?}, ...)"),
+            ParseError::TooManyValues(token, _) => write!(f, "TooManyValues({token:?}, ...)"),
+            ParseError::TooComplex(token) => write!(f, "TooComplex({token:?})"),
+            ParseError::UnexpectedToken(token) => write!(f, "UnexpectedToken({token:?})"),
+        }
+    }
+}
+
+impl Error for ParseError<'_> {}
 
 impl fmt::Display for ParseError<'_> {
     fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.description().fmt(f)
+        match *self {
+            ParseError::NoMatches(_, _) => "No match.",
+            ParseError::AlreadySpecified(_, _) => "Already specified.",
+            ParseError::AmbiguousMatch(_, _) => "Ambiguous match.",
+            ParseError::TooManyValues(_, _) => "Too many values.",
+            ParseError::TooComplex(_) => "The input has too many interpretations.",
+            ParseError::UnexpectedToken(_) => "Unexpected token.",
+        }
+        .fmt(f)
+    }
+}
+
+/// Errors that calling `parse_untrusted` on the `Parser` can raise.
+#[derive(Clone, Debug)]
+pub enum LineError<'text> {
+    /// The line could not be tokenized.
+    Tokenize(TokenizerError),
+    /// The tokens could not be parsed.
+    Parse(ParseError<'text>),
+}
+
+impl Error for LineError<'_> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            LineError::Tokenize(ref err) => Some(err),
+            LineError::Parse(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for LineError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            LineError::Tokenize(ref err) => err.fmt(f),
+            LineError::Parse(ref err) => err.fmt(f),
+        }
+    }
+}
+
+/// Errors that binding a handler to a command can raise.
+#[derive(Clone, Debug)]
+pub enum BindError {
+    /// There is no command with the given path.
+    UnknownPath(String),
+    /// Handlers can only be bound by way of a root node.
+    NotRoot,
+}
+
+impl Error for BindError {}
+
+impl fmt::Display for BindError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            BindError::UnknownPath(_) => "There is no command with that path.",
+            BindError::NotRoot => "Handlers can only be bound from the root node.",
+        }
+        .fmt(f)
+    }
+}
+
+/// Errors that building a command tree with
+/// [`CommandTree::try_finalize`] can raise.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TreeError {
+    /// A command or parameter uses a reserved word as its name.
+    /// This has the path of the command or parameter and the word.
+    ReservedWord(String, String),
+}
+
+impl Error for TreeError {}
+
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            TreeError::ReservedWord(_, _) => "A name is a reserved word.",
+        }
+        .fmt(f)
+    }
+}
+
+/// Errors that calling `verify` on the `Parser` can raise.
+#[derive(Clone, Debug)]
+pub enum VerifyError {
+    /// No command has been accepted by the parser.
+    NoCommandAccepted,
+    /// A required parameter is missing.
+    MissingParameter(String),
+    /// A value of the named parameter is not valid for its
+    /// `ValueType` or not one of its `choices`.
+    InvalidValue(String),
+    /// The parameters given violate a group declared with
+    /// `Command::exclusive` or `Command::one_of`.
+    GroupViolation(ParameterGroup),
+    /// More than one of the above problems was found.
+    Multiple(Vec<VerifyError>),
+}
+
+impl VerifyError {
+    /// The individual problems that this error reports.
+    pub fn errors(&self) -> &[VerifyError] {
+        match *self {
+            VerifyError::Multiple(ref errors) => errors,
+            _ => slice::from_ref(self),
+        }
+    }
+}
+
+impl Error for VerifyError {}
+
+impl fmt::Display for VerifyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            VerifyError::NoCommandAccepted => "No command has been accepted by the parser.",
+            VerifyError::MissingParameter(_) => "A required parameter is missing.",
+            VerifyError::InvalidValue(_) => "A parameter has an invalid value.",
+            VerifyError::GroupViolation(ref group) => match group.kind {
+                GroupKind::Exclusive => "Parameters which exclude each other were given.",
+                GroupKind::OneOf => "Exactly one of a group of parameters is required.",
+            },
+            VerifyError::Multiple(_) => "The command has several problems.",
+        }
+        .fmt(f)
+    }
+}
+
+/// Errors that calling `execute` on the `Parser` can raise.
+#[derive(Debug)]
+pub enum ExecuteError {
+    /// No command has been accepted by the parser.
+    NoCommandAccepted,
+    /// The command with the given path has no handler.
+    NoHandler(String),
+    /// The command with the given path isn't allowed by the
+    /// execution profile.
+    Blocked(String),
+    /// The handler for the command returned an error.
+    HandlerFailed(Box<dyn Error>),
+}
+
+impl Error for ExecuteError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            ExecuteError::HandlerFailed(ref err) => Some(&**err),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for ExecuteError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ExecuteError::NoCommandAccepted => "No command has been accepted by the parser.",
+            ExecuteError::NoHandler(_) => "The command has no handler.",
+            ExecuteError::Blocked(_) => "The command is not allowed by the execution profile.",
+            ExecuteError::HandlerFailed(_) => "The command failed.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::single_match)]
+mod test {
+    use super::*;
+    use crate::tokenizer::tokenize;
+    use std::cell::RefCell;
+
+    #[test]
+    #[should_panic]
+    fn verify_signals_no_command() {
+        let root = CommandTree::new().finalize();
+        let parser = Parser::new(root);
+        match parser.verify() {
+            Err(VerifyError::NoCommandAccepted) => panic!(),
+            _ => {}
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_signals_no_matches() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("h") {
+            match parser.parse(tokens) {
+                Err(ParseError::NoMatches(_, _)) => panic!(),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_signals_ambiguous_match() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        tree.command(Command::new("set"));
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("s") {
+            match parser.parse(tokens) {
+                Err(ParseError::AmbiguousMatch(_, _)) => panic!(),
+                _ => {}
+            }
+        }
+    }
+
+    #[test]
+    fn parse_rejects_special_tokens() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show log").parameter(Parameter::new("filter")));
+        tree.command(Command::new("echo").parameter(Parameter::new("text").repeatable(true)));
+        let root = tree.finalize();
+        for (line, special) in [("show log ?", "?"), ("echo a|b", "|"), ("echo a;b", ";")] {
+            let mut parser = Parser::new(Rc::clone(&root));
+            match tokenize(line) {
+                Ok(tokens) => match parser.parse(tokens) {
+                    Err(ParseError::UnexpectedToken(token)) => assert_eq!(token.text, special),
+                    _ => panic!("Parsed {}.", line),
+                },
+                _ => panic!("Tokenize failed."),
+            }
+            assert!(parser.parameters.names().next().is_none());
+        }
+
+        let mut parser = Parser::new(root);
+        let completion = parser.complete_line("echo a; sh", 10);
+        assert_eq!(completion.completions[0].options[0].option_string, "show");
+    }
+
+    #[test]
+    fn parse_multiple_parameters() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("show")
+                .parameter(Parameter::new("name"))
+                .parameter(Parameter::new("verbose").kind(ParameterKind::Flag)),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("show eth0 verbose") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("name"), vec!["eth0"]);
+            assert!(parser.parameters.contains("verbose"));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_multiple_word_commands() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+        tree.command(Command::new("show route"));
+        tree.command(Command::new("show"));
+        let root = tree.finalize();
+        assert_eq!(root.successors().len(), 1);
+        assert_eq!(root.successors()[0].successors().len(), 2);
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("sh int eth0") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.nodes.len(), 3);
+            assert_eq!(parser.nodes[1].node().name, "interface");
+            assert_eq!(parser.parameters.values("name"), vec!["eth0"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_uses_lookahead() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("route")
+                .parameter(Parameter::new("src").kind(ParameterKind::Named))
+                .parameter(Parameter::new("dst")),
+        );
+        let root = tree.finalize();
+
+        // The named parameter `src` needs a value to match.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("route src") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("dst"), vec!["src"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Only `src` as a name can be followed by another value.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("route src 10.0.0.1") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("src"), vec!["10.0.0.1"]);
+            assert!(!parser.parameters.contains("dst"));
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Without lookahead, the name scores higher than a value.
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("route src") {
+            assert!(parser.advance(tokens[0]).is_ok());
+            assert!(parser.advance(tokens[2]).is_ok());
+            assert!(matches!(*parser.current_node, Node::ParameterName(_)));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn execute_runs_handler() {
+        fn ok(context: &ExecContext) -> Result<(), Box<dyn Error>> {
+            assert_eq!(context.command().node.path, "show interface");
+            assert_eq!(context.tokens().len(), 3);
+            assert_eq!(context.parameter("name"), Some("eth0"));
+            assert!(context.values("missing").is_empty());
+            Ok(())
+        }
+        fn fail(_context: &ExecContext) -> Result<(), Box<dyn Error>> {
+            Err("failed".into())
+        }
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("show interface")
+                .handler(ok)
+                .parameter(Parameter::new("name")),
+        );
+        tree.command(Command::new("show log").handler(fail));
+        tree.command(Command::new("quit"));
+        let executed = Rc::new(Cell::new(false));
+        let flag = Rc::clone(&executed);
+        tree.command(Command::new("run").handler(move |_| {
+            flag.set(true);
+            Ok(())
+        }));
+        let root = tree.finalize();
+
+        let parser = Parser::new(Rc::clone(&root));
+        if let Err(ExecuteError::NoCommandAccepted) = parser.execute() {
+        } else {
+            panic!();
+        }
+
+        let execute = |text| {
+            let mut parser = Parser::new(Rc::clone(&root));
+            match tokenize(text) {
+                Ok(tokens) => assert!(parser.parse(tokens).is_ok()),
+                _ => panic!("Tokenize failed."),
+            }
+            parser.execute()
+        };
+        assert!(execute("show interface eth0").is_ok());
+        assert!(execute("run").is_ok());
+        assert!(executed.get());
+        match execute("show log") {
+            Err(ExecuteError::HandlerFailed(err)) => assert_eq!(err.to_string(), "failed"),
+            _ => panic!(),
+        }
+        match execute("quit") {
+            Err(ExecuteError::NoHandler(path)) => assert_eq!(path, "quit"),
+            _ => panic!(),
+        }
+        match execute("show") {
+            Err(ExecuteError::NoHandler(path)) => assert_eq!(path, "show"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn execute_bound_handler() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface"));
+        tree.command(Command::new("show route"));
+        let root = tree.finalize();
+        assert!(root
+            .bind("show interface", |context| {
+                context.output().line("bound");
+                Ok(())
+            })
+            .is_ok());
+        assert!(root.bind("show", |_| Ok(())).is_ok());
+        assert!(matches!(
+            root.bind("show log", |_| Ok(())),
+            Err(BindError::UnknownPath(_))
+        ));
+        assert!(matches!(
+            root.successors()[0].bind("show", |_| Ok(())),
+            Err(BindError::NotRoot)
+        ));
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("show interface") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.execute().unwrap().lines(), ["bound"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn execute_runs_fallback() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        let seen = Rc::new(RefCell::new(vec![]));
+        let record = Rc::clone(&seen);
+        tree.fallback(move |tokens| {
+            record
+                .borrow_mut()
+                .extend(tokens.iter().map(|t| t.text.to_string()));
+            Ok(())
+        });
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("ls -l") {
+            assert!(parser.parse(tokens).is_err());
+            assert!(parser.execute().is_ok());
+            assert_eq!(*seen.borrow(), vec!["ls", "-l"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // The fallback isn't used once a command has matched.
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("show -l") {
+            assert!(parser.parse(tokens).is_err());
+            assert!(matches!(parser.execute(), Err(ExecuteError::NoHandler(_))));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_simple_parameters_in_order() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("copy")
+                .parameter(Parameter::new("from"))
+                .parameter(Parameter::new("to")),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("copy a b") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("from"), vec!["a"]);
+            assert_eq!(parser.parameters.values("to"), vec!["b"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_typed_values() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("set")
+                .parameter(Parameter::new("count").value_type(ValueType::Integer))
+                .parameter(Parameter::new("enabled").value_type(ValueType::Boolean)),
+        );
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("set 3 Yes") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.get::<i32>("count").unwrap(), 3);
+            assert!(parser.parameters.get::<bool>("enabled").unwrap());
+            assert_eq!(parser.parameters.values("enabled")[0].raw, "Yes");
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("set three") {
+            assert!(matches!(
+                parser.parse(tokens),
+                Err(ParseError::NoMatches(_, _))
+            ));
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("set") {
+            assert!(parser.parse(tokens).is_ok());
+            let completions = parser.complete(None);
+            assert_eq!(completions.len(), 1);
+            assert_eq!(completions[0].help_symbol, "<count:integer>");
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn verify_checks_values() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping").parameter(Parameter::new("host").value_type(ValueType::Ipv4)),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("ping 10.0.0.1") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("host") {
+            parser
+                .parameters
+                .insert("host", ParameterValue::new(tokens[0]));
+            match parser.verify() {
+                Err(VerifyError::InvalidValue(name)) => assert_eq!(name, "host"),
+                _ => panic!(),
+            }
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn execute_wrapping_command() {
+        fn watch(context: &ExecContext) -> Result<(), Box<dyn Error>> {
+            let wrapped = context.wrapped().map(|n| n.node().path.clone());
+            context.output().line(wrapped.unwrap_or_default());
+            context
+                .output()
+                .line(context.wrapped_tokens().len().to_string());
+            Ok(())
+        }
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+        tree.command(Command::new("show log"));
+        tree.command(Command::new("watch").wraps(String::new()).handler(watch));
+        tree.command(
+            Command::new("explain")
+                .wraps("show".to_string())
+                .handler(watch),
+        );
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("watch show interface eth0") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+            let output = parser.execute().unwrap();
+            assert_eq!(output.lines(), ["show interface", "3"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("explain ") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.complete(None).len(), 2);
+            assert_eq!(parser.execute().unwrap().lines(), ["", "0"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("explain show log") {
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_negatable_flags() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("interface")
+                .parameter(
+                    Parameter::new("shutdown")
+                        .kind(ParameterKind::Flag)
+                        .negatable(true),
+                )
+                .parameter(
+                    Parameter::new("debug")
+                        .kind(ParameterKind::Flag)
+                        .negatable(true),
+                )
+                .parameter(Parameter::new("verbose").kind(ParameterKind::Flag)),
+        );
+        let root = tree.finalize();
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("interface no shutdown debug") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("shutdown"), ["false"]);
+            assert_eq!(parser.parameters.values("debug"), ["true"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("interface no shutdown shutdown") {
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("interface no verbose") {
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("interface no ") {
+            let mut parser = Parser::new(Rc::clone(&parser.root));
+            assert!(parser.parse(tokens[..1].to_vec()).is_ok());
+            assert!(parser.advance(tokens[2]).is_ok());
+            assert_eq!(parser.complete(None).len(), 2);
+        }
+    }
+
+    #[test]
+    fn verify_parameter_groups() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("src").kind(ParameterKind::Named))
+                .parameter(Parameter::new("interface").kind(ParameterKind::Named))
+                .parameter(Parameter::new("brief").kind(ParameterKind::Flag))
+                .parameter(Parameter::new("detail").kind(ParameterKind::Flag))
+                .one_of(&["src", "interface"])
+                .exclusive(&["brief", "detail"]),
+        );
+        let root = tree.finalize();
+        let verify = |line| {
+            let mut parser = Parser::new(Rc::clone(&root));
+            match tokenize(line) {
+                Ok(tokens) => {
+                    assert!(parser.parse(tokens).is_ok());
+                    parser.verify().map_err(|err| match err {
+                        VerifyError::GroupViolation(group) => group.kind,
+                        _ => panic!(),
+                    })
+                }
+                Err(_) => panic!("Tokenize failed."),
+            }
+        };
+        assert!(verify("ping src a brief").is_ok());
+        assert_eq!(verify("ping brief"), Err(GroupKind::OneOf));
+        assert_eq!(verify("ping src a interface b"), Err(GroupKind::OneOf));
+        assert_eq!(
+            verify("ping interface b brief detail"),
+            Err(GroupKind::Exclusive)
+        );
+    }
+
+    #[test]
+    fn line_status() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").handler(|_| Ok(())));
+        tree.command(
+            Command::new("show log")
+                .handler(|_| Ok(()))
+                .parameter(Parameter::new("brief").kind(ParameterKind::Flag))
+                .parameter(Parameter::new("detail").kind(ParameterKind::Flag))
+                .exclusive(&["brief", "detail"]),
+        );
+        let root = tree.finalize();
+        let status = |line| Parser::new(Rc::clone(&root)).line_status(line);
+        assert_eq!(status(""), InputStatus::Incomplete);
+        assert_eq!(status("show "), InputStatus::Incomplete);
+        assert_eq!(status("show int"), InputStatus::Complete);
+        assert_eq!(status("show log brief"), InputStatus::Complete);
+        assert_eq!(status("show log brief detail"), InputStatus::Invalid);
+        assert_eq!(status("show log \\!"), InputStatus::Invalid);
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        assert_eq!(parser.line_status("show int"), InputStatus::Complete);
+        assert_eq!(parser.line_status("show int"), InputStatus::Complete);
+        assert_eq!(parser.line_status("show log"), InputStatus::Complete);
+
+        let mut tree = CommandTree::new();
+        let tokenizer = TokenizerConfig::new().here_documents(true);
+        tree.config(TreeConfig::new().tokenizer(tokenizer));
+        tree.command(
+            Command::new("load")
+                .handler(|_| Ok(()))
+                .parameter(Parameter::new("text").required(true)),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        assert_eq!(
+            parser.line_status("load <<EOF\nabc"),
+            InputStatus::Incomplete
+        );
+        assert_eq!(
+            parser.line_status("load <<EOF\nabc\nEOF"),
+            InputStatus::Complete
+        );
+        assert_eq!(status("show <<EOF\nabc"), InputStatus::Invalid);
+    }
+
+    #[test]
+    fn verify_reports_all_problems() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("host").required(true))
+                .parameter(
+                    Parameter::new("count")
+                        .value_type(ValueType::Integer)
+                        .required(true),
+                )
+                .parameter(Parameter::new("ttl").value_type(ValueType::Integer)),
+        );
+        let root = tree.finalize();
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("ping many") {
+            assert!(parser.advance(tokens[0]).is_ok());
+            parser
+                .parameters
+                .insert("ttl", ParameterValue::new(tokens[2]));
+            match parser.verify() {
+                Err(VerifyError::Multiple(errors)) => {
+                    let names = errors
+                        .iter()
+                        .map(|err| match *err {
+                            VerifyError::MissingParameter(ref name) => format!("missing {name}"),
+                            VerifyError::InvalidValue(ref name) => format!("invalid {name}"),
+                            _ => panic!(),
+                        })
+                        .collect::<Vec<_>>();
+                    assert_eq!(names, ["missing host", "missing count", "invalid ttl"]);
+                }
+                _ => panic!(),
+            }
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_normalized_values() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("interface")
+                .parameter(Parameter::new("name").normalize(Normalization::Uppercase))
+                .parameter(
+                    Parameter::new("mtu")
+                        .kind(ParameterKind::Named)
+                        .normalize(Normalization::Trim)
+                        .value_type(ValueType::Integer),
+                ),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("interface eth0 mtu \" 1500 \"") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+            assert_eq!(parser.parameters.get::<String>("name").unwrap(), "ETH0");
+            assert_eq!(parser.parameters.get::<i64>("mtu").unwrap(), 1500);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_value_types() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("clear").parameter(
+                Parameter::new("neighbor")
+                    .kind(ParameterKind::Named)
+                    .value_types(&[ValueType::Ipv4, ValueType::MacAddress]),
+            ),
+        );
+        let root = tree.finalize();
+        let name = &root.lookup("clear").unwrap().successors()[0];
+        assert_eq!(name.node().help_symbol, "neighbor <A.B.C.D>|<H:H:H:H:H:H>");
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("clear neighbor 00:1a:2b:3c:4d:5e") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+            let values = parser.parameters.values("neighbor");
+            assert_eq!(values[0].value_type, Some(ValueType::MacAddress));
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("clear neighbor 10.0.0.1") {
+            assert!(parser.parse(tokens).is_ok());
+            let values = parser.parameters.values("neighbor");
+            assert_eq!(values[0].value_type, Some(ValueType::Ipv4));
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("clear neighbor eth0") {
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_integer_radix() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("chmod")
+                .parameter(Parameter::new("mode").value_type(ValueType::Integer))
+                .parameter(Parameter::new("path")),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("chmod 0o755 /tmp") {
+            assert!(parser.parse(tokens).is_ok());
+            let mode = parser.parameters.value("mode").unwrap();
+            assert_eq!(mode.as_str(), "493");
+            assert_eq!(mode.raw, "0o755");
+            assert_eq!(mode.radix, Some(8));
+            assert_eq!(parser.parameters.value("path").unwrap().radix, None);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_abbreviations() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+        tree.command(Command::new("show international"));
+        tree.command(Command::new("shutdown"));
+        let root = tree.finalize();
+        let parse = |line| {
+            let mut parser = Parser::new(Rc::clone(&root));
+            match tokenize(line) {
+                Ok(tokens) => parser.parse(tokens).map(|_| parser.canonical_line()),
+                Err(_) => panic!("Tokenize failed."),
+            }
+        };
+        assert_eq!(parse("sh interf eth0").unwrap(), "show interface eth0");
+        // Only "interface" takes a parameter.
+        assert_eq!(parse("sh int eth0").unwrap(), "show interface eth0");
+        assert_eq!(parse("show internat").unwrap(), "show international");
+        assert!(matches!(
+            parse("show int"),
+            Err(ParseError::AmbiguousMatch(_, _))
+        ));
+        assert!(matches!(parse("s"), Err(ParseError::AmbiguousMatch(_, _))));
+    }
+
+    #[test]
+    fn prompt_for_missing_parameters() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("host").required(true))
+                .parameter(
+                    Parameter::new("count")
+                        .kind(ParameterKind::Named)
+                        .value_type(ValueType::Integer)
+                        .required(true),
+                )
+                .parameter(Parameter::new("ttl").kind(ParameterKind::Named)),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        assert!(parser.prompts().is_empty());
+        if let Ok(tokens) = tokenize("ping 10.0.0.1") {
+            assert!(parser.parse(tokens).is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let prompts = parser.prompts();
+        assert_eq!(prompts.len(), 1);
+        assert_eq!(prompts[0].name, "count");
+        assert_eq!(prompts[0].completion.help_symbol, "<count:integer>");
+        if let Ok(tokens) = tokenize("many 3") {
+            assert!(matches!(
+                parser.supply(&prompts[0], tokens[0]),
+                Err(ParseError::NoMatches(_, _))
+            ));
+            assert!(parser.supply(&prompts[0], tokens[2]).is_ok());
+            assert!(parser.supply(&prompts[0], tokens[2]).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        assert!(parser.prompts().is_empty());
+        assert!(parser.verify().is_ok());
+        assert_eq!(parser.parameters.get::<i64>("count").unwrap(), 3);
+    }
+
+    #[test]
+    fn parser_builder_overrides() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("cd").parameter(Parameter::new("dir").value_type(ValueType::Path)),
+        );
+        tree.home_dir(|| Some("/home/tree".to_string()));
+        tree.fallback(|_| Err("tree".into()));
+        let root = tree.finalize();
+
+        let parser = Parser::new(Rc::clone(&root));
+        assert_eq!(parser.home_dir().unwrap(), "/home/tree");
+        let builder = ParserBuilder::new(root)
+            .home_dir(|| Some("/home/builder".to_string()))
+            .fallback(|_| Ok(()));
+        let mut parser = builder.build();
+        if let Ok(tokens) = tokenize("cd ~/src") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(
+                parser.parameters.value("dir").unwrap().as_str(),
+                "/home/builder/src"
+            );
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = builder.build();
+        if let Ok(tokens) = tokenize("ls") {
+            assert!(parser.parse(tokens).is_err());
+            assert!(parser.execute().is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn reset_parser() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show").parameter(Parameter::new("what")));
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("show version") {
+            assert!(parser.parse(tokens).is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        parser.reset();
+        assert!(parser.nodes.is_empty());
+        assert!(parser.tokens.is_empty());
+        assert!(!parser.parameters.contains("what"));
+        assert!(matches!(
+            parser.verify(),
+            Err(VerifyError::NoCommandAccepted)
+        ));
+        if let Ok(tokens) = tokenize("show") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
     }
-}
 
-/// Errors that calling `verify` on the `Parser` can raise.
-#[derive(Clone, Debug)]
-pub enum VerifyError {
-    /// No command has been accepted by the parser.
-    NoCommandAccepted,
-    /// A required parameter is missing.
-    MissingParameter(String),
-}
+    #[test]
+    fn into_invocation() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(Parameter::new("host"))
+                .parameter(Parameter::new("count").kind(ParameterKind::Named)),
+        );
+        let root = tree.finalize();
+        assert!(Parser::new(Rc::clone(&root)).into_invocation().is_none());
 
-impl Error for VerifyError {
-    fn description(&self) -> &str {
-        match *self {
-            VerifyError::NoCommandAccepted => "No command has been accepted by the parser.",
-            VerifyError::MissingParameter(_) => "A required parameter is missing.",
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("ping \"10.0.0.1\" count 3") {
+            assert!(parser.parse(tokens).is_ok());
+        } else {
+            panic!("Tokenize failed.");
         }
+        let invocation = parser.into_invocation().unwrap();
+        let invocation = std::thread::spawn(move || invocation).join().unwrap();
+        assert_eq!(invocation.command_path, "ping");
+        assert_eq!(invocation.tokens, ["ping", "\"10.0.0.1\"", "count", "3"]);
+        assert_eq!(
+            invocation.parameters.get::<String>("host").unwrap(),
+            "10.0.0.1"
+        );
+        assert_eq!(invocation.parameters.get::<u32>("count").unwrap(), 3);
     }
-}
 
-impl fmt::Display for VerifyError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.description().fmt(f)
+    #[test]
+    fn parse_untrusted_input() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("set")
+                .parameter(Parameter::new("a").repeatable(true))
+                .parameter(Parameter::new("b").repeatable(true)),
+        );
+        tree.command(
+            Command::new("show interface")
+                .parameter(Parameter::new("name").value_type(ValueType::Integer)),
+        );
+        tree.command(Command::new("shutdown"));
+        let root = tree.finalize();
+        let config = TokenizerConfig::new();
+
+        // Every value could be given for either parameter.
+        let line = "set x x x x x x x x x x x x x x x x x x x x x x x x x x x x x x";
+        let mut parser = Parser::new(Rc::clone(&root));
+        assert!(matches!(
+            parser.parse_untrusted(line, &config),
+            Err(LineError::Parse(ParseError::TooComplex(_)))
+        ));
+
+        // Random input, including quotes, escapes and control characters.
+        let alphabet = [
+            "s", "h", "o", "w", "i", "n", " ", "\t", "\"", "'", "\\", "|", "?", ";", "<<", "\n",
+            "1", "-", "\u{0}", "\u{1b}", "\u{4e2d}", "\u{301}",
+        ];
+        let mut seed = 12345u64;
+        for _ in 0..2000 {
+            let mut line = String::new();
+            for _ in 0..(seed % 24) {
+                seed = seed
+                    .wrapping_mul(6364136223846793005)
+                    .wrapping_add(1442695040888963407);
+                line.push_str(alphabet[(seed >> 33) as usize % alphabet.len()]);
+            }
+            let mut parser = Parser::new(Rc::clone(&root));
+            if parser.parse_untrusted(&line, &config).is_ok() {
+                let _ = parser.verify();
+            }
+            let _ = Parser::new(Rc::clone(&root)).line_status(&line);
+        }
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use crate::tokenizer::tokenize;
+    #[test]
+    fn complete_line() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("show interface")
+                .parameter(Parameter::new("name").choices(&["eth0", "eth1"])),
+        );
+        tree.command(Command::new("show ip"));
+        let root = tree.finalize();
+        let complete = |line, cursor| {
+            let completion = Parser::new(Rc::clone(&root)).complete_line(line, cursor);
+            let options = completion
+                .completions
+                .iter()
+                .flat_map(|c| c.options.iter().map(|o| o.option_string.clone()))
+                .collect::<Vec<_>>();
+            (completion.replace, options)
+        };
+        assert_eq!(complete("", 0), (0..0, vec!["show".to_string()]));
+        assert_eq!(complete("sh", 2), (0..2, vec!["show".to_string()]));
+        assert_eq!(
+            complete("show ", 5),
+            (5..5, vec!["interface".to_string(), "ip".to_string()])
+        );
+        assert_eq!(
+            complete("show ip", 6),
+            (5..6, vec!["interface".to_string(), "ip".to_string()])
+        );
+        assert_eq!(
+            complete("show interface e extra", 16),
+            (
+                15..16,
+                vec!["eth0".to_string(), "eth1".to_string(), "eth".to_string()]
+            )
+        );
+        assert_eq!(complete("\u{4e2d} sh", 6), (4..6, vec![]));
+        assert_eq!(complete("\u{4e2d}", 1), (1..1, vec![]));
+        assert_eq!(complete("show", 10), (10..10, vec![]));
+
+        let mut parser = Parser::new(root);
+        assert_eq!(parser.complete_line("show i", 6).completions.len(), 2);
+        assert_eq!(parser.complete_line("show i", 6).completions.len(), 2);
+        assert_eq!(parser.hint("show in", 7).as_deref(), Some("terface"));
+        assert_eq!(parser.hint("show in", 7).as_deref(), Some("terface"));
+    }
 
     #[test]
-    #[should_panic]
-    fn verify_signals_no_command() {
-        let root = CommandTree::new().finalize();
-        let parser = Parser::new(root);
-        match parser.verify() {
-            Err(VerifyError::NoCommandAccepted) => panic!(),
-            _ => {}
+    fn line_tokenizer_config() {
+        let mut tree = CommandTree::new();
+        let tokenizer = TokenizerConfig::new().separator(',').single_quotes(false);
+        tree.config(TreeConfig::new().tokenizer(tokenizer));
+        tree.command(
+            Command::new("show interface")
+                .parameter(Parameter::new("name"))
+                .handler(|_| Ok(())),
+        );
+        tree.command(Command::new("show ip"));
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        assert_eq!(parser.complete_line("show,", 5).completions.len(), 2);
+        let help = parser.contextual_help("show,?").unwrap();
+        assert_eq!(help.completions.len(), 2);
+        let mut parser = Parser::new(root);
+        assert_eq!(
+            parser.line_status("show,interface it's"),
+            InputStatus::Complete
+        );
+    }
+
+    #[test]
+    fn complete_quoted() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("open").parameter(Parameter::new("file").choices(&[
+                "My Documents",
+                "My Music",
+                "Notes",
+            ])),
+        );
+        let root = tree.finalize();
+        let complete = |line: &str| {
+            let completion = Parser::new(Rc::clone(&root)).complete_line(line, line.len());
+            let options = completion
+                .completions
+                .iter()
+                .flat_map(|c| c.options.iter().map(|o| o.option_string.clone()))
+                .collect::<Vec<_>>();
+            (completion.replace, options)
+        };
+        assert_eq!(
+            complete(r#"open "My D"#),
+            (5..10, vec![r#""My Documents""#.to_string()])
+        );
+        assert_eq!(
+            complete(r"open My\ M"),
+            (5..10, vec![r"My\ Music".to_string()])
+        );
+        assert_eq!(
+            complete("open 'My"),
+            (
+                5..8,
+                vec![
+                    "'My Documents'".to_string(),
+                    "'My Music'".to_string(),
+                    "'My ".to_string()
+                ]
+            )
+        );
+        assert_eq!(
+            complete(r#"open "N"#),
+            (5..7, vec![r#""Notes""#.to_string()])
+        );
+    }
+
+    #[test]
+    fn root_node_ops() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show"));
+        let root = tree.finalize();
+        let parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("show") {
+            assert!(!root.matches(&parser, tokens[0]));
+            assert!(!root.acceptable(&parser, &root));
+            let completion = root.complete(Some(tokens[0]));
+            assert!(completion.exhaustive);
+            assert!(completion.options.is_empty());
+        } else {
+            panic!("Tokenize failed.");
         }
     }
 
     #[test]
-    #[should_panic]
-    fn parse_signals_no_matches() {
+    fn complete_ordering() {
         let mut tree = CommandTree::new();
+        tree.command(Command::new("shutdown"));
         tree.command(Command::new("show"));
+        tree.command(Command::new("sh"));
+        tree.command(Command::new("set").priority(Priority::command().raised(1)));
+        tree.command(
+            Command::new("speed")
+                .parameter(Parameter::new("value").choices(&["100", "10", "1000"])),
+        );
         let mut parser = Parser::new(tree.finalize());
-        if let Ok(tokens) = tokenize("h") {
-            match parser.parse(tokens) {
-                Err(ParseError::NoMatches(_, _)) => panic!(),
-                _ => {}
+        let names = |completions: Vec<Completion>| {
+            completions
+                .iter()
+                .map(|c| c.help_symbol.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(parser.complete(None)),
+            ["set", "sh", "show", "shutdown", "speed"]
+        );
+        if let Ok(tokens) = tokenize("sh") {
+            assert_eq!(
+                names(parser.complete(Some(tokens[0]))),
+                ["sh", "show", "shutdown"]
+            );
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("speed 10") {
+            assert!(parser.parse(tokens[..1].to_vec()).is_ok());
+            let completions = parser.complete(Some(tokens[2]));
+            let options = completions[0]
+                .options
+                .iter()
+                .map(|o| o.option_string.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(options, ["10", "100", "1000"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn command_template() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("copy")
+                .parameter(Parameter::new("source").required(true))
+                .parameter(
+                    Parameter::new("force")
+                        .kind(ParameterKind::Flag)
+                        .negatable(true)
+                        .required(true),
+                )
+                .parameter(
+                    Parameter::new("count")
+                        .value_type(ValueType::Integer)
+                        .required(true),
+                ),
+        );
+        let root = tree.finalize();
+        if let Node::Command(ref command) = **root.lookup("copy").unwrap() {
+            let template = command.template();
+            assert_eq!(template.line, "copy <source> force <count:integer>");
+            let placeholders = template
+                .placeholders
+                .iter()
+                .map(|p| (p.name.as_str(), &template.line[p.range.clone()]))
+                .collect::<Vec<_>>();
+            assert_eq!(
+                placeholders,
+                [("source", "<source>"), ("count", "<count:integer>")]
+            );
+        } else {
+            panic!("Lookup failed.");
+        }
+    }
+
+    #[test]
+    fn custom_completion() {
+        struct Fixed(&'static str);
+
+        impl Complete for Fixed {
+            fn complete<'text>(
+                &self,
+                node: &Node,
+                token: Option<Token<'text>>,
+            ) -> Completion<'text> {
+                Completion::new(
+                    node.node().name.clone(),
+                    String::new(),
+                    token,
+                    true,
+                    &[self.0],
+                    &[],
+                )
+            }
+        }
+
+        let mut tree = CommandTree::new();
+        tree.value_completer(|_| vec!["other".to_string()]);
+        tree.command(Command::new("show").complete_with(Fixed("sh")));
+        tree.command(
+            Command::new("echo").parameter(Parameter::new("text").complete_with(Fixed("hello"))),
+        );
+        let root = tree.finalize();
+        let options = |completions: Vec<Completion>| {
+            completions
+                .iter()
+                .flat_map(|c| c.options.iter().map(|o| o.option_string.clone()))
+                .collect::<Vec<_>>()
+        };
+        let mut parser = Parser::new(Rc::clone(&root));
+        assert_eq!(options(parser.complete(None)), ["echo", "sh"]);
+
+        // Accepting a requoted option leaves a line which parses.
+        let mut tree = CommandTree::new();
+        tree.value_completer(|_| vec!["it's".to_string(), r"a\b".to_string()]);
+        tree.command(Command::new("open").parameter(Parameter::new("name")));
+        let open = tree.finalize();
+        let completion = Parser::new(Rc::clone(&open)).complete_line("open ", 5);
+        let quoted = completion.completions[0]
+            .options
+            .iter()
+            .filter(|o| o.complete)
+            .map(|o| o.option_string.clone())
+            .collect::<Vec<_>>();
+        assert_eq!(quoted, [r#""a\\b""#, r#""it's""#]);
+        for (option, value) in quoted.iter().zip([r"a\b", "it's"]) {
+            let line = format!("open {}", option);
+            let mut parser = Parser::new(Rc::clone(&open));
+            match tokenize(&line) {
+                Ok(tokens) => {
+                    assert!(parser.parse(tokens).is_ok());
+                    assert_eq!(parser.parameters.value("name").unwrap().as_str(), value);
+                }
+                _ => panic!("Tokenize failed."),
             }
         }
+        if let Ok(tokens) = tokenize("echo h") {
+            assert!(parser.parse(tokens[..1].to_vec()).is_ok());
+            assert_eq!(options(parser.complete(Some(tokens[2]))), ["hello"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
     }
 
     #[test]
-    #[should_panic]
-    fn parse_signals_ambiguous_match() {
+    fn truncated_completions() {
+        struct Counted;
+
+        impl Complete for Counted {
+            fn complete<'text>(
+                &self,
+                _node: &Node,
+                _token: Option<Token<'text>>,
+            ) -> Completion<'text> {
+                panic!("Every option was listed.");
+            }
+
+            fn complete_limited<'text>(
+                &self,
+                node: &Node,
+                token: Option<Token<'text>>,
+                limit: usize,
+            ) -> Completion<'text> {
+                let values = (0..limit).map(|i| format!("v{}", i)).collect::<Vec<_>>();
+                let values = values.iter().map(String::as_str).collect::<Vec<_>>();
+                let node = node.node();
+                Completion::new(node.name.clone(), String::new(), token, true, &values, &[])
+                    .truncated(100_000, "Type more.")
+            }
+        }
+
+        let mut tree = CommandTree::new();
+        tree.config(TreeConfig::new().max_completion_options(3));
+        tree.value_completer(|_| vec!["b0".to_string(), "b1".to_string()]);
+        tree.command(
+            Command::new("get")
+                .parameter(
+                    Parameter::new("key").completer(|_| vec!["a".to_string(), "b".to_string()]),
+                )
+                .parameter(Parameter::new("value").complete_with(Counted)),
+        );
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        let completion = parser.complete_line("get ", 4).completions.remove(0);
+        assert_eq!(completion.options.len(), 3);
+        assert_eq!(completion.total_count, Some(5));
+        assert_eq!(completion.hint.as_deref(), Some(TRUNCATED_HINT));
+        let mut parser = Parser::new(Rc::clone(&root));
+        let completion = parser.complete_line("get a", 5).completions.remove(0);
+        assert!(!completion.is_truncated());
+        assert!(completion.hint.is_none());
+
+        let mut parser = Parser::new(root);
+        let completion = parser.complete_line("get a ", 6).completions.remove(0);
+        assert_eq!(completion.options.len(), 3);
+        assert_eq!(completion.total_count, Some(100_000));
+        assert_eq!(completion.hint.as_deref(), Some("Type more."));
+    }
+
+    #[test]
+    fn complete_epilogue() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(
+                    Parameter::new("host")
+                        .kind(ParameterKind::Named)
+                        .required(true),
+                )
+                .epilogue(|parameters| {
+                    assert!(parameters.contains("host"));
+                    vec![Completion::new(
+                        "> <file>".to_string(),
+                        "Save the output".to_string(),
+                        None,
+                        true,
+                        &[">"],
+                        &[],
+                    )]
+                }),
+        );
+        let root = tree.finalize();
+        let symbols = |line: &str| {
+            let completion = Parser::new(Rc::clone(&root)).complete_line(line, line.len());
+            completion
+                .completions
+                .iter()
+                .map(|c| c.help_symbol.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(symbols("ping "), ["host <host>"]);
+        assert_eq!(symbols("ping host a "), ["> <file>"]);
+        assert_eq!(symbols("ping host a >"), ["> <file>"]);
+        assert!(symbols("ping host a x").is_empty());
+    }
+
+    #[test]
+    fn hidden_when() {
+        let support = Rc::new(Cell::new(false));
+        let visible = Rc::clone(&support);
         let mut tree = CommandTree::new();
         tree.command(Command::new("show"));
+        tree.command(Command::new("debug").hidden_when(move |_| !visible.get()));
+        tree.command(Command::new("secret").hidden(true));
+        let root = tree.finalize();
+        let names = || {
+            let completion = Parser::new(Rc::clone(&root)).complete_line("", 0);
+            completion
+                .completions
+                .iter()
+                .map(|c| c.help_symbol.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names(), ["show"]);
+        support.set(true);
+        assert_eq!(names(), ["debug", "show"]);
+        support.set(false);
+        if let Ok(tokens) = tokenize("debug") {
+            let mut parser = Parser::new(Rc::clone(&root));
+            assert!(parser.parse(tokens).is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn static_commands() {
+        static COMMANDS: &[StaticCommand] = &[StaticCommand {
+            name: "set mode",
+            help_text: Some("Set the mode"),
+            hidden: false,
+            priority: Priority::command(),
+            tags: &[],
+            deprecated: None,
+            parameters: &[StaticParameter {
+                name: "mode",
+                help_text: None,
+                hidden: false,
+                priority: None,
+                kind: ParameterKind::Simple,
+                required: true,
+                repeatable: false,
+                aliases: &[],
+                value_types: &[],
+                choices: &["fast", "slow"],
+                negatable: false,
+                normalizations: &[Normalization::Lowercase],
+                deprecated: None,
+            }],
+        }];
+        let mut tree = CommandTree::new();
+        tree.static_commands(COMMANDS);
+        let source = tree.static_source("COMMANDS");
+        assert!(source.contains("        help_text: Some(\"Set the mode\"),\n"));
+        assert!(source.contains("                choices: &[\"fast\", \"slow\"],\n"));
+        assert!(source
+            .contains("                value_types: &[::commands::parser::ValueType::String],\n"));
+        let root = tree.finalize();
+        if let Ok(tokens) = tokenize("set mode FAST") {
+            let mut parser = Parser::new(Rc::clone(&root));
+            parser.parse(tokens).unwrap();
+            assert_eq!(parser.parameters().value("mode").unwrap().as_str(), "fast");
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("set mode medium") {
+            let mut parser = Parser::new(root);
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn static_tags_and_deprecations() {
+        static COMMANDS: &[StaticCommand] = &[StaticCommand {
+            name: "erase config",
+            help_text: None,
+            hidden: false,
+            priority: Priority::command(),
+            tags: &[CommandTag::Destructive, CommandTag::FileAccess],
+            deprecated: Some("use \"reset\" instead"),
+            parameters: &[StaticParameter {
+                name: "force",
+                help_text: None,
+                hidden: false,
+                priority: None,
+                kind: ParameterKind::Flag,
+                required: false,
+                repeatable: false,
+                aliases: &[],
+                value_types: &[ValueType::String],
+                choices: &[],
+                negatable: false,
+                normalizations: &[],
+                deprecated: Some("it is no longer needed"),
+            }],
+        }];
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("erase config")
+                .tag(CommandTag::Destructive)
+                .tag(CommandTag::FileAccess)
+                .deprecated("use \"reset\" instead")
+                .parameter(
+                    Parameter::new("force")
+                        .kind(ParameterKind::Flag)
+                        .deprecated("it is no longer needed"),
+                ),
+        );
+        let source = tree.static_source("COMMANDS");
+        assert!(source.contains(
+            "        tags: &[::commands::parser::CommandTag::Destructive, \
+             ::commands::parser::CommandTag::FileAccess],\n"
+        ));
+        assert!(source.contains("        deprecated: Some(\"use \\\"reset\\\" instead\"),\n"));
+        assert!(source.contains("                deprecated: Some(\"it is no longer needed\"),\n"));
+
+        let mut from_static = CommandTree::new();
+        from_static.static_commands(COMMANDS);
+        assert_eq!(from_static.static_source("COMMANDS"), source);
+        let root = from_static.finalize();
+        match root.lookup("erase config").map(|n| &**n) {
+            Some(Node::Command(command)) => {
+                assert_eq!(
+                    command.tags,
+                    [CommandTag::Destructive, CommandTag::FileAccess]
+                );
+            }
+            _ => panic!("The command is missing."),
+        }
+        assert_eq!(deprecations(&root).len(), 2);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_completions() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").help("Show interfaces"));
+        let root = tree.finalize();
+        let line = Parser::new(root).complete_line("show in", 7);
+        let json = serde_json::to_value(&line).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "replace": { "start": 5, "end": 7 },
+                "completions": [{
+                    "help_symbol": "interface",
+                    "help_text": "Show interfaces",
+                    "token": {
+                        "text": "in",
+                        "token_type": "word",
+                        "location": {
+                            "start": { "byte": 5, "char": 5, "line": 0, "column": 5 },
+                            "end": { "byte": 6, "char": 6, "line": 0, "column": 6 },
+                        },
+                    },
+                    "replace": { "start": 5, "end": 7 },
+                    "exhaustive": true,
+                    "required": false,
+                    "options": [{ "option_string": "interface", "complete": true }],
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn invocation_records() {
+        let records = Rc::new(RefCell::new(vec![]));
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("route add")
+                .handler(|context| match context.parameter("via") {
+                    Some("0.0.0.0") => Err("No route.".into()),
+                    _ => Ok(()),
+                })
+                .parameter(
+                    Parameter::new("via")
+                        .kind(ParameterKind::Named)
+                        .value_type(ValueType::Ipv4),
+                )
+                .parameter(
+                    Parameter::new("tag")
+                        .kind(ParameterKind::Named)
+                        .repeatable(true),
+                )
+                .parameter(Parameter::new("quiet").kind(ParameterKind::Flag)),
+        );
+        let sink = Rc::clone(&records);
+        let builder = ParserBuilder::new(tree.finalize()).invocation_sink(
+            move |record: &InvocationRecord| sink.borrow_mut().push(record.clone()),
+        );
+        for line in ["ro a tag b qu tag a via 10.0.0.1", "route add via 0.0.0.0"] {
+            let mut parser = builder.build();
+            if let Ok(tokens) = tokenize(line) {
+                parser.parse(tokens).unwrap();
+            } else {
+                panic!("Tokenize failed.");
+            }
+            let _ = parser.execute();
+        }
+        let value = |name: &str, value: &str, value_type| RecordedValue {
+            name: name.to_string(),
+            value: value.to_string(),
+            value_type,
+        };
+        let records = records.borrow();
+        assert_eq!(
+            *records,
+            [
+                InvocationRecord {
+                    path: "route add".to_string(),
+                    line: "route add tag b quiet tag a via 10.0.0.1".to_string(),
+                    parameters: vec![
+                        value("via", "10.0.0.1", Some(ValueType::Ipv4)),
+                        value("tag", "b", Some(ValueType::String)),
+                        value("tag", "a", Some(ValueType::String)),
+                        value("quiet", "quiet", None),
+                    ],
+                    outcome: InvocationOutcome::Succeeded,
+                },
+                InvocationRecord {
+                    path: "route add".to_string(),
+                    line: "route add via 0.0.0.0".to_string(),
+                    parameters: vec![value("via", "0.0.0.0", Some(ValueType::Ipv4))],
+                    outcome: InvocationOutcome::Failed("No route.".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn serialize_invocation_records() {
+        let record = InvocationRecord {
+            path: "ping".to_string(),
+            line: "ping 10.0.0.1".to_string(),
+            parameters: vec![RecordedValue {
+                name: "host".to_string(),
+                value: "10.0.0.1".to_string(),
+                value_type: Some(ValueType::Ipv4),
+            }],
+            outcome: InvocationOutcome::Failed("Unreachable.".to_string()),
+        };
+        assert_eq!(
+            serde_json::to_value(&record).unwrap(),
+            serde_json::json!({
+                "path": "ping",
+                "line": "ping 10.0.0.1",
+                "parameters": [{ "name": "host", "value": "10.0.0.1", "value_type": "ipv4" }],
+                "outcome": { "failed": "Unreachable." },
+            })
+        );
+    }
+
+    #[test]
+    fn hint_continuations() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface"));
+        tree.command(Command::new("show ip"));
+        tree.command(
+            Command::new("set log").parameter(Parameter::new("level").choices(&["debug", "info"])),
+        );
+        let root = tree.finalize();
+        let hint = |line: &str, cursor: usize| Parser::new(Rc::clone(&root)).hint(line, cursor);
+        assert_eq!(hint("se", 2).as_deref(), Some("t"));
+        assert_eq!(hint("set ", 4).as_deref(), Some("log"));
+        assert_eq!(hint("set log d", 9).as_deref(), Some("ebug"));
+        assert_eq!(hint("set log ", 8), None);
+        assert_eq!(hint("show ", 5), None);
+        assert_eq!(hint("show i", 6), None);
+        assert_eq!(hint("sh", 1), None);
+        assert_eq!(hint("x", 1), None);
+    }
+
+    #[test]
+    fn parse_already_specified() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("connect")
+                .parameter(Parameter::new("ssh").kind(ParameterKind::Flag))
+                .parameter(Parameter::new("port").kind(ParameterKind::Named)),
+        );
+        let root = tree.finalize();
+        let parse = |line: &str| {
+            let tokens = tokenize(line).unwrap();
+            match Parser::new(Rc::clone(&root)).parse(tokens) {
+                Ok(()) => "ok".to_string(),
+                Err(ParseError::AlreadySpecified(token, node)) => {
+                    format!("{} already specified by {}", node.node().name, token.text)
+                }
+                Err(err) => err.to_string(),
+            }
+        };
+        assert_eq!(parse("connect ssh port 22"), "ok");
+        assert_eq!(parse("connect ssh ss"), "ssh already specified by ss");
+        assert_eq!(
+            parse("connect port 22 port 23"),
+            "port already specified by port"
+        );
+        assert_eq!(parse("connect telnet"), "No match.");
+    }
+
+    #[test]
+    fn complete_marks_required() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("ping")
+                .parameter(
+                    Parameter::new("host")
+                        .kind(ParameterKind::Named)
+                        .required(true),
+                )
+                .parameter(Parameter::new("count").kind(ParameterKind::Named)),
+        );
+        let mut parser = Parser::new(tree.finalize());
+        let required = |parser: &Parser| {
+            parser
+                .complete(None)
+                .iter()
+                .map(|c| (c.help_symbol.clone(), c.required))
+                .collect::<Vec<_>>()
+        };
+        if let Ok(tokens) = tokenize("ping") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(
+                required(&parser),
+                [
+                    ("count <count>".to_string(), false),
+                    ("host <host>".to_string(), true)
+                ]
+            );
+        } else {
+            panic!("Tokenize failed.");
+        }
+        parser.reset();
+        if let Ok(tokens) = tokenize("ping host example.com") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(required(&parser), [("count <count>".to_string(), false)]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn help_templates() {
+        let mut tree = CommandTree::new();
+        tree.help_fragment("iface", "of the interface for {command}");
+        tree.command(Command::new("interface").help("Configure {name}"));
+        tree.command(
+            Command::new("interface shutdown")
+                .help("{parent}: {unknown} {iface")
+                .parameter(
+                    Parameter::new("reason")
+                        .kind(ParameterKind::Named)
+                        .help("The reason {iface}"),
+                )
+                .parameter(
+                    Parameter::new("force")
+                        .kind(ParameterKind::Flag)
+                        .negatable(true)
+                        .help("{name} {parent}"),
+                ),
+        );
+        let root = tree.finalize();
+        let shutdown = root.lookup("interface shutdown").unwrap();
+        assert_eq!(
+            shutdown.node().help_text,
+            "Configure interface: {unknown} {iface"
+        );
+        let help = shutdown
+            .successors()
+            .iter()
+            .map(|n| (n.path(), n.node().help_text.as_str()))
+            .collect::<Vec<_>>();
+        assert!(help.contains(&(
+            "interface shutdown reason",
+            "The reason of the interface for interface shutdown"
+        )));
+        assert!(help.contains(&(
+            "interface shutdown force",
+            "force Configure interface: {unknown} {iface"
+        )));
+        let no = &shutdown.successors()[2];
+        assert_eq!(no.successors()[0].path(), "interface shutdown no force");
+        assert_eq!(
+            no.successors()[0].node().help_text,
+            "force Configure interface: {unknown} {iface"
+        );
+    }
+
+    #[test]
+    fn reserved_words() {
+        let tree = |parameter| {
+            let mut tree = CommandTree::new();
+            tree.config(TreeConfig::new().reserved_words(&["|", "no"]));
+            tree.command(Command::new("show log").parameter(parameter));
+            tree.try_finalize()
+        };
+        assert!(tree(Parameter::new("no")).is_ok());
+        assert_eq!(
+            tree(Parameter::new("no").kind(ParameterKind::Flag)).err(),
+            Some(TreeError::ReservedWord(
+                "show log no".to_string(),
+                "no".to_string()
+            ))
+        );
+        assert_eq!(
+            tree(Parameter::new("pipe").kind(ParameterKind::Named).alias("|")).err(),
+            Some(TreeError::ReservedWord(
+                "show log pipe".to_string(),
+                "|".to_string()
+            ))
+        );
+        let mut parser = Parser::new(tree(Parameter::new("filter")).unwrap());
+        if let Ok(tokens) = tokenize("show log no") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.canonical_line(), "show log \"no\"");
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn canonical_line() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("show ip interface")
+                .parameter(Parameter::new("brief").kind(ParameterKind::Flag)),
+        );
+        tree.command(Command::new("shutdown"));
+        let root = tree.finalize();
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("show ip int br") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.canonical_line(), "show ip interface brief");
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("sh") {
+            assert!(matches!(
+                parser.parse(tokens),
+                Err(ParseError::AmbiguousMatch(_, _))
+            ));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn contextual_help() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface"));
+        tree.command(Command::new("show ip"));
+        let root = tree.finalize();
+        let help = Parser::new(Rc::clone(&root))
+            .contextual_help("show ?")
+            .unwrap();
+        assert_eq!(help.line, "show ");
+        assert_eq!(help.completions.len(), 2);
+        let help = Parser::new(Rc::clone(&root))
+            .contextual_help("show in?")
+            .unwrap();
+        assert_eq!(help.completions.len(), 1);
+        let help = Parser::new(Rc::clone(&root))
+            .contextual_help("shout ?")
+            .unwrap();
+        assert!(help.completions.is_empty());
+        let mut parser = Parser::new(root);
+        assert!(parser.contextual_help("show").is_none());
+        assert!(parser.contextual_help("show \"in?").is_none());
+        for _ in 0..2 {
+            let help = parser.contextual_help("show ?").unwrap();
+            assert_eq!(help.completions.len(), 2);
+        }
+    }
+
+    #[test]
+    fn help_tokens() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show interface").help("Interfaces"));
+        tree.command(Command::new("show ip").help("IP settings"));
         tree.command(Command::new("set"));
+        tree.command(Command::new("\u{e9}t\u{e9}"));
+        let root = tree.finalize();
+        let help = |line: &'static str| match tokenize(line) {
+            Ok(tokens) => Parser::new(Rc::clone(&root)).help(tokens),
+            Err(_) => panic!("Tokenize failed."),
+        };
+        let symbols = |completions: Vec<Completion>| {
+            completions
+                .iter()
+                .map(|c| format!("{}: {}", c.help_symbol, c.help_text))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            symbols(help("show ? ").unwrap()),
+            ["interface: Interfaces", "ip: IP settings"]
+        );
+        assert_eq!(symbols(help("show ip?").unwrap()), ["ip: IP settings"]);
+        let partial = help("\u{e9}t?").unwrap();
+        let token = partial[0].token.unwrap();
+        assert_eq!(token.text, "\u{e9}t");
+        assert_eq!(token.location.end.byte, 2);
+        assert_eq!(token.location.end.char, 1);
+        assert_eq!(help("s?").unwrap().len(), 2);
+        assert_eq!(help("?").unwrap().len(), 3);
+        assert!(help("shout ?").unwrap().is_empty());
+        assert!(help("show").is_none());
+        assert!(help("show \"ip?\"").is_none());
+        assert!(help("show ;").is_none());
+        assert!(help("").is_none());
+
+        // The same as the help for the line.
+        for line in ["show ?", "show ip?", "s?", "?", "shout ?"] {
+            let contextual = Parser::new(Rc::clone(&root)).contextual_help(line);
+            assert_eq!(
+                symbols(help(line).unwrap()),
+                symbols(contextual.unwrap().completions)
+            );
+        }
+    }
+
+    #[test]
+    fn parse_choices() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("power").parameter(
+                Parameter::new("mode")
+                    .kind(ParameterKind::Named)
+                    .choices(&["on", "off", "auto"]),
+            ),
+        );
         let mut parser = Parser::new(tree.finalize());
-        if let Ok(tokens) = tokenize("s") {
+        if let Ok(tokens) = tokenize("power mode sleep") {
+            assert!(parser.parse(tokens).is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let mut parser = Parser::new(Rc::clone(&parser.root));
+        if let Ok(tokens) = tokenize("power mode o") {
+            assert!(parser.parse(tokens[..1].to_vec()).is_ok());
+            assert!(parser.advance(tokens[2]).is_ok());
+            let completions = parser.complete(Some(tokens[4]));
+            assert_eq!(completions.len(), 1);
+            assert!(completions[0].exhaustive);
+            let options = completions[0]
+                .options
+                .iter()
+                .filter(|o| o.complete)
+                .map(|o| o.option_string.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(options, vec!["off", "on"]);
+        }
+        if let Ok(tokens) = tokenize("auto") {
+            parser
+                .parameters
+                .insert("mode", ParameterValue::new(tokens[0]));
+            assert!(parser.verify().is_ok());
+        }
+        if let Ok(tokens) = tokenize("o") {
+            parser
+                .parameters
+                .insert("mode", ParameterValue::new(tokens[0]));
+            assert!(matches!(parser.verify(), Err(VerifyError::InvalidValue(_))));
+        }
+    }
+
+    #[test]
+    fn parse_path_values() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("edit").parameter(Parameter::new("file").value_type(ValueType::Path)),
+        );
+        tree.home_dir(|| Some("/home/me".to_string()));
+        let mut parser = Parser::new(tree.finalize());
+        if let Ok(tokens) = tokenize("edit ~/notes") {
+            assert!(parser.parse(tokens).is_ok());
+            let file = &parser.parameters.values("file")[0];
+            assert_eq!(file.value, "/home/me/notes");
+            assert_eq!(file.raw, "~/notes");
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_backtracks() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("c")
+                .parameter(Parameter::new("n").kind(ParameterKind::Named))
+                .parameter(Parameter::new("v"))
+                .parameter(Parameter::new("fast").kind(ParameterKind::Flag)),
+        );
+        let root = tree.finalize();
+
+        // `n` could be the name of `n` or a value for `v`, and either
+        // can be followed by `fast`, but only treating it as a name
+        // leaves somewhere for `x` to go.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("c n fast x") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("n"), vec!["fast"]);
+            assert_eq!(parser.parameters.values("v"), vec!["x"]);
+            assert_eq!(parser.nodes.len(), 4);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Both interpretations succeed here, but the name of `n`
+        // scores higher than a value for `v`.
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("c n fast") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("n"), vec!["fast"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_ranks_by_score() {
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("c")
+                .parameter(Parameter::new("v"))
+                .parameter(Parameter::new("fast").kind(ParameterKind::Flag))
+                .parameter(Parameter::new("far").kind(ParameterKind::Flag)),
+        );
+        tree.command(Command::new("copy"));
+        let root = tree.finalize();
+
+        // An exact match is preferred to a prefix match.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("c") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.nodes[0].node().name, "c");
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Equal scores remain ambiguous.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("c fa") {
             match parser.parse(tokens) {
-                Err(ParseError::AmbiguousMatch(_, _)) => panic!(),
-                _ => {}
+                Err(ParseError::AmbiguousMatch(token, matches)) => {
+                    assert_eq!(token.text, "fa");
+                    assert_eq!(matches.len(), 2);
+                }
+                _ => panic!(),
+            }
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Completions are ordered by score.
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("c far") {
+            let token = tokens[2];
+            assert!(parser.advance(tokens[0]).is_ok());
+            let completions = parser.complete(Some(token));
+            assert_eq!(completions.len(), 2);
+            assert_eq!(completions[0].help_symbol, "<far>");
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Usage raises the score of a node.
+        root.successors()[1].node().usage.set(200);
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("c") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.nodes[0].node().name, "copy");
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        // Unless the results must be reproducible.
+        let mut parser = ParserBuilder::new(root)
+            .config(TreeConfig::new().deterministic(true))
+            .build();
+        if let Ok(tokens) = tokenize("c") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.nodes[0].node().name, "c");
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn parse_limits_repeated_values() {
+        let mut tree = CommandTree::new();
+        tree.config(TreeConfig::new().max_repeated_values(2));
+        tree.command(Command::new("ping").parameter(Parameter::new("host").repeatable(true)));
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("ping a b") {
+            assert!(parser.parse(tokens).is_ok());
+            assert_eq!(parser.parameters.values("host"), vec!["a", "b"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("ping a b c") {
+            match parser.parse(tokens) {
+                Err(ParseError::TooManyValues(token, _)) => assert_eq!(token.text, "c"),
+                _ => panic!(),
             }
+        } else {
+            panic!("Tokenize failed.");
         }
     }
+
+    #[test]
+    fn execution_profiles() {
+        let profile = Rc::new(Cell::new(ExecutionProfile::Safe));
+        let mut tree = CommandTree::new();
+        tree.command(
+            Command::new("watch")
+                .wraps(String::new())
+                .handler(|_| Ok(())),
+        );
+        tree.command(Command::new("show").handler(|_| Ok(())));
+        tree.command(
+            Command::new("erase")
+                .tag(CommandTag::Destructive)
+                .handler(|_| Ok(())),
+        );
+        tree.execution_profile(Rc::clone(&profile));
+        let root = tree.finalize();
+        let run = |builder: &ParserBuilder, line| {
+            let mut parser = builder.build();
+            if let Ok(tokens) = tokenize(line) {
+                parser.parse(tokens).unwrap();
+            } else {
+                panic!("Tokenize failed.");
+            }
+            parser.execute().map(|_| ())
+        };
+        let builder = ParserBuilder::new(Rc::clone(&root));
+        assert!(run(&builder, "show").is_ok());
+        assert!(
+            matches!(run(&builder, "erase"), Err(ExecuteError::Blocked(ref p)) if p == "erase")
+        );
+        // Wrapping a blocked command doesn't run it.
+        assert!(
+            matches!(run(&builder, "watch erase"), Err(ExecuteError::Blocked(ref p)) if p == "erase")
+        );
+        assert_eq!(root.lookup("erase").unwrap().node().usage.get(), 0);
+
+        let full = Rc::new(Cell::new(ExecutionProfile::Full));
+        let overridden = ParserBuilder::new(Rc::clone(&root)).execution_profile(full);
+        assert!(run(&overridden, "watch erase").is_ok());
+        profile.set(ExecutionProfile::Full);
+        assert!(run(&builder, "erase").is_ok());
+    }
 }
@@ -0,0 +1,202 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::error::Error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+/// A MAC address, the value of a `ValueType::MacAddress` parameter.
+///
+/// It can be written with the octets separated by `:` or `-`, like
+/// `00:1a:2b:3c:4d:5e`, or as three groups of four hexadecimal
+/// digits separated by `.`, like `001a.2b3c.4d5e`. It is displayed
+/// in the first form.
+///
+/// ```
+/// use commands::parser::MacAddress;
+///
+/// let mac = "001A.2B3C.4D5E".parse::<MacAddress>().unwrap();
+/// assert_eq!(mac.octets(), [0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+/// assert_eq!(mac.to_string(), "00:1a:2b:3c:4d:5e");
+/// ```
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct MacAddress([u8; 6]);
+
+impl MacAddress {
+    /// Construct a `MacAddress` from its octets.
+    pub fn new(octets: [u8; 6]) -> Self {
+        MacAddress(octets)
+    }
+
+    /// The octets of the address.
+    pub fn octets(&self) -> [u8; 6] {
+        self.0
+    }
+}
+
+impl FromStr for MacAddress {
+    type Err = ValueError;
+
+    fn from_str(text: &str) -> Result<Self, ValueError> {
+        let (groups, width) = if text.contains('.') {
+            (text.split('.').collect::<Vec<_>>(), 4)
+        } else if text.contains('-') {
+            (text.split('-').collect::<Vec<_>>(), 2)
+        } else {
+            (text.split(':').collect::<Vec<_>>(), 2)
+        };
+        if groups.len() * width != 12
+            || groups
+                .iter()
+                .any(|g| g.len() != width || !g.bytes().all(|b| b.is_ascii_hexdigit()))
+        {
+            return Err(ValueError::InvalidMacAddress);
+        }
+        let digits = groups.concat();
+        let mut octets = [0; 6];
+        for (i, octet) in octets.iter_mut().enumerate() {
+            *octet = u8::from_str_radix(&digits[i * 2..i * 2 + 2], 16)
+                .map_err(|_| ValueError::InvalidMacAddress)?;
+        }
+        Ok(MacAddress(octets))
+    }
+}
+
+impl fmt::Display for MacAddress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        let [a, b, c, d, e, g] = self.0;
+        write!(f, "{a:02x}:{b:02x}:{c:02x}:{d:02x}:{e:02x}:{g:02x}")
+    }
+}
+
+/// A list of numbers and ranges of numbers, like `1-5,10,12`, the
+/// value of a `ValueType::Ranges` parameter. This is commonly used
+/// for lists of VLANs or ports.
+///
+/// ```
+/// use commands::parser::RangeList;
+///
+/// let vlans = "1-3,10".parse::<RangeList>().unwrap();
+/// assert!(vlans.contains(2));
+/// assert!(!vlans.contains(4));
+/// assert_eq!(vlans.values().collect::<Vec<_>>(), [1, 2, 3, 10]);
+/// assert_eq!(vlans.to_string(), "1-3,10");
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RangeList(Vec<RangeInclusive<u64>>);
+
+impl RangeList {
+    /// The ranges in the list, in the order that they were given.
+    /// A single number is a range of one.
+    pub fn ranges(&self) -> &[RangeInclusive<u64>] {
+        &self.0
+    }
+
+    /// Whether or not `value` is within any of the ranges.
+    pub fn contains(&self, value: u64) -> bool {
+        self.0.iter().any(|r| r.contains(&value))
+    }
+
+    /// Iterate over the numbers within the ranges.
+    pub fn values(&self) -> impl Iterator<Item = u64> + '_ {
+        self.0.iter().flat_map(Clone::clone)
+    }
+}
+
+impl FromStr for RangeList {
+    type Err = ValueError;
+
+    fn from_str(text: &str) -> Result<Self, ValueError> {
+        let number = |text: &str| {
+            if text.is_empty() || !text.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(ValueError::InvalidRange);
+            }
+            text.parse::<u64>().map_err(|_| ValueError::InvalidRange)
+        };
+        text.split(',')
+            .map(|item| {
+                let (start, end) = match item.split_once('-') {
+                    Some((start, end)) => (number(start)?, number(end)?),
+                    None => (number(item)?, number(item)?),
+                };
+                if start > end {
+                    return Err(ValueError::InvalidRange);
+                }
+                Ok(start..=end)
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(RangeList)
+    }
+}
+
+impl fmt::Display for RangeList {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        for (i, range) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(",")?;
+            }
+            if range.start() == range.end() {
+                write!(f, "{}", range.start())?;
+            } else {
+                write!(f, "{}-{}", range.start(), range.end())?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Errors that parsing a [`MacAddress`] or [`RangeList`] can raise.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueError {
+    /// The text is not a MAC address.
+    InvalidMacAddress,
+    /// The text is not a list of numbers and ranges.
+    InvalidRange,
+}
+
+impl Error for ValueError {}
+
+impl fmt::Display for ValueError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ValueError::InvalidMacAddress => "Not a valid MAC address.",
+            ValueError::InvalidRange => "Not a valid list of ranges.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_mac_addresses() {
+        let mac = MacAddress::new([0, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]);
+        assert_eq!("00:1a:2b:3c:4d:5e".parse(), Ok(mac));
+        assert_eq!("00-1A-2B-3C-4D-5E".parse(), Ok(mac));
+        assert_eq!("001a.2b3c.4d5e".parse(), Ok(mac));
+        assert!("00:1a:2b:3c:4d".parse::<MacAddress>().is_err());
+        assert!("00:1a:2b:3c:4d:5g".parse::<MacAddress>().is_err());
+        assert!("0:1a:2b:3c:4d:5e0".parse::<MacAddress>().is_err());
+        assert!("001a2b3c4d5e".parse::<MacAddress>().is_err());
+        assert!("00:1a-2b:3c:4d:5e".parse::<MacAddress>().is_err());
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let ranges = "1-5,10,12-12".parse::<RangeList>().unwrap();
+        assert_eq!(ranges.ranges(), [1..=5, 10..=10, 12..=12]);
+        assert_eq!(ranges.to_string(), "1-5,10,12");
+        assert!("5-1".parse::<RangeList>().is_err());
+        assert!("1,,2".parse::<RangeList>().is_err());
+        assert!("1-".parse::<RangeList>().is_err());
+        assert!("-1".parse::<RangeList>().is_err());
+        assert!("+1".parse::<RangeList>().is_err());
+        assert!("1-2-3".parse::<RangeList>().is_err());
+    }
+}
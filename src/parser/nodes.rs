@@ -8,11 +8,18 @@
 // by the currently permissible set of commands and their
 // parameters.
 
-use std::rc::Rc;
+use std::borrow::Cow;
+use std::cell::{Cell, RefCell};
+use std::collections::HashMap;
+use std::error::Error;
+use std::rc::{Rc, Weak};
 
 use super::constants::*;
-use super::{Completion, Parser};
-use crate::tokenizer::Token;
+use super::{
+    BindError, CommandTemplate, Complete, Completion, ExecContext, InvocationSink, MatchQuality,
+    ParameterValue, ParsedParameters, Parser, Placeholder, TreeConfig, ValueType,
+};
+use crate::tokenizer::{unquote_partial, Token};
 
 /// Enumeration of node types used to have vectors of `Node` and so on.
 pub enum Node {
@@ -58,7 +65,13 @@ pub trait NodeOps {
     /// This is the expected behavior for [`CommandNode`],
     /// [`ParameterNameNode`], as well as [`ParameterNode`] where the
     /// [`ParameterKind`] is `Flag`.
-    fn matches(&self, parser: &Parser, token: Token) -> bool;
+    fn matches(&self, parser: &Parser, token: Token) -> bool {
+        self.match_quality(parser, token).is_some()
+    }
+
+    /// How well the `token` matches this node, if it matches at all.
+    /// This is used to rank the nodes which match a token.
+    fn match_quality(&self, parser: &Parser, token: Token) -> Option<MatchQuality>;
 }
 
 /// A parse tree node.
@@ -72,9 +85,9 @@ pub struct TreeNode {
     /// Help text describing this node.
     pub help_text: String,
     /// Hidden nodes are not completed. This doesn't modify matching.
-    pub hidden: bool,
+    pub hidden: Hidden,
     /// Match and complete priority.
-    pub priority: i32,
+    pub priority: Priority,
     /// Whether or not this node can be repeated. A repeated
     /// node can be `accept`ed multiple times.
     pub repeatable: bool,
@@ -82,19 +95,146 @@ pub struct TreeNode {
     pub repeat_marker: Option<Rc<Node>>,
     /// Possible successor nodes. Collected while building.
     pub successors: Vec<Rc<Node>>,
+    /// The number of times that this node has been part of
+    /// an executed command. This contributes to its score.
+    pub usage: Cell<u32>,
+    /// If present, the node is deprecated and this describes what
+    /// to use instead. See [`deprecations`].
+    ///
+    /// [`deprecations`]: crate::parser::deprecations
+    pub deprecated: Option<String>,
+    /// The full path to this node from the root, like
+    /// `show interface`. For parameters, this is the path
+    /// of the command followed by the name of the parameter.
+    /// Set while building.
+    pub path: String,
+    /// If present, the completion logic used instead of that of
+    /// the node itself. See [`Complete`].
+    pub custom_completion: Option<Rc<dyn Complete>>,
 }
 
 /// The root of a command tree.
+///
+/// A root is never a successor, so it doesn't implement [`NodeOps`]
+/// and it can't be matched, accepted or completed by mistake:
+///
+/// ```compile_fail
+/// use commands::parser::{NodeOps, RootNode};
+///
+/// let root = RootNode::new(vec![]);
+/// let completion = root.complete(None);
+/// ```
 pub struct RootNode {
     /// [`TreeNode`] data.
     pub node: TreeNode,
+    /// The [`TreeConfig`] for the tree.
+    pub config: Rc<TreeConfig>,
+    /// The command nodes of the tree, by their full path.
+    pub(crate) index: HashMap<String, Rc<Node>>,
+    /// If present, the handler run for input which doesn't
+    /// match any command.
+    pub fallback: Option<FallbackHandler>,
+    /// If present, supplies values to offer when completing any
+    /// named or simple parameter.
+    pub value_completer: Option<ValueCompleter>,
+    /// If present, supplies the home directory for path parameters.
+    /// Otherwise, the `HOME` environment variable is used.
+    pub home_dir: Option<HomeDirProvider>,
+    /// If present, is given a record of each command executed.
+    pub invocation_sink: Option<Rc<dyn InvocationSink>>,
+    /// If present, decides which commands may be executed.
+    /// Otherwise, they all may be.
+    pub execution_profile: Option<Rc<Cell<ExecutionProfile>>>,
+    /// If present, the root of the filter commands which can
+    /// follow a `|` in a pipeline.
+    pub filters: Option<Rc<Node>>,
+}
+
+/// Supplies the home directory used to expand a leading `~` in
+/// the values of path parameters.
+pub type HomeDirProvider = Rc<dyn Fn() -> Option<String>>;
+
+/// The handler run by [`Parser::execute`] when no command matched
+/// the input. It is given the non-whitespace tokens of the input.
+///
+/// [`Parser::execute`]: crate::parser::Parser::execute
+pub type FallbackHandler = Rc<dyn Fn(&[Token]) -> Result<(), Box<dyn Error>>>;
+
+/// The handler for a command, run by [`Parser::execute`] with
+/// an [`ExecContext`] describing what the parser accepted.
+///
+/// Handlers may be closures. Application state that a handler
+/// needs to modify can be captured within an `Rc<RefCell<_>>`.
+///
+/// [`ExecContext`]: crate::parser::ExecContext
+/// [`Parser::execute`]: crate::parser::Parser::execute
+pub type CommandHandler = Rc<dyn Fn(&ExecContext) -> Result<(), Box<dyn Error>>>;
+
+/// Supplies extra completions for a command, offered once all of
+/// its required parameters have been given, like `|` to advertise
+/// filtering its output. It is given the parameters parsed so far.
+/// See [`Command::epilogue`].
+///
+/// [`Command::epilogue`]: crate::parser::Command::epilogue
+pub type CompletionEpilogue = Rc<dyn Fn(&ParsedParameters) -> Vec<Completion<'static>>>;
+
+/// Decides whether a node with [`Hidden::When`] is hidden. It is
+/// given the parser which is completing the node.
+pub type HiddenPredicate = Rc<dyn Fn(&Parser) -> bool>;
+
+/// Whether or not a node is hidden. Hidden nodes are not completed
+/// or suggested, but they still match when typed in full, so
+/// diagnostic commands can be kept out of sight of normal users
+/// while remaining available to those who know about them.
+///
+/// A `bool` converts into `Hidden::Always` or `Hidden::Never`.
+#[derive(Clone, Default)]
+pub enum Hidden {
+    /// The node is always visible.
+    #[default]
+    Never,
+    /// The node is always hidden.
+    Always,
+    /// The node is hidden when the predicate returns `true`. This
+    /// can depend upon the parser or upon application state, like
+    /// whether a support mode or a feature has been enabled.
+    When(HiddenPredicate),
+}
+
+impl Hidden {
+    /// Whether or not the node is hidden from `parser`.
+    pub fn is_hidden(&self, parser: &Parser) -> bool {
+        match *self {
+            Hidden::Never => false,
+            Hidden::Always => true,
+            Hidden::When(ref predicate) => predicate(parser),
+        }
+    }
+
+    /// Whether or not the node may be hidden. This is used when
+    /// there's no parser to decide with.
+    pub fn may_hide(&self) -> bool {
+        !matches!(*self, Hidden::Never)
+    }
+}
+
+impl From<bool> for Hidden {
+    fn from(hidden: bool) -> Self {
+        if hidden {
+            Hidden::Always
+        } else {
+            Hidden::Never
+        }
+    }
 }
 
 /// A node representing a command. Constructed via [`Command`] and [`CommandTree`].
 ///
-/// If `wrapped_root` is set then this node wraps another command.
-/// This is used for the help command so that it can complete
-/// normal commands. The `successors` will be those of the wrapped node.
+/// If `wrapped_root` is set then this node wraps a subtree of the
+/// commands, so that a full command from that subtree can follow it.
+/// This is used for commands like `help <command...>` or
+/// `watch <command...>`. Once this node has been accepted, its
+/// parameters and the successors of the wrapped node may follow.
 ///
 /// [`Command`]: crate::parser::Command
 /// [`CommandTree`]: crate::parser::CommandTree
@@ -102,11 +242,45 @@ pub struct CommandNode {
     /// [`TreeNode`] data.
     pub node: TreeNode,
     /// The handler which is executed once this node has been accepted.
-    pub handler: Option<fn(node: &Node) -> ()>,
+    /// This can be changed after the tree has been built with
+    /// [`RootNode::bind`].
+    pub handler: RefCell<Option<CommandHandler>>,
     /// Parameter nodes for this command
     pub parameters: Vec<Rc<Node>>,
-    /// If present, the command wrapped by this node.
-    pub wrapped_root: Option<Rc<Node>>,
+    /// The groups of parameters which constrain each other.
+    pub groups: Vec<ParameterGroup>,
+    /// If present, the node wrapped by this node. This is set when
+    /// the tree is finalized and is weak as a command may wrap the
+    /// root or one of its own ancestors.
+    pub wrapped_root: RefCell<Option<Weak<Node>>>,
+    /// If present, supplies extra completions once the required
+    /// parameters of this command have been given.
+    pub epilogue: Option<CompletionEpilogue>,
+    /// What the command does, which decides whether an
+    /// [`ExecutionProfile`] allows it to be executed.
+    pub tags: Vec<CommandTag>,
+}
+
+/// A group of parameters of a command which constrain each other,
+/// such as parameters which are mutually exclusive.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParameterGroup {
+    /// How the parameters relate to each other.
+    pub kind: GroupKind,
+    /// The names of the parameters.
+    pub names: Vec<String>,
+}
+
+impl ParameterGroup {
+    /// Check the group against the names of the parameters which
+    /// have been given, returning `false` if it is violated.
+    pub fn check(&self, parameters: &ParsedParameters) -> bool {
+        let given = self.names.iter().filter(|n| parameters.contains(n)).count();
+        match self.kind {
+            GroupKind::Exclusive => given <= 1,
+            GroupKind::OneOf => given == 1,
+        }
+    }
 }
 
 /// A node that represented the name portion of a named
@@ -127,8 +301,33 @@ pub struct ParameterNode {
     pub required: bool,
     /// What type of [`ParameterKind`] this is.
     pub kind: ParameterKind,
+    /// If present, supplies the values to offer when completing
+    /// this parameter.
+    pub completer: Option<ValueCompleter>,
+    /// The type of value accepted by a named or simple parameter.
+    pub value_type: ValueType,
+    /// Other types of value also accepted by a named or simple
+    /// parameter. A value is detected as the first of `value_type`
+    /// and then these which accepts it.
+    pub alternative_types: Vec<ValueType>,
+    /// If not empty, the only values accepted by a named or
+    /// simple parameter.
+    pub choices: Vec<String>,
+    /// A negatable flag can also be given as `no <name>`. It stores
+    /// `true` when given and `false` when negated.
+    pub negatable: bool,
+    /// If true, this node is the `no <name>` form of a negatable flag.
+    pub negation: bool,
+    /// The transformations applied, in order, to the values of a
+    /// named or simple parameter.
+    pub normalizations: Vec<Normalization>,
 }
 
+/// Supplies the values to offer when completing a parameter,
+/// given the text entered so far. Values which don't start with
+/// the text are discarded.
+pub type ValueCompleter = Rc<dyn Fn(&str) -> Vec<String>>;
+
 impl PartialEq for Node {
     /// Nodes are equal based on pointer equality.
     fn eq(&self, other: &Self) -> bool {
@@ -152,6 +351,71 @@ impl Node {
         }
     }
 
+    /// Find the command node with the given full `path` within
+    /// the tree. This is only supported on a root node. See
+    /// [`RootNode::lookup`].
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// tree.command(Command::new("show route"));
+    /// let root = tree.finalize();
+    ///
+    /// let node = root.lookup("show interface").unwrap();
+    /// assert_eq!(node.node().name, "interface");
+    /// assert!(root.lookup("show").is_some());
+    /// assert!(root.lookup("show int").is_none());
+    /// ```
+    pub fn lookup(&self, path: &str) -> Option<&Rc<Node>> {
+        match *self {
+            Node::Root(ref root) => root.lookup(path),
+            _ => None,
+        }
+    }
+
+    /// Set the handler for the command with the given full `path`
+    /// within the tree. This is only supported on a root node. See
+    /// [`RootNode::bind`].
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface"));
+    /// let root = tree.finalize();
+    ///
+    /// assert!(root.bind("show interface", |_| Ok(())).is_ok());
+    /// assert!(root.bind("show route", |_| Ok(())).is_err());
+    /// ```
+    pub fn bind<F>(&self, path: &str, handler: F) -> Result<(), BindError>
+    where
+        F: Fn(&ExecContext) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        match *self {
+            Node::Root(ref root) => root.bind(path, handler),
+            _ => Err(BindError::NotRoot),
+        }
+    }
+
+    /// Get the full path to this node from the root of the tree.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Parameter};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// tree.command(Command::new("show interface").parameter(Parameter::new("name")));
+    /// let root = tree.finalize();
+    ///
+    /// let node = root.lookup("show interface").unwrap();
+    /// assert_eq!(node.path(), "show interface");
+    /// assert_eq!(node.successors()[0].path(), "show interface name");
+    /// ```
+    pub fn path(&self) -> &str {
+        &self.node().path
+    }
+
     /// Get or calculate successors of this node.
     pub fn successors(&self) -> &Vec<Rc<Node>> {
         match *self {
@@ -167,7 +431,11 @@ impl NodeOps for Node {
             Node::Command(ref command) => command.accept(parser, token, node_ref),
             Node::Parameter(ref parameter) => parameter.accept(parser, token, node_ref),
             Node::ParameterName(ref name) => name.accept(parser, token, node_ref),
-            Node::Root(ref root) => root.accept(parser, token, node_ref),
+            // `RootNode` doesn't implement `NodeOps`, as it is never
+            // a successor. A root among the successors of a tree that
+            // was assembled wrongly is never acceptable, so it can't
+            // be accepted.
+            Node::Root(_) => {}
         }
     }
 
@@ -176,25 +444,39 @@ impl NodeOps for Node {
             Node::Command(ref command) => command.acceptable(parser, node_ref),
             Node::Parameter(ref parameter) => parameter.acceptable(parser, node_ref),
             Node::ParameterName(ref name) => name.acceptable(parser, node_ref),
-            Node::Root(ref root) => root.acceptable(parser, node_ref),
+            Node::Root(_) => false,
         }
     }
 
+    /// Uses the `custom_completion` of the node, if it has one.
     fn complete<'text>(&self, token: Option<Token<'text>>) -> Completion<'text> {
+        if let Some(ref custom) = self.node().custom_completion {
+            return custom.complete(self, token);
+        }
         match *self {
             Node::Command(ref command) => command.complete(token),
             Node::Parameter(ref parameter) => parameter.complete(token),
             Node::ParameterName(ref name) => name.complete(token),
-            Node::Root(ref root) => root.complete(token),
+            Node::Root(ref root) => Completion {
+                help_symbol: root.node.help_symbol.clone(),
+                help_text: root.node.help_text.clone(),
+                token,
+                replace: token.map(|t| t.location.char_range()),
+                exhaustive: true,
+                required: false,
+                options: vec![],
+                total_count: None,
+                hint: None,
+            },
         }
     }
 
-    fn matches(&self, parser: &Parser, token: Token) -> bool {
+    fn match_quality(&self, parser: &Parser, token: Token) -> Option<MatchQuality> {
         match *self {
-            Node::Command(ref command) => command.matches(parser, token),
-            Node::Parameter(ref parameter) => parameter.matches(parser, token),
-            Node::ParameterName(ref name) => name.matches(parser, token),
-            Node::Root(ref root) => root.matches(parser, token),
+            Node::Command(ref command) => command.match_quality(parser, token),
+            Node::Parameter(ref parameter) => parameter.match_quality(parser, token),
+            Node::ParameterName(ref name) => name.match_quality(parser, token),
+            Node::Root(_) => None,
         }
     }
 }
@@ -207,33 +489,52 @@ impl RootNode {
                 name: "__root__".to_string(),
                 help_symbol: "".to_string(),
                 help_text: "".to_string(),
-                hidden: false,
-                priority: PRIORITY_DEFAULT,
+                hidden: Hidden::Never,
+                priority: Priority::command(),
                 repeat_marker: None,
                 repeatable: false,
                 successors,
+                path: String::new(),
+                usage: Cell::new(0),
+                deprecated: None,
+                custom_completion: None,
             },
+            config: Rc::new(TreeConfig::default()),
+            index: HashMap::new(),
+            fallback: None,
+            value_completer: None,
+            home_dir: None,
+            invocation_sink: None,
+            execution_profile: None,
+            filters: None,
         }
     }
-}
-
-/// `RootNode` does not want to perform any actual `NodeOps` as these
-/// operations should only be invoked by the `Parser` on successor nodes.
-impl NodeOps for RootNode {
-    fn accept(&self, _parser: &mut Parser, _token: Token, _node_ref: &Rc<Node>) {}
-
-    fn acceptable(&self, _parser: &Parser, _node_ref: &Rc<Node>) -> bool {
-        false
-    }
 
-    /// A `RootNode` can not be completed.
-    fn complete<'text>(&self, _token: Option<Token<'text>>) -> Completion<'text> {
-        panic!("BUG: Can not complete a root node.");
+    /// Find the command node with the given full `path`, like
+    /// `show interface`. This uses an index built while finalizing
+    /// the tree and does not perform any matching, so the words
+    /// within the `path` must not be abbreviated.
+    pub fn lookup(&self, path: &str) -> Option<&Rc<Node>> {
+        let path = path.split_whitespace().collect::<Vec<_>>().join(" ");
+        self.index.get(&path)
     }
 
-    /// A `RootNode` can not be matched.
-    fn matches(&self, _parser: &Parser, _token: Token) -> bool {
-        panic!("BUG: Can not match a root node.");
+    /// Set the handler for the command with the given full `path`,
+    /// replacing any handler that it already had. The `path` is
+    /// found as with [`lookup`].
+    ///
+    /// [`lookup`]: RootNode::lookup
+    pub fn bind<F>(&self, path: &str, handler: F) -> Result<(), BindError>
+    where
+        F: Fn(&ExecContext) -> Result<(), Box<dyn Error>> + 'static,
+    {
+        match self.lookup(path).map(|n| &**n) {
+            Some(Node::Command(command)) => {
+                *command.handler.borrow_mut() = Some(Rc::new(handler));
+                Ok(())
+            }
+            _ => Err(BindError::UnknownPath(path.to_string())),
+        }
     }
 }
 
@@ -242,10 +543,10 @@ impl CommandNode {
     pub fn new(
         name: &str,
         help_text: Option<&str>,
-        hidden: bool,
-        priority: i32,
+        hidden: Hidden,
+        priority: Priority,
         successors: Vec<Rc<Node>>,
-        handler: Option<fn(node: &Node) -> ()>,
+        handler: Option<CommandHandler>,
         parameters: Vec<Rc<Node>>,
     ) -> Self {
         CommandNode {
@@ -258,20 +559,89 @@ impl CommandNode {
                 repeat_marker: None,
                 repeatable: false,
                 successors,
+                path: String::new(),
+                usage: Cell::new(0),
+                deprecated: None,
+                custom_completion: None,
             },
-            handler,
+            handler: RefCell::new(handler),
             parameters,
-            wrapped_root: None,
+            groups: vec![],
+            wrapped_root: RefCell::new(None),
+            epilogue: None,
+            tags: vec![],
         }
     }
+
+    /// The node wrapped by this command, if any.
+    pub fn wrapped(&self) -> Option<Rc<Node>> {
+        self.wrapped_root.borrow().as_ref().and_then(Weak::upgrade)
+    }
+
+    /// A line to insert for this command which an editor can offer
+    /// as a snippet: the full name of the command followed by its
+    /// required parameters, in the order that they were declared,
+    /// with a placeholder for the value of each.
+    ///
+    /// ```
+    /// use commands::parser::{Command, CommandTree, Node, Parameter, ParameterKind, ValueType};
+    ///
+    /// let mut tree = CommandTree::new();
+    /// let address = |name| {
+    ///     Parameter::new(name)
+    ///         .kind(ParameterKind::Named)
+    ///         .value_type(ValueType::Ipv4)
+    ///         .required(true)
+    /// };
+    /// tree.command(
+    ///     Command::new("show route")
+    ///         .parameter(address("src"))
+    ///         .parameter(address("dst"))
+    ///         .parameter(Parameter::new("vrf").kind(ParameterKind::Named)),
+    /// );
+    /// let root = tree.finalize();
+    ///
+    /// if let Node::Command(ref command) = **root.lookup("show route").unwrap() {
+    ///     let template = command.template();
+    ///     assert_eq!(template.line, "show route src <A.B.C.D> dst <A.B.C.D>");
+    ///     assert_eq!(template.placeholders[1].name, "dst");
+    ///     assert_eq!(template.placeholders[1].range, 29..38);
+    /// }
+    /// ```
+    pub fn template(&self) -> CommandTemplate {
+        let mut template = CommandTemplate {
+            line: self.node.path.clone(),
+            placeholders: vec![],
+        };
+        for parameter in &self.parameters {
+            let p = match **parameter {
+                Node::Parameter(ref p) if p.required && !p.negation => p,
+                _ => continue,
+            };
+            if p.kind != ParameterKind::Simple {
+                template.line.push(' ');
+                template.line.push_str(&p.node.name);
+            }
+            if p.kind != ParameterKind::Flag {
+                template.line.push(' ');
+                let start = template.line.len();
+                template
+                    .line
+                    .push_str(&p.value_type.help_symbol(&p.node.name));
+                template.placeholders.push(Placeholder {
+                    name: p.node.name.clone(),
+                    range: start..template.line.len(),
+                });
+            }
+        }
+        template
+    }
 }
 
 impl NodeOps for CommandNode {
     /// Record this command.
     fn accept(&self, parser: &mut Parser, _token: Token, node_ref: &Rc<Node>) {
-        if self.handler.is_some() {
-            parser.commands.push(Rc::clone(node_ref));
-        }
+        parser.commands.push(Rc::clone(node_ref));
     }
 
     fn acceptable(&self, parser: &Parser, node_ref: &Rc<Node>) -> bool {
@@ -289,8 +659,8 @@ impl NodeOps for CommandNode {
         )
     }
 
-    fn matches(&self, _parser: &Parser, token: Token) -> bool {
-        self.node.name.starts_with(token.text)
+    fn match_quality(&self, parser: &Parser, token: Token) -> Option<MatchQuality> {
+        MatchQuality::of(&self.node.name, token.text, parser.config.fuzzy_matching)
     }
 }
 
@@ -298,8 +668,8 @@ impl ParameterNameNode {
     /// Construct a new `ParameterNameNode`.
     pub fn new(
         name: &str,
-        hidden: bool,
-        priority: i32,
+        hidden: Hidden,
+        priority: Priority,
         successors: Vec<Rc<Node>>,
         repeatable: bool,
         repeat_marker: Option<Rc<Node>>,
@@ -318,6 +688,10 @@ impl ParameterNameNode {
                 repeat_marker,
                 repeatable,
                 successors,
+                path: String::new(),
+                usage: Cell::new(0),
+                deprecated: None,
+                custom_completion: None,
             },
             parameter: Rc::clone(&parameter),
         }
@@ -350,8 +724,8 @@ impl NodeOps for ParameterNameNode {
         )
     }
 
-    fn matches(&self, _parser: &Parser, token: Token) -> bool {
-        self.node.name.starts_with(token.text)
+    fn match_quality(&self, parser: &Parser, token: Token) -> Option<MatchQuality> {
+        MatchQuality::of(&self.node.name, token.text, parser.config.fuzzy_matching)
     }
 }
 
@@ -360,8 +734,8 @@ impl ParameterNode {
     pub fn new(
         name: &str,
         help_text: Option<&str>,
-        hidden: bool,
-        priority: i32,
+        hidden: Hidden,
+        priority: Priority,
         successors: Vec<Rc<Node>>,
         repeatable: bool,
         repeat_marker: Option<Rc<Node>>,
@@ -388,29 +762,192 @@ impl ParameterNode {
                 repeat_marker,
                 repeatable,
                 successors,
+                path: String::new(),
+                usage: Cell::new(0),
+                deprecated: None,
+                custom_completion: None,
             },
             kind,
             required,
+            completer: None,
+            value_type: ValueType::default(),
+            alternative_types: vec![],
+            choices: vec![],
+            negatable: false,
+            negation: false,
+            normalizations: vec![],
+        }
+    }
+
+    /// Set the type of value accepted by this parameter, updating
+    /// the help symbol to show it.
+    pub fn set_value_type(&mut self, value_type: ValueType) {
+        self.value_type = value_type;
+        self.node.help_symbol = value_type.help_symbol(&self.node.name);
+        if self.node.repeatable {
+            self.node.help_symbol.push_str("...");
+        }
+    }
+
+    /// Set the types of value accepted by this parameter, in the
+    /// order that a value is detected as them. The first is the
+    /// `value_type` and the others are the `alternative_types`.
+    /// The help symbol shows each of them.
+    pub fn set_value_types(&mut self, value_types: &[ValueType]) {
+        if let Some((first, rest)) = value_types.split_first() {
+            self.set_value_type(*first);
+            self.alternative_types = rest.to_vec();
+            if !rest.is_empty() {
+                let symbols = value_types
+                    .iter()
+                    .map(|t| t.help_symbol(&self.node.name))
+                    .collect::<Vec<_>>();
+                self.node.help_symbol = symbols.join("|");
+                if self.node.repeatable {
+                    self.node.help_symbol.push_str("...");
+                }
+            }
+        }
+    }
+
+    /// The types of value accepted by this parameter, in the order
+    /// that a value is detected as them.
+    pub fn value_types(&self) -> impl Iterator<Item = ValueType> + '_ {
+        Some(self.value_type)
+            .into_iter()
+            .chain(self.alternative_types.iter().cloned())
+    }
+
+    /// Whether or not `text` is a valid value for this parameter,
+    /// given its value types and `choices`, once normalized.
+    pub fn accepts(&self, text: &str) -> bool {
+        let text = self.normalize(text);
+        self.value_types().any(|t| t.accepts(&text))
+            && (self.choices.is_empty() || self.choices.iter().any(|c| *c == text))
+    }
+
+    /// Detect which of the value types of this parameter `text` is
+    /// a value of, returning that type along with the `canonical`
+    /// text for it. This is `None` when the text isn't accepted.
+    pub fn detect<'a>(
+        &self,
+        text: &'a str,
+        config: &TreeConfig,
+    ) -> Option<(ValueType, Cow<'a, str>)> {
+        self.value_types().find_map(|value_type| {
+            let canonical = self.canonical_as(value_type, text, config)?;
+            let accepted = value_type.accepts(&canonical)
+                && (self.choices.is_empty() || self.choices.iter().any(|c| *c == canonical));
+            accepted.then_some((value_type, canonical))
+        })
+    }
+
+    /// The value to check and store for `text`, once normalized
+    /// and, for numbers, rewritten from the `number_format` of the
+    /// `config`. This is `None` when the number isn't written in
+    /// that format.
+    ///
+    /// This is for the value type which the text is detected as,
+    /// or the `value_type` if it isn't accepted.
+    pub fn canonical<'a>(&self, text: &'a str, config: &TreeConfig) -> Option<Cow<'a, str>> {
+        match self.detect(text, config) {
+            Some((_, canonical)) => Some(canonical),
+            None => self.canonical_as(self.value_type, text, config),
+        }
+    }
+
+    fn canonical_as<'a>(
+        &self,
+        value_type: ValueType,
+        text: &'a str,
+        config: &TreeConfig,
+    ) -> Option<Cow<'a, str>> {
+        let text = self.normalize(text);
+        match value_type {
+            ValueType::Integer
+                if ValueType::parse_integer(&text).is_some_and(|(_, radix)| radix != 10) =>
+            {
+                Some(text)
+            }
+            ValueType::Integer | ValueType::Float => {
+                let number = match config.number_format.canonicalize(&text)? {
+                    Cow::Owned(number) => Some(number),
+                    Cow::Borrowed(_) => None,
+                };
+                Some(number.map_or(text, Cow::Owned))
+            }
+            _ => Some(text),
+        }
+    }
+
+    /// Apply the `normalizations` of this parameter to `text`.
+    pub fn normalize<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for normalization in &self.normalizations {
+            text = match *normalization {
+                Normalization::Lowercase => Cow::Owned(text.to_lowercase()),
+                Normalization::Uppercase => Cow::Owned(text.to_uppercase()),
+                Normalization::Trim => match text {
+                    Cow::Borrowed(text) => Cow::Borrowed(text.trim()),
+                    Cow::Owned(text) => Cow::Owned(text.trim().to_string()),
+                },
+            };
         }
+        text
     }
 }
 
 impl NodeOps for ParameterNode {
-    /// Record this parameter value.
+    /// Record this parameter value, without any quotes or
+    /// escaping backslashes and in its `canonical` form, along
+    /// with the token's text.
     fn accept(&self, parser: &mut Parser, token: Token, _node_ref: &Rc<Node>) {
+        let mut value = ParameterValue::new(token);
+        if self.negatable || self.negation {
+            value.value = (!self.negation).to_string();
+        }
+        let detected = self.detect(&value.value, &parser.config);
+        let value_type = detected.as_ref().map_or(self.value_type, |(t, _)| *t);
+        let canonical = match detected {
+            Some((_, canonical)) => Some(canonical),
+            None => self.canonical_as(value_type, &value.value, &parser.config),
+        };
+        if let Some(canonical) = canonical {
+            if canonical != value.value {
+                value.value = canonical.into_owned();
+            }
+        }
+        if self.kind != ParameterKind::Flag {
+            value.value_type = Some(value_type);
+        }
+        if value_type == ValueType::Integer {
+            value.radix = ValueType::parse_integer(&value.value).map(|(_, radix)| radix);
+        }
+        if let Some(parsed) = value_type.parse(&value.value) {
+            if parsed != value.value {
+                value.value = parsed.into_owned();
+            }
+        }
+        if value_type == ValueType::Path && value.value.starts_with('~') {
+            let home = parser.home_dir();
+            let expanded = ValueType::expand_home(&value.value, home.as_deref());
+            value.value = expanded.into_owned();
+        }
         if self.node.repeatable {
-            unimplemented!();
+            parser.parameters.push(&self.node.name, value);
         } else {
-            parser
-                .parameters
-                .insert(self.node.name.clone(), token.text.to_string());
+            parser.parameters.insert(&self.node.name, value);
         }
     }
 
+    /// A negatable flag is only acceptable once, whether negated or not.
     fn acceptable(&self, parser: &Parser, node_ref: &Rc<Node>) -> bool {
         if self.node.repeatable {
             return true;
         }
+        if (self.negatable || self.negation) && parser.parameters.contains(&self.node.name) {
+            return false;
+        }
         !parser.nodes.contains(node_ref)
             && match self.node.repeat_marker {
                 None => true,
@@ -420,7 +957,48 @@ impl NodeOps for ParameterNode {
 
     /// By default named and simple parameters complete only to the token
     /// being input while flag parameters complete to the name of the flag.
+    ///
+    /// Named and simple parameters with `choices` complete to only
+    /// those. Otherwise, those with a `completer` complete to the
+    /// values that it supplies, or to those supplied by their
+    /// `ValueType`, if any.
     fn complete<'text>(&self, token: Option<Token<'text>>) -> Completion<'text> {
+        if !self.choices.is_empty() && self.kind != ParameterKind::Flag {
+            let choices = self.choices.iter().map(String::as_str).collect::<Vec<_>>();
+            return Completion::new(
+                self.node.help_symbol.clone(),
+                self.node.help_text.clone(),
+                token,
+                true,
+                &choices,
+                &[],
+            );
+        }
+        let text = token.map_or(Cow::Borrowed(""), |t| {
+            unquote_partial(t.text, t.smart_quoted).0
+        });
+        let values =
+            match (&self.completer, self.kind) {
+                (_, ParameterKind::Flag) => None,
+                (Some(completer), _) => Some(completer(&text)),
+                (None, _) => self.value_types().filter_map(|t| t.complete(&text)).reduce(
+                    |mut values, more| {
+                        values.extend(more);
+                        values
+                    },
+                ),
+            };
+        if let Some(values) = values {
+            let values = values.iter().map(String::as_str).collect::<Vec<_>>();
+            return Completion::new(
+                self.node.help_symbol.clone(),
+                self.node.help_text.clone(),
+                token,
+                false,
+                &values,
+                &[],
+            );
+        }
         match self.kind {
             ParameterKind::Named | ParameterKind::Simple => Completion::new(
                 self.node.help_symbol.clone(),
@@ -441,10 +1019,18 @@ impl NodeOps for ParameterNode {
         }
     }
 
-    fn matches(&self, _parser: &Parser, token: Token) -> bool {
+    fn match_quality(&self, parser: &Parser, token: Token) -> Option<MatchQuality> {
         match self.kind {
-            ParameterKind::Named | ParameterKind::Simple => true,
-            ParameterKind::Flag => self.node.name.starts_with(token.text),
+            ParameterKind::Named | ParameterKind::Simple => {
+                if self.detect(&token.value(), &parser.config).is_some() {
+                    Some(MatchQuality::Value)
+                } else {
+                    None
+                }
+            }
+            ParameterKind::Flag => {
+                MatchQuality::of(&self.node.name, token.text, parser.config.fuzzy_matching)
+            }
         }
     }
 }
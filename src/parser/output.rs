@@ -0,0 +1,45 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// The output produced by executing a command.
+///
+/// Rather than printing, command handlers add lines to the output
+/// via [`ExecContext::output`]. The output is returned from
+/// [`Parser::execute`] for the embedding application to display.
+///
+/// [`ExecContext::output`]: crate::parser::ExecContext::output
+/// [`Parser::execute`]: crate::parser::Parser::execute
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct CommandOutput {
+    lines: Vec<String>,
+}
+
+impl CommandOutput {
+    /// Construct an empty `CommandOutput`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a line to the output.
+    pub fn line<S: Into<String>>(&mut self, line: S) {
+        self.lines.push(line.into());
+    }
+
+    /// Add the lines of `other` to the output.
+    pub fn append(&mut self, other: CommandOutput) {
+        self.lines.extend(other.lines);
+    }
+
+    /// The lines of the output.
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Whether or not there is any output.
+    pub fn is_empty(&self) -> bool {
+        self.lines.is_empty()
+    }
+}
@@ -0,0 +1,255 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::nodes::Node;
+use super::{CommandOutput, ExecuteError, ParseError, Parser, VerifyError};
+use crate::tokenizer::{Token, TokenType};
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// Execute the commands in `tokens`, which are separated by `|`,
+/// starting each from the `root` of a command tree.
+///
+/// The output of each command is given to the next as its input,
+/// available from [`ExecContext::input`], and the output of the
+/// last command is returned. This allows for filters like
+/// `show log | include error`. Without any `|`, this is the same
+/// as parsing, verifying and executing the command.
+///
+/// When the tree has filter commands, added with
+/// [`CommandTree::filter`], the commands after each `|` are those
+/// filters rather than the commands of the tree. Every command
+/// is parsed and verified before any are executed, and each can
+/// see the filters which follow it with [`ExecContext::filters`].
+///
+/// ```
+/// use commands::parser::{execute_pipeline, Command, CommandTree};
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show log").handler(|context| {
+///     context.output().line("started");
+///     context.output().line("failed");
+///     Ok(())
+/// }));
+/// tree.command(Command::new("count").handler(|context| {
+///     let count = context.input().map_or(0, |input| input.lines().len());
+///     context.output().line(count.to_string());
+///     Ok(())
+/// }));
+/// let root = tree.finalize();
+///
+/// if let Ok(tokens) = tokenize("show log | count") {
+///     let output = execute_pipeline(&root, tokens).unwrap();
+///     assert_eq!(output.lines(), ["2"]);
+/// }
+/// ```
+///
+/// [`CommandTree::filter`]: crate::parser::CommandTree::filter
+/// [`ExecContext::input`]: crate::parser::ExecContext::input
+/// [`ExecContext::filters`]: crate::parser::ExecContext::filters
+pub fn execute_pipeline<'text>(
+    root: &Rc<Node>,
+    tokens: Vec<Token<'text>>,
+) -> Result<CommandOutput, PipelineError<'text>> {
+    let mut parsers = vec![];
+    let stages = tokens.split(|t| t.token_type == TokenType::Pipe);
+    for (stage, tokens) in stages.enumerate() {
+        if tokens.iter().all(|t| t.token_type == TokenType::Whitespace) {
+            return Err(PipelineError::EmptyCommand(stage));
+        }
+        let mut parser = Parser::new(Rc::clone(root));
+        if stage > 0 {
+            parser.enter_filters();
+        }
+        parser
+            .parse(tokens.to_vec())
+            .map_err(PipelineError::Parse)?;
+        parser.verify().map_err(PipelineError::Verify)?;
+        parsers.push(parser);
+    }
+    let filters = parsers
+        .iter()
+        .filter_map(|p| p.clone().into_invocation())
+        .collect::<Vec<_>>();
+    let mut input: Option<CommandOutput> = None;
+    for (stage, parser) in parsers.iter().enumerate() {
+        let following = filters.get(stage + 1..).unwrap_or(&[]);
+        let output = parser
+            .execute_with(input.as_ref(), following)
+            .map_err(PipelineError::Execute)?;
+        input = Some(output);
+    }
+    Ok(input.unwrap_or_default())
+}
+
+/// Errors that executing a pipeline can raise.
+#[derive(Debug)]
+pub enum PipelineError<'text> {
+    /// There was no command before or after a `|`. This has the
+    /// index of the command within the pipeline.
+    EmptyCommand(usize),
+    /// A command could not be parsed.
+    Parse(ParseError<'text>),
+    /// A command was not valid.
+    Verify(VerifyError),
+    /// A command failed.
+    Execute(ExecuteError),
+}
+
+impl Error for PipelineError<'_> {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match *self {
+            PipelineError::Verify(ref err) => Some(err),
+            PipelineError::Execute(ref err) => Some(err),
+            PipelineError::EmptyCommand(_) | PipelineError::Parse(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for PipelineError<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            PipelineError::EmptyCommand(_) => "A command is missing from the pipeline.".fmt(f),
+            PipelineError::Parse(ref err) => err.fmt(f),
+            PipelineError::Verify(ref err) => err.fmt(f),
+            PipelineError::Execute(ref err) => err.fmt(f),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, Parameter};
+    use crate::session::builtins;
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn pipe_output() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show log").handler(|context| {
+            assert!(context.input().is_none());
+            context.output().line("link up");
+            context.output().line("link down");
+            Ok(())
+        }));
+        tree.command(
+            Command::new("include")
+                .parameter(Parameter::new("pattern").required(true))
+                .handler(|context| {
+                    let pattern = context.parameter("pattern").unwrap_or("");
+                    if let Some(input) = context.input() {
+                        for line in input.lines().iter().filter(|l| l.contains(pattern)) {
+                            context.output().line(line.clone());
+                        }
+                    }
+                    Ok(())
+                }),
+        );
+        let root = tree.finalize();
+
+        if let Ok(tokens) = tokenize("show log | include down | include link") {
+            let output = execute_pipeline(&root, tokens).unwrap();
+            assert_eq!(output.lines(), ["link down"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("show log") {
+            let output = execute_pipeline(&root, tokens).unwrap();
+            assert_eq!(output.lines().len(), 2);
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("show log | ") {
+            match execute_pipeline(&root, tokens) {
+                Err(PipelineError::EmptyCommand(1)) => {}
+                _ => panic!(),
+            }
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(tokens) = tokenize("show log | exclude down") {
+            assert!(matches!(
+                execute_pipeline(&root, tokens),
+                Err(PipelineError::Parse(_))
+            ));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn filter_commands() {
+        let mut tree = CommandTree::new();
+        tree.filter(builtins::count());
+        tree.filter(
+            Command::new("exclude")
+                .parameter(Parameter::new("pattern").required(true))
+                .handler(|context| {
+                    let pattern = context.parameter("pattern").unwrap_or("");
+                    if let Some(input) = context.input() {
+                        for line in input.lines().iter().filter(|l| !l.contains(pattern)) {
+                            context.output().line(line.clone());
+                        }
+                    }
+                    Ok(())
+                }),
+        );
+        tree.command(Command::new("show log").handler(|context| {
+            let filters = context
+                .filters()
+                .iter()
+                .map(|f| f.command_path.as_str())
+                .collect::<Vec<_>>();
+            context.output().line(filters.join(","));
+            context.output().line("link up");
+            context.output().line("link down");
+            context.output().line("cpu high");
+            Ok(())
+        }));
+        tree.command(Command::new("count words"));
+        let root = tree.finalize();
+
+        let run = |line: &'static str| match tokenize(line) {
+            Ok(tokens) => execute_pipeline(&root, tokens),
+            Err(_) => panic!("Tokenize failed."),
+        };
+        assert_eq!(
+            run("show log | exclude cpu | exclude up").unwrap().lines(),
+            ["exclude,exclude", "link down"]
+        );
+        assert_eq!(run("show log | count").unwrap().lines(), ["4"]);
+        assert_eq!(
+            run("show log | exclude link | count").unwrap().lines(),
+            ["2"]
+        );
+        // The commands of the tree aren't filters, and filters aren't commands.
+        assert!(matches!(
+            run("show log | show log"),
+            Err(PipelineError::Parse(_))
+        ));
+        assert!(matches!(
+            run("count"),
+            Err(PipelineError::Execute(ExecuteError::NoHandler(_)))
+        ));
+        assert!(matches!(
+            run("show log | exclude"),
+            Err(PipelineError::Verify(_))
+        ));
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        let completion = parser.complete_line("show log | c", 12);
+        assert_eq!(completion.replace, 11..12);
+        let options = completion.completions[0]
+            .options
+            .iter()
+            .map(|o| o.option_string.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(options, ["count"]);
+    }
+}
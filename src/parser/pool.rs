@@ -0,0 +1,115 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::{Parser, ParserBuilder};
+
+/// A pool of [`Parser`]s which can be reused for many lines.
+///
+/// Parsing a line allocates space for the nodes, tokens and values
+/// that are accepted. When a parser is returned to the pool, it is
+/// reset but keeps that space, so parsing many lines, as a server
+/// might, doesn't allocate for each of them. The lines given to the
+/// parsers don't need to outlive the pool. As the tokens borrow from
+/// a line, keeping the space for them is only best-effort, and it
+/// may be allocated again.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, ParserBuilder, ParserPool};
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("show"));
+///
+/// let mut pool = ParserPool::new(ParserBuilder::new(tree.finalize()));
+/// for _ in 0..3 {
+///     let line = String::from("show");
+///     let mut parser = pool.get();
+///     if let Ok(tokens) = tokenize(&line) {
+///         parser.parse(tokens).unwrap();
+///     }
+///     pool.put(parser);
+/// }
+/// assert_eq!(pool.len(), 1);
+/// ```
+pub struct ParserPool {
+    builder: ParserBuilder,
+    parsers: Vec<Parser<'static>>,
+}
+
+impl ParserPool {
+    /// Construct an empty pool which uses the `builder` when it
+    /// needs another parser.
+    pub fn new(builder: ParserBuilder) -> Self {
+        ParserPool {
+            builder,
+            parsers: vec![],
+        }
+    }
+
+    /// Take a parser from the pool, building one if the pool is
+    /// empty. The parser is at the root of the tree.
+    pub fn get<'text>(&mut self) -> Parser<'text> {
+        match self.parsers.pop() {
+            Some(parser) => parser.recycle(),
+            None => self.builder.build(),
+        }
+    }
+
+    /// Return a `parser` to the pool, so that it can be reused.
+    pub fn put(&mut self, parser: Parser) {
+        self.parsers.push(parser.recycle());
+    }
+
+    /// The number of parsers waiting in the pool.
+    pub fn len(&self) -> usize {
+        self.parsers.len()
+    }
+
+    /// Whether or not the pool has no parsers waiting in it.
+    pub fn is_empty(&self) -> bool {
+        self.parsers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{Command, CommandTree, Parameter};
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn reuse_parsers() {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show").parameter(Parameter::new("what")));
+        let mut pool = ParserPool::new(ParserBuilder::new(tree.finalize()));
+        assert!(pool.is_empty());
+
+        let line = String::from("show version");
+        let mut parser = pool.get();
+        if let Ok(tokens) = tokenize(&line) {
+            assert!(parser.parse(tokens).is_ok());
+        } else {
+            panic!("Tokenize failed.");
+        }
+        let capacity = parser.nodes.capacity();
+        pool.put(parser);
+        drop(line);
+        assert_eq!(pool.len(), 1);
+
+        let line = String::from("show");
+        let mut parser = pool.get();
+        assert!(pool.is_empty());
+        assert!(parser.nodes.is_empty());
+        assert!(parser.tokens.is_empty());
+        assert_eq!(parser.nodes.capacity(), capacity);
+        if let Ok(tokens) = tokenize(&line) {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(!parser.parameters().contains("what"));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+}
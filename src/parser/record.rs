@@ -0,0 +1,88 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::value_type::ValueType;
+
+/// Receives an [`InvocationRecord`] for each command that a
+/// [`Parser`] executes, so that the commands can be replicated,
+/// echoed to another client or used as the events which build up
+/// the state of an application.
+///
+/// The sink is given to [`CommandTree::invocation_sink`] or to
+/// [`ParserBuilder::invocation_sink`]. Any function taking an
+/// `&InvocationRecord` is a sink.
+///
+/// [`Parser`]: crate::parser::Parser
+/// [`CommandTree::invocation_sink`]: crate::parser::CommandTree::invocation_sink
+/// [`ParserBuilder::invocation_sink`]: crate::parser::ParserBuilder::invocation_sink
+pub trait InvocationSink {
+    /// Record the execution of a command. This is called after
+    /// the handler of the command has returned.
+    fn record(&self, record: &InvocationRecord);
+}
+
+impl<F: Fn(&InvocationRecord)> InvocationSink for F {
+    fn record(&self, record: &InvocationRecord) {
+        self(record)
+    }
+}
+
+/// A command which was executed, as given to an [`InvocationSink`].
+///
+/// With the `serde` feature, records can be serialized:
+///
+/// ```json
+/// {
+///   "path": "interface set",
+///   "line": "interface set eth0 mtu 1500",
+///   "parameters": [
+///     { "name": "name", "value": "eth0", "value_type": "string" },
+///     { "name": "mtu", "value": "1500", "value_type": "integer" }
+///   ],
+///   "outcome": "succeeded"
+/// }
+/// ```
+///
+/// A failed command has an outcome like `{ "failed": "message" }`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct InvocationRecord {
+    /// The full path of the command which was executed.
+    pub path: String,
+    /// The input in canonical form, as with `Parser::canonical_line`.
+    pub line: String,
+    /// The values of the parameters which were given, in the order
+    /// that the command declares its parameters. A repeatable
+    /// parameter has a value for each time that it was given.
+    pub parameters: Vec<RecordedValue>,
+    /// Whether or not the handler of the command succeeded.
+    pub outcome: InvocationOutcome,
+}
+
+/// The value of a parameter within an [`InvocationRecord`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct RecordedValue {
+    /// The name of the parameter.
+    pub name: String,
+    /// The value, as the handler was given it. A flag has its name
+    /// as its value, or `true` or `false` if it is negatable.
+    pub value: String,
+    /// The type that the value was detected as. This is `None`
+    /// for flags.
+    pub value_type: Option<ValueType>,
+}
+
+/// The outcome of an [`InvocationRecord`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum InvocationOutcome {
+    /// The handler returned successfully.
+    Succeeded,
+    /// The handler returned an error, which has this message.
+    Failed(String),
+}
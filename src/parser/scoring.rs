@@ -0,0 +1,177 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+/// How well a token matches a node.
+///
+/// Variants are ordered from the best match to the worst.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum MatchQuality {
+    /// The token is the name of the node.
+    Exact,
+    /// The token is a prefix of the name of the node.
+    Prefix,
+    /// The characters of the token appear, in order, within the
+    /// name of the node, starting with the first character. This
+    /// is only used when `TreeConfig::fuzzy_matching` is enabled.
+    Fuzzy,
+    /// The node accepts any value, like a simple parameter.
+    Value,
+}
+
+impl MatchQuality {
+    /// Determine how well `text` matches the `name` of a node.
+    pub fn of(name: &str, text: &str, fuzzy: bool) -> Option<MatchQuality> {
+        if name == text {
+            Some(MatchQuality::Exact)
+        } else if name.starts_with(text) {
+            Some(MatchQuality::Prefix)
+        } else if fuzzy && is_fuzzy_match(name, text) {
+            Some(MatchQuality::Fuzzy)
+        } else {
+            None
+        }
+    }
+}
+
+fn is_fuzzy_match(name: &str, text: &str) -> bool {
+    let mut name_chars = name.chars();
+    let mut text_chars = text.chars();
+    match (name_chars.next(), text_chars.next()) {
+        (Some(n), Some(t)) if n == t => {}
+        _ => return false,
+    }
+    text_chars.all(|t| name_chars.any(|n| n == t))
+}
+
+/// Score how well `pattern` matches `candidate` when the characters
+/// of the pattern appear, in order, within the candidate, ignoring
+/// case. Each matched character scores, with a bonus for starting
+/// a word and for following the previous match directly, while
+/// each character skipped between matches costs a little. This is
+/// `None` when the pattern isn't a subsequence of the candidate.
+pub(crate) fn fuzzy_score(candidate: &str, pattern: &str) -> Option<i32> {
+    let mut pattern = pattern.chars().flat_map(char::to_lowercase).peekable();
+    let mut score = 0i32;
+    let mut previous: Option<char> = None;
+    let mut matched_previous = false;
+    let mut started = false;
+    for c in candidate.chars() {
+        let next = match pattern.peek() {
+            Some(&next) => next,
+            None => break,
+        };
+        let word_start = !previous.is_some_and(char::is_alphanumeric);
+        if c.to_lowercase().eq(Some(next)) {
+            pattern.next();
+            score += 16;
+            if word_start {
+                score += 8;
+            } else if matched_previous {
+                score += 4;
+            }
+            matched_previous = true;
+            started = true;
+        } else {
+            if started {
+                score -= 1;
+            }
+            matched_previous = false;
+        }
+        previous = Some(c);
+    }
+    match pattern.peek() {
+        Some(_) => None,
+        None => Some(score),
+    }
+}
+
+/// The weights used to score the nodes which match a token.
+///
+/// When more than one node matches a token, the nodes with the
+/// highest score are preferred. Completion options are also
+/// ordered by their score.
+///
+/// The score of a node is the weight for its [`MatchQuality`]
+/// plus its priority and its usage count, each multiplied by
+/// their weight.
+#[derive(Clone, Copy, Debug)]
+pub struct ScoreWeights {
+    /// The weight of an exact match.
+    pub exact: i32,
+    /// The weight of a prefix match.
+    pub prefix: i32,
+    /// The weight of a fuzzy match.
+    pub fuzzy: i32,
+    /// The weight of a match by a node accepting any value.
+    pub value: i32,
+    /// The multiplier for the priority of the node.
+    pub priority: i32,
+    /// The multiplier for the number of times that the node
+    /// has been used in an executed command. This is ignored
+    /// when `TreeConfig::deterministic` is enabled.
+    pub usage: i32,
+}
+
+impl Default for ScoreWeights {
+    fn default() -> Self {
+        ScoreWeights {
+            exact: 300,
+            prefix: 200,
+            fuzzy: 100,
+            value: 0,
+            priority: 1,
+            usage: 1,
+        }
+    }
+}
+
+impl ScoreWeights {
+    /// The weight for the given `quality` of match.
+    pub fn quality(&self, quality: MatchQuality) -> i32 {
+        match quality {
+            MatchQuality::Exact => self.exact,
+            MatchQuality::Prefix => self.prefix,
+            MatchQuality::Fuzzy => self.fuzzy,
+            MatchQuality::Value => self.value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn match_quality() {
+        assert_eq!(
+            MatchQuality::of("show", "show", false),
+            Some(MatchQuality::Exact)
+        );
+        assert_eq!(
+            MatchQuality::of("show", "sh", false),
+            Some(MatchQuality::Prefix)
+        );
+        assert_eq!(MatchQuality::of("show", "sw", false), None);
+        assert_eq!(
+            MatchQuality::of("show", "sw", true),
+            Some(MatchQuality::Fuzzy)
+        );
+        assert_eq!(MatchQuality::of("show", "hw", true), None);
+        assert_eq!(MatchQuality::of("show", "swo", true), None);
+        assert!(MatchQuality::Exact < MatchQuality::Prefix);
+    }
+
+    #[test]
+    fn fuzzy_scores() {
+        assert_eq!(fuzzy_score("show interface", "xyz"), None);
+        assert_eq!(fuzzy_score("show", "sw"), Some(16 + 8 + 16 - 2));
+        let interface = fuzzy_score("show interface", "shint").unwrap();
+        let ip = fuzzy_score("show ip interface", "shint").unwrap();
+        assert!(interface > ip);
+        assert_eq!(fuzzy_score("Show", "sH"), fuzzy_score("show", "sh"));
+        assert_eq!(fuzzy_score("show", ""), Some(0));
+    }
+}
@@ -0,0 +1,159 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::constants::{CommandTag, Normalization, ParameterKind, Priority};
+use super::{Command, Parameter, ValueType};
+
+/// A description of a command which can be kept in a `static`, so
+/// that a large set of commands costs nothing to describe until it
+/// is added to a [`CommandTree`] with [`CommandTree::static_commands`].
+///
+/// These can be written by hand, or generated by a build script
+/// with [`CommandTree::static_source`]. They only describe what can
+/// be known at compile time, so handlers are bound by path with
+/// [`CommandTree::bind`] or [`RootNode::bind`] instead.
+///
+/// ```
+/// use commands::parser::{CommandTree, ParameterKind, Parser, Priority};
+/// use commands::parser::{StaticCommand, StaticParameter, ValueType};
+/// use commands::tokenizer::tokenize;
+///
+/// static COMMANDS: &[StaticCommand] = &[StaticCommand {
+///     name: "ping",
+///     help_text: Some("Check that a host is reachable"),
+///     hidden: false,
+///     priority: Priority::command(),
+///     tags: &[],
+///     deprecated: None,
+///     parameters: &[StaticParameter {
+///         name: "count",
+///         help_text: None,
+///         hidden: false,
+///         priority: None,
+///         kind: ParameterKind::Named,
+///         required: false,
+///         repeatable: false,
+///         aliases: &["c"],
+///         value_types: &[ValueType::Integer],
+///         choices: &[],
+///         negatable: false,
+///         normalizations: &[],
+///         deprecated: None,
+///     }],
+/// }];
+///
+/// let mut tree = CommandTree::new();
+/// tree.static_commands(COMMANDS);
+/// let mut parser = Parser::new(tree.finalize());
+/// if let Ok(tokens) = tokenize("ping c 3") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// assert_eq!(parser.parameters().value("count").unwrap().as_str(), "3");
+/// ```
+///
+/// [`CommandTree`]: crate::parser::CommandTree
+/// [`CommandTree::bind`]: crate::parser::CommandTree::bind
+/// [`CommandTree::static_commands`]: crate::parser::CommandTree::static_commands
+/// [`CommandTree::static_source`]: crate::parser::CommandTree::static_source
+/// [`RootNode::bind`]: crate::parser::RootNode::bind
+#[derive(Clone, Copy)]
+pub struct StaticCommand {
+    /// The name of the command, as given to [`Command::new`].
+    pub name: &'static str,
+    /// The help text, if any.
+    pub help_text: Option<&'static str>,
+    /// Whether or not the command is hidden.
+    pub hidden: bool,
+    /// The priority of the command.
+    pub priority: Priority,
+    /// What the command does, as given to [`Command::tag`].
+    pub tags: &'static [CommandTag],
+    /// What to use instead, if the command is deprecated.
+    pub deprecated: Option<&'static str>,
+    /// The parameters of the command.
+    pub parameters: &'static [StaticParameter],
+}
+
+/// A description of a parameter of a [`StaticCommand`].
+#[derive(Clone, Copy)]
+pub struct StaticParameter {
+    /// The name of the parameter.
+    pub name: &'static str,
+    /// The help text, if any.
+    pub help_text: Option<&'static str>,
+    /// Whether or not the parameter is hidden.
+    pub hidden: bool,
+    /// The priority of the parameter, or `None` for the default
+    /// priority of its kind.
+    pub priority: Option<Priority>,
+    /// The kind of parameter.
+    pub kind: ParameterKind,
+    /// Whether or not the parameter must be given.
+    pub required: bool,
+    /// Whether or not the parameter can be given more than once.
+    pub repeatable: bool,
+    /// Other names for a named parameter or flag.
+    pub aliases: &'static [&'static str],
+    /// The types of value accepted, tried in order.
+    pub value_types: &'static [ValueType],
+    /// The values allowed, if limited to a set of choices.
+    pub choices: &'static [&'static str],
+    /// Whether or not a flag can be negated with `no`.
+    pub negatable: bool,
+    /// The transformations applied to values, in order.
+    pub normalizations: &'static [Normalization],
+    /// What to use instead, if the parameter is deprecated.
+    pub deprecated: Option<&'static str>,
+}
+
+impl<'a> From<&StaticCommand> for Command<'a> {
+    fn from(command: &StaticCommand) -> Self {
+        let mut c = Command::new(command.name)
+            .hidden(command.hidden)
+            .priority(command.priority);
+        if let Some(help_text) = command.help_text {
+            c = c.help(help_text);
+        }
+        if let Some(replacement) = command.deprecated {
+            c = c.deprecated(replacement);
+        }
+        c = command.tags.iter().fold(c, |c, tag| c.tag(*tag));
+        command
+            .parameters
+            .iter()
+            .fold(c, |c, p| c.parameter(Parameter::from(p)))
+    }
+}
+
+impl<'a> From<&StaticParameter> for Parameter<'a> {
+    fn from(parameter: &StaticParameter) -> Self {
+        let mut p = Parameter::new(parameter.name)
+            .hidden(parameter.hidden)
+            .kind(parameter.kind)
+            .required(parameter.required)
+            .repeatable(parameter.repeatable)
+            .negatable(parameter.negatable);
+        if let Some(help_text) = parameter.help_text {
+            p = p.help(help_text);
+        }
+        if let Some(priority) = parameter.priority {
+            p = p.priority(priority);
+        }
+        if let Some(replacement) = parameter.deprecated {
+            p = p.deprecated(replacement);
+        }
+        p = parameter
+            .normalizations
+            .iter()
+            .fold(p, |p, n| p.normalize(*n));
+        parameter
+            .aliases
+            .iter()
+            .fold(p, |p, alias| p.alias(alias))
+            .value_types(parameter.value_types)
+            .choices(parameter.choices)
+    }
+}
@@ -0,0 +1,335 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::ValueType;
+use crate::tokenizer::{SourceLocation, Token};
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// A value given for a parameter.
+///
+/// Along with the `value` itself, this keeps the `raw` text of
+/// the token that it came from and its `location` within the
+/// input, so that an error in the value can be reported against
+/// the original text, including any quotes.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParameterValue {
+    /// The text of the token, as it appeared in the input.
+    pub raw: String,
+    /// The value, with quotes and escaping backslashes removed.
+    /// For a here document, this is the body of the document.
+    pub value: String,
+    /// The location of the token within the input.
+    pub location: SourceLocation,
+    /// The radix that an integer value was given in, such as 16
+    /// for `0x1F`. The `value` itself is always decimal. This is
+    /// `None` for values which aren't integers.
+    pub radix: Option<u32>,
+    /// The type of value that the value was detected as, for a
+    /// named or simple parameter. This tells which was given for
+    /// a parameter which accepts more than one type of value.
+    pub value_type: Option<ValueType>,
+}
+
+impl ParameterValue {
+    /// Construct a `ParameterValue` from the `token` given for it.
+    pub fn new(token: Token) -> Self {
+        ParameterValue {
+            raw: token.text.to_string(),
+            value: token.value().into_owned(),
+            location: token.location,
+            radix: None,
+            value_type: None,
+        }
+    }
+
+    /// The value as a string slice.
+    pub fn as_str(&self) -> &str {
+        &self.value
+    }
+}
+
+impl fmt::Display for ParameterValue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.value.fmt(f)
+    }
+}
+
+impl PartialEq<str> for ParameterValue {
+    fn eq(&self, other: &str) -> bool {
+        self.value == other
+    }
+}
+
+impl PartialEq<&str> for ParameterValue {
+    fn eq(&self, other: &&str) -> bool {
+        self.value == *other
+    }
+}
+
+/// The values given for each parameter while parsing, by
+/// parameter name.
+///
+/// Values can be retrieved as any type implementing `FromStr`:
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, Parameter, ParameterKind, Parser};
+/// use commands::tokenizer::tokenize;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("ping")
+///         .parameter(Parameter::new("host"))
+///         .parameter(Parameter::new("count").kind(ParameterKind::Named)),
+/// );
+/// let mut parser = Parser::new(tree.finalize());
+/// if let Ok(tokens) = tokenize("ping count 3 localhost") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// let parameters = parser.parameters();
+/// assert_eq!(parameters.get::<u32>("count").unwrap(), 3);
+/// assert_eq!(parameters.get::<String>("host").unwrap(), "localhost");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct ParsedParameters {
+    values: BTreeMap<String, Vec<ParameterValue>>,
+}
+
+impl ParsedParameters {
+    /// Construct an empty `ParsedParameters`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a value for the parameter `name`, after any given before.
+    pub fn push(&mut self, name: &str, value: ParameterValue) {
+        self.values.entry(name.to_string()).or_default().push(value);
+    }
+
+    /// Set the value for the parameter `name`, replacing any
+    /// given before.
+    pub fn insert(&mut self, name: &str, value: ParameterValue) {
+        self.values.insert(name.to_string(), vec![value]);
+    }
+
+    /// Remove the values of every parameter.
+    pub fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Whether or not a value has been given for the parameter `name`.
+    pub fn contains(&self, name: &str) -> bool {
+        self.values.contains_key(name)
+    }
+
+    /// The first value given for the parameter `name`, if any.
+    pub fn value(&self, name: &str) -> Option<&ParameterValue> {
+        self.values(name).first()
+    }
+
+    /// All of the values given for the parameter `name`. This is
+    /// empty when the parameter was not given.
+    pub fn values(&self, name: &str) -> &[ParameterValue] {
+        self.values.get(name).map_or(&[], Vec::as_slice)
+    }
+
+    /// The names of the parameters which have been given values,
+    /// in order by name.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.values.keys().map(String::as_str)
+    }
+
+    /// Parse the first value given for the parameter `name`.
+    ///
+    /// A `ParameterError::Missing` is returned when the parameter
+    /// was not given. A `ParameterError::Invalid` is returned with
+    /// the offending value when it can not be parsed.
+    pub fn get<T: FromStr>(&self, name: &str) -> Result<T, ParameterError>
+    where
+        T::Err: fmt::Display,
+    {
+        match self.value(name) {
+            Some(value) => parse_value(name, value),
+            None => Err(ParameterError::Missing(name.to_string())),
+        }
+    }
+
+    /// Parse the first value given for the parameter `name`, if
+    /// it was given.
+    pub fn get_optional<T: FromStr>(&self, name: &str) -> Result<Option<T>, ParameterError>
+    where
+        T::Err: fmt::Display,
+    {
+        self.value(name)
+            .map(|value| parse_value(name, value))
+            .transpose()
+    }
+
+    /// Parse all of the values given for the parameter `name`.
+    /// This is empty when the parameter was not given.
+    pub fn get_all<T: FromStr>(&self, name: &str) -> Result<Vec<T>, ParameterError>
+    where
+        T::Err: fmt::Display,
+    {
+        self.values(name)
+            .iter()
+            .map(|value| parse_value(name, value))
+            .collect()
+    }
+}
+
+fn parse_value<T: FromStr>(name: &str, value: &ParameterValue) -> Result<T, ParameterError>
+where
+    T::Err: fmt::Display,
+{
+    value
+        .value
+        .parse()
+        .map_err(|err: T::Err| ParameterError::Invalid {
+            name: name.to_string(),
+            location: value.location,
+            message: err.to_string(),
+        })
+}
+
+/// A type which can be built from the values given for the
+/// parameters of a command.
+///
+/// This lets a handler work with its parameters as a struct
+/// rather than looking each of them up by name:
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, ExecContext, FromParsedArguments};
+/// use commands::parser::{Parameter, ParameterError, ParameterKind, ParsedParameters, Parser};
+/// use commands::tokenizer::tokenize;
+///
+/// struct ShowRouteArgs {
+///     prefix: String,
+///     limit: Option<u32>,
+/// }
+///
+/// impl FromParsedArguments for ShowRouteArgs {
+///     fn from_parsed_arguments(parameters: &ParsedParameters) -> Result<Self, ParameterError> {
+///         Ok(ShowRouteArgs {
+///             prefix: parameters.get("prefix")?,
+///             limit: parameters.get_optional("limit")?,
+///         })
+///     }
+/// }
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("show route")
+///         .parameter(Parameter::new("prefix").required(true))
+///         .parameter(Parameter::new("limit").kind(ParameterKind::Named))
+///         .handler(|context: &ExecContext| {
+///             let args: ShowRouteArgs = context.extract()?;
+///             assert_eq!(args.prefix, "10.0.0.0/8");
+///             assert_eq!(args.limit, Some(5));
+///             Ok(())
+///         }),
+/// );
+/// let mut parser = Parser::new(tree.finalize());
+/// if let Ok(tokens) = tokenize("show route limit 5 10.0.0.0/8") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// assert!(parser.execute().is_ok());
+/// ```
+pub trait FromParsedArguments: Sized {
+    /// Build a value from the `parameters`, failing with the
+    /// `ParameterError` for the first parameter which is missing
+    /// or has a value that can not be converted.
+    fn from_parsed_arguments(parameters: &ParsedParameters) -> Result<Self, ParameterError>;
+}
+
+/// Errors that retrieving a value from [`ParsedParameters`] can raise.
+#[derive(Clone, Debug)]
+pub enum ParameterError {
+    /// No value was given for the named parameter.
+    Missing(String),
+    /// The value given for a parameter could not be parsed.
+    Invalid {
+        /// The name of the parameter.
+        name: String,
+        /// The location of the offending token within the input.
+        location: SourceLocation,
+        /// The reason that the value could not be parsed.
+        message: String,
+    },
+}
+
+impl Error for ParameterError {}
+
+impl fmt::Display for ParameterError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            ParameterError::Missing(ref name) => {
+                write!(f, "A required parameter is missing: {name}.")
+            }
+            ParameterError::Invalid {
+                ref name,
+                ref message,
+                ..
+            } => write!(f, "The value of {name} is invalid: {message}."),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tokenizer::tokenize;
+
+    #[test]
+    fn quoted_value_keeps_raw_text() {
+        if let Ok(tokens) = tokenize(r#"show "a \"b\" c""#) {
+            let value = ParameterValue::new(tokens[2]);
+            assert_eq!(value.raw, r#""a \"b\" c""#);
+            assert_eq!(value, r#"a "b" c"#);
+            assert_eq!(value.location.start.char, 5);
+            assert_eq!(value.location.end.char, 15);
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn typed_values() {
+        let mut parameters = ParsedParameters::new();
+        if let Ok(tokens) = tokenize("3 x 5") {
+            parameters.push("n", ParameterValue::new(tokens[0]));
+            parameters.push("n", ParameterValue::new(tokens[4]));
+            parameters.insert("s", ParameterValue::new(tokens[2]));
+        } else {
+            panic!("Tokenize failed.");
+        }
+        assert_eq!(parameters.get::<u8>("n").unwrap(), 3);
+        assert_eq!(parameters.get_all::<u8>("n").unwrap(), vec![3, 5]);
+        assert!(parameters.get_all::<u8>("m").unwrap().is_empty());
+        assert_eq!(parameters.get_optional::<u8>("m").unwrap(), None);
+        assert_eq!(parameters.get_optional::<u8>("n").unwrap(), Some(3));
+        match parameters.get::<u8>("m") {
+            Err(err @ ParameterError::Missing(_)) => {
+                assert_eq!(err.to_string(), "A required parameter is missing: m.")
+            }
+            _ => panic!(),
+        }
+        match parameters.get::<u8>("s") {
+            Err(ParameterError::Invalid { name, location, .. }) => {
+                assert_eq!(name, "s");
+                assert_eq!(location.start.char, 2);
+                assert!(parameters
+                    .get::<u8>("s")
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("The value of s is invalid: "));
+            }
+            _ => panic!(),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use super::network::{MacAddress, RangeList};
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+/// The type of value that a named or simple parameter accepts.
+///
+/// A parameter only matches tokens which are valid values of its
+/// type, which helps to resolve what would otherwise be ambiguous
+/// and lets handlers rely upon the values that they are given.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum ValueType {
+    /// Any value.
+    #[default]
+    String,
+    /// A signed integer, like `-3`. It may also be given in
+    /// hexadecimal, octal or binary, like `0x1F`, `0o755` or
+    /// `0b1010`, and is stored in decimal with the radix recorded
+    /// in [`ParameterValue::radix`].
+    ///
+    /// [`ParameterValue::radix`]: crate::parser::ParameterValue::radix
+    Integer,
+    /// A finite floating point number, like `2.5`.
+    Float,
+    /// A boolean. `true`, `yes`, `on` and `1` are accepted for
+    /// `true` and `false`, `no`, `off` and `0` for `false`, all
+    /// without regard to case. The stored value is either `true`
+    /// or `false`.
+    Boolean,
+    /// A path to a file or directory. A leading `~` is replaced
+    /// by the home directory when the value is stored.
+    ///
+    /// With the `fs-completion` feature, paths are completed from
+    /// the entries of the directory being entered.
+    Path,
+    /// An IPv4 address, like `10.0.0.1`.
+    Ipv4,
+    /// An IPv6 address, like `fe80::1`.
+    Ipv6,
+    /// An IPv4 or IPv6 network in CIDR notation, like `10.0.0.0/8`.
+    Cidr,
+    /// A MAC address, like `00:1a:2b:3c:4d:5e`. It is stored in
+    /// that form and can be parsed as a [`MacAddress`].
+    ///
+    /// [`MacAddress`]: crate::parser::MacAddress
+    MacAddress,
+    /// A list of numbers and ranges of numbers, like `1-5,10,12`.
+    /// It can be parsed as a [`RangeList`].
+    ///
+    /// [`RangeList`]: crate::parser::RangeList
+    Ranges,
+}
+
+impl ValueType {
+    /// The name of the type, as shown in help symbols.
+    pub fn name(&self) -> &'static str {
+        match *self {
+            ValueType::String => "string",
+            ValueType::Integer => "integer",
+            ValueType::Float => "float",
+            ValueType::Boolean => "boolean",
+            ValueType::Path => "path",
+            ValueType::Ipv4 => "ipv4",
+            ValueType::Ipv6 => "ipv6",
+            ValueType::Cidr => "cidr",
+            ValueType::MacAddress => "mac",
+            ValueType::Ranges => "ranges",
+        }
+    }
+
+    /// The help symbol for a parameter called `name` of this type,
+    /// like `<count:integer>`. Addresses use the form of the address
+    /// instead, like `<A.B.C.D>`.
+    pub fn help_symbol(&self, name: &str) -> String {
+        match *self {
+            ValueType::String => format!("<{name}>"),
+            ValueType::Ipv4 => "<A.B.C.D>".to_string(),
+            ValueType::Ipv6 => "<X:X::X:X>".to_string(),
+            ValueType::Cidr => "<A.B.C.D/M|X:X::X:X/M>".to_string(),
+            ValueType::MacAddress => "<H:H:H:H:H:H>".to_string(),
+            _ => format!("<{name}:{}>", self.name()),
+        }
+    }
+
+    /// Check the `text` of a value, returning the value to store
+    /// when it is valid.
+    pub fn parse<'a>(&self, text: &'a str) -> Option<Cow<'a, str>> {
+        match *self {
+            ValueType::String | ValueType::Path => Some(Cow::Borrowed(text)),
+            ValueType::Integer => match ValueType::parse_integer(text)? {
+                (_, 10) => Some(Cow::Borrowed(text)),
+                (value, _) => Some(Cow::Owned(value.to_string())),
+            },
+            ValueType::Float => text
+                .parse::<f64>()
+                .ok()
+                .filter(|f| f.is_finite())
+                .map(|_| Cow::Borrowed(text)),
+            ValueType::Ipv4 => text.parse::<Ipv4Addr>().ok().map(|_| Cow::Borrowed(text)),
+            ValueType::Ipv6 => text.parse::<Ipv6Addr>().ok().map(|_| Cow::Borrowed(text)),
+            ValueType::Cidr => {
+                let (address, length) = text.split_once('/')?;
+                let max = match address.parse::<IpAddr>().ok()? {
+                    IpAddr::V4(_) => 32,
+                    IpAddr::V6(_) => 128,
+                };
+                let digits = length.bytes().all(|b| b.is_ascii_digit());
+                match length.parse::<u8>() {
+                    Ok(length) if digits && length <= max => Some(Cow::Borrowed(text)),
+                    _ => None,
+                }
+            }
+            ValueType::MacAddress => text
+                .parse::<MacAddress>()
+                .ok()
+                .map(|mac| Cow::Owned(mac.to_string())),
+            ValueType::Ranges => text
+                .parse::<RangeList>()
+                .ok()
+                .map(|ranges| Cow::Owned(ranges.to_string())),
+            ValueType::Boolean => match text.to_ascii_lowercase().as_str() {
+                "true" | "yes" | "on" | "1" => Some(Cow::Borrowed("true")),
+                "false" | "no" | "off" | "0" => Some(Cow::Borrowed("false")),
+                _ => None,
+            },
+        }
+    }
+
+    /// Parse an integer along with its radix. After any sign, a
+    /// prefix of `0x`, `0o` or `0b` gives a radix of 16, 8 or 2.
+    /// Otherwise, the integer is decimal.
+    ///
+    /// ```
+    /// use commands::parser::ValueType;
+    ///
+    /// assert_eq!(ValueType::parse_integer("0x1F"), Some((31, 16)));
+    /// assert_eq!(ValueType::parse_integer("-0b101"), Some((-5, 2)));
+    /// assert_eq!(ValueType::parse_integer("755"), Some((755, 10)));
+    /// ```
+    pub fn parse_integer(text: &str) -> Option<(i64, u32)> {
+        let unsigned = text.strip_prefix(['-', '+']).unwrap_or(text);
+        let (radix, digits) = match unsigned.get(..2) {
+            Some("0x") | Some("0X") => (16, &unsigned[2..]),
+            Some("0o") | Some("0O") => (8, &unsigned[2..]),
+            Some("0b") | Some("0B") => (2, &unsigned[2..]),
+            _ => return text.parse::<i64>().ok().map(|value| (value, 10)),
+        };
+        if digits.starts_with(['-', '+']) {
+            return None;
+        }
+        let magnitude = i128::from(u64::from_str_radix(digits, radix).ok()?);
+        let value = if text.starts_with('-') {
+            -magnitude
+        } else {
+            magnitude
+        };
+        i64::try_from(value).ok().map(|value| (value, radix))
+    }
+
+    /// Replace a leading `~` in a path with the `home` directory.
+    /// Other paths, like `~user`, are returned unchanged.
+    pub fn expand_home<'a>(path: &'a str, home: Option<&str>) -> Cow<'a, str> {
+        match (path.strip_prefix('~'), home) {
+            (Some(rest), Some(home)) if rest.is_empty() || rest.starts_with('/') => {
+                Cow::Owned(format!("{}{rest}", home.trim_end_matches('/')))
+            }
+            _ => Cow::Borrowed(path),
+        }
+    }
+
+    /// Whether or not the `text` is a valid value of this type.
+    pub fn accepts(&self, text: &str) -> bool {
+        self.parse(text).is_some()
+    }
+
+    /// The values of this type which complete the `text` entered so
+    /// far, if the type is able to supply them.
+    #[cfg_attr(not(feature = "fs-completion"), allow(unused_variables))]
+    pub fn complete(&self, text: &str) -> Option<Vec<String>> {
+        match *self {
+            #[cfg(feature = "fs-completion")]
+            ValueType::Path => Some(complete_path(text)),
+            _ => None,
+        }
+    }
+}
+
+/// List the entries of the directory named by `text` up to its last
+/// `/` which start with the rest of `text`. Directories end with a `/`
+/// so that completion can continue into them and hidden entries are
+/// only listed when the rest of `text` starts with a `.`.
+#[cfg(feature = "fs-completion")]
+fn complete_path(text: &str) -> Vec<String> {
+    let (dir, partial) = match text.rfind('/') {
+        Some(i) => (&text[..=i], &text[i + 1..]),
+        None => ("", text),
+    };
+    let entries = match std::fs::read_dir(if dir.is_empty() { "." } else { dir }) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut paths = entries
+        .filter_map(Result::ok)
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            if !name.starts_with(partial) || (name.starts_with('.') && !partial.starts_with('.')) {
+                return None;
+            }
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            Some(format!("{dir}{name}{}", if is_dir { "/" } else { "" }))
+        })
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_values() {
+        assert!(ValueType::String.accepts("anything"));
+        assert!(ValueType::Integer.accepts("-42"));
+        assert!(!ValueType::Integer.accepts("4.2"));
+        assert_eq!(ValueType::Integer.parse("0o755").unwrap(), "493");
+        assert_eq!(
+            ValueType::Integer.parse("-0x8000000000000000").unwrap(),
+            "-9223372036854775808"
+        );
+        assert!(!ValueType::Integer.accepts("0x8000000000000000"));
+        assert!(!ValueType::Integer.accepts("0x-1"));
+        assert!(!ValueType::Integer.accepts("0b102"));
+        assert!(!ValueType::Integer.accepts("0x"));
+        assert!(ValueType::Float.accepts("4.2"));
+        assert!(!ValueType::Float.accepts("inf"));
+        assert!(!ValueType::Float.accepts("x"));
+        assert_eq!(ValueType::Boolean.parse("Yes").unwrap(), "true");
+        assert_eq!(ValueType::Boolean.parse("off").unwrap(), "false");
+        assert!(!ValueType::Boolean.accepts("maybe"));
+        assert_eq!(ValueType::Integer.help_symbol("count"), "<count:integer>");
+        assert_eq!(ValueType::String.help_symbol("name"), "<name>");
+        assert_eq!(ValueType::Path.help_symbol("file"), "<file:path>");
+    }
+
+    #[test]
+    fn parse_addresses() {
+        assert!(ValueType::Ipv4.accepts("10.0.0.1"));
+        assert!(!ValueType::Ipv4.accepts("10.0.0.256"));
+        assert!(!ValueType::Ipv4.accepts("fe80::1"));
+        assert!(ValueType::Ipv6.accepts("fe80::1"));
+        assert!(!ValueType::Ipv6.accepts("10.0.0.1"));
+        assert!(ValueType::Cidr.accepts("10.0.0.0/8"));
+        assert!(ValueType::Cidr.accepts("fe80::/64"));
+        assert!(!ValueType::Cidr.accepts("10.0.0.0/33"));
+        assert!(!ValueType::Cidr.accepts("10.0.0.0/+8"));
+        assert!(!ValueType::Cidr.accepts("10.0.0.0"));
+        assert_eq!(
+            ValueType::MacAddress.parse("001A.2B3C.4D5E").unwrap(),
+            "00:1a:2b:3c:4d:5e"
+        );
+        assert!(!ValueType::MacAddress.accepts("00:1a:2b"));
+        assert_eq!(ValueType::Ranges.parse("1-5,7-7").unwrap(), "1-5,7");
+        assert!(!ValueType::Ranges.accepts("5-1"));
+        assert_eq!(ValueType::Ranges.help_symbol("vlans"), "<vlans:ranges>");
+        assert_eq!(ValueType::Ipv4.help_symbol("src"), "<A.B.C.D>");
+    }
+
+    #[cfg(feature = "fs-completion")]
+    #[test]
+    fn complete_paths() {
+        let dir = std::env::temp_dir().join(format!("commands-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("bin")).unwrap();
+        std::fs::write(dir.join("binary"), "").unwrap();
+        std::fs::write(dir.join(".bin"), "").unwrap();
+        let text = format!("{}/bi", dir.display());
+        let paths = ValueType::Path.complete(&text).unwrap();
+        assert_eq!(
+            paths,
+            vec![
+                format!("{}/bin/", dir.display()),
+                format!("{}/binary", dir.display())
+            ]
+        );
+        assert_eq!(
+            ValueType::Path
+                .complete(&format!("{}/.", dir.display()))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(ValueType::Path
+            .complete("/does/not/exist/")
+            .unwrap()
+            .is_empty());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn expand_home() {
+        let home = Some("/home/me/");
+        assert_eq!(ValueType::expand_home("~", home), "/home/me");
+        assert_eq!(ValueType::expand_home("~/a", home), "/home/me/a");
+        assert_eq!(ValueType::expand_home("~you/a", home), "~you/a");
+        assert_eq!(ValueType::expand_home("a/~", home), "a/~");
+        assert_eq!(ValueType::expand_home("~/a", None), "~/a");
+    }
+}
@@ -0,0 +1,115 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Rendering
+//!
+//! Format completions and nodes as aligned columns of help symbols
+//! and help text, like the output of `?` on a router:
+//!
+//! ```text
+//!   interface  Show interfaces
+//!   ip         Show IP information
+//! ```
+//!
+//! Columns are aligned by their width on a terminal, so wide
+//! characters are accounted for. When help text has more than one
+//! line, the lines after the first are indented to the column of
+//! the help text.
+//!
+//! ```
+//! use commands::parser::{Command, CommandTree, Parser};
+//! use commands::render;
+//!
+//! let mut tree = CommandTree::new();
+//! tree.command(Command::new("show interface").help("Show interfaces"));
+//! tree.command(Command::new("show ip").help("Show IP information"));
+//!
+//! let mut parser = Parser::new(tree.finalize());
+//! let completion = parser.complete_line("show ", 5);
+//! assert_eq!(
+//!     render::completions(&completion.completions),
+//!     "  interface  Show interfaces\n  ip         Show IP information\n"
+//! );
+//! ```
+
+use crate::parser::{Completion, Node};
+use crate::util::display_width;
+use std::rc::Rc;
+
+/// Format `completions` as a column of their help symbols and a
+/// column of their help text, one per line.
+pub fn completions(completions: &[Completion]) -> String {
+    columns(
+        completions
+            .iter()
+            .map(|c| (c.help_symbol.as_str(), c.help_text.as_str())),
+    )
+}
+
+/// Format `nodes` as a column of their help symbols and a column
+/// of their help text, one per line. This suits the nodes of a
+/// `ParseError`, like those which could have been accepted after
+/// `ParseError::NoMatches`.
+pub fn nodes(nodes: &[Rc<Node>]) -> String {
+    columns(
+        nodes
+            .iter()
+            .map(|n| (n.node().help_symbol.as_str(), n.node().help_text.as_str())),
+    )
+}
+
+/// Format `rows` of a symbol and its help text as aligned columns.
+/// Each row is indented by two spaces and the columns are separated
+/// by at least two spaces. A row with no help text is just the
+/// symbol.
+///
+/// ```
+/// use commands::render;
+///
+/// let table = render::columns([("<cr>", ""), ("detail", "Show more\nof it")]);
+/// assert_eq!(table, "  <cr>\n  detail  Show more\n          of it\n");
+/// ```
+pub fn columns<'a, I>(rows: I) -> String
+where
+    I: IntoIterator<Item = (&'a str, &'a str)>,
+{
+    let rows = rows.into_iter().collect::<Vec<_>>();
+    let width = rows
+        .iter()
+        .map(|(symbol, _)| display_width(symbol))
+        .max()
+        .unwrap_or(0);
+    let mut table = String::new();
+    for (symbol, help_text) in rows {
+        table.push_str("  ");
+        table.push_str(symbol);
+        let mut lines = help_text.lines();
+        if let Some(first) = lines.next() {
+            let padding = width - display_width(symbol) + 2;
+            table.push_str(&" ".repeat(padding));
+            table.push_str(first);
+            for line in lines {
+                table.push('\n');
+                table.push_str(&" ".repeat(width + 4));
+                table.push_str(line);
+            }
+        }
+        table.push('\n');
+    }
+    table
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn wide_symbols() {
+        let table = columns([("名前", "Name"), ("id", "Identifier")]);
+        assert_eq!(table, "  名前  Name\n  id    Identifier\n");
+        assert_eq!(columns([]), "");
+    }
+}
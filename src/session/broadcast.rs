@@ -0,0 +1,249 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::parser::{InvocationOutcome, InvocationRecord};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+/// Announces the configuration commands executed by one session to
+/// the other sessions of an application, which may be running on
+/// other threads, so that they can tell their users, as with
+/// `config changed by admin`.
+///
+/// Each session [`subscribe`]s under its name and gives the
+/// [`sink`] for its name to its command tree or parser builder.
+/// A session is never sent its own announcements.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree, Parameter, ParserBuilder};
+/// use commands::session::Broadcast;
+/// use commands::tokenizer::tokenize;
+///
+/// let broadcast = Broadcast::new();
+/// let operator = broadcast.subscribe("operator");
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(
+///     Command::new("hostname")
+///         .parameter(Parameter::new("name"))
+///         .handler(|_| Ok(())),
+/// );
+/// let admin = ParserBuilder::new(tree.finalize())
+///     .invocation_sink(broadcast.sink("admin", |record| record.path == "hostname"));
+///
+/// let mut parser = admin.build();
+/// if let Ok(tokens) = tokenize("hostname r1") {
+///     parser.parse(tokens).unwrap();
+/// }
+/// parser.execute().unwrap();
+///
+/// let announcements = operator.pending();
+/// assert_eq!(announcements[0].session, "admin");
+/// assert_eq!(announcements[0].line, "hostname r1");
+/// ```
+///
+/// [`subscribe`]: Broadcast::subscribe
+/// [`sink`]: Broadcast::sink
+#[derive(Clone, Default)]
+pub struct Broadcast {
+    subscribers: Arc<Mutex<Subscribers>>,
+}
+
+#[derive(Default)]
+struct Subscribers {
+    next_id: usize,
+    list: Vec<Subscriber>,
+}
+
+struct Subscriber {
+    id: usize,
+    session: String,
+    sender: Sender<Announcement>,
+    enabled: Arc<AtomicBool>,
+}
+
+/// A configuration command executed by a session, as sent to the
+/// other sessions by a [`Broadcast`].
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Announcement {
+    /// The name of the session which executed the command.
+    pub session: String,
+    /// The full path of the command.
+    pub path: String,
+    /// The command in canonical form.
+    pub line: String,
+}
+
+impl Broadcast {
+    /// Construct a `Broadcast` without any subscribers.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Subscribe the `session` to the announcements of the other
+    /// sessions. It is unsubscribed when the [`Subscription`] is
+    /// dropped.
+    pub fn subscribe(&self, session: &str) -> Subscription {
+        let (sender, receiver) = mpsc::channel();
+        let enabled = Arc::new(AtomicBool::new(true));
+        let mut subscribers = self.lock();
+        let id = subscribers.next_id;
+        subscribers.next_id += 1;
+        subscribers.list.push(Subscriber {
+            id,
+            session: session.to_string(),
+            sender,
+            enabled: Arc::clone(&enabled),
+        });
+        Subscription {
+            id,
+            receiver,
+            enabled,
+            broadcast: self.clone(),
+        }
+    }
+
+    /// Send the `announcement` to each subscriber which has it
+    /// enabled, other than the session that it came from.
+    pub fn announce(&self, announcement: &Announcement) {
+        let subscribers = self.lock();
+        for subscriber in &subscribers.list {
+            if subscriber.session != announcement.session
+                && subscriber.enabled.load(Ordering::Relaxed)
+            {
+                // A subscription which was dropped during the
+                // announcement has already removed itself.
+                let _ = subscriber.sender.send(announcement.clone());
+            }
+        }
+    }
+
+    /// An invocation sink for the `session`, which announces each
+    /// command that succeeds and that `is_configuration` selects.
+    pub fn sink<F>(&self, session: &str, is_configuration: F) -> impl Fn(&InvocationRecord)
+    where
+        F: Fn(&InvocationRecord) -> bool + 'static,
+    {
+        let broadcast = self.clone();
+        let session = session.to_string();
+        move |record: &InvocationRecord| {
+            if record.outcome == InvocationOutcome::Succeeded && is_configuration(record) {
+                broadcast.announce(&Announcement {
+                    session: session.clone(),
+                    path: record.path.clone(),
+                    line: record.line.clone(),
+                });
+            }
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Subscribers> {
+        // The list is never left inconsistent, so a panic while it
+        // was locked doesn't matter.
+        self.subscribers
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// The subscription of a session to a [`Broadcast`], through which
+/// it receives announcements.
+pub struct Subscription {
+    id: usize,
+    receiver: Receiver<Announcement>,
+    enabled: Arc<AtomicBool>,
+    broadcast: Broadcast,
+}
+
+impl Subscription {
+    /// Whether or not announcements are being received.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled.load(Ordering::Relaxed)
+    }
+
+    /// Start or stop receiving announcements, as when the user of
+    /// the session turns them off. Announcements made while they
+    /// are stopped are not received later.
+    pub fn set_enabled(&self, enabled: bool) {
+        self.enabled.store(enabled, Ordering::Relaxed);
+    }
+
+    /// The announcements received since they were last taken,
+    /// without waiting for more.
+    pub fn pending(&self) -> Vec<Announcement> {
+        self.receiver.try_iter().collect()
+    }
+
+    /// The receiver of the announcements, to wait for them or to
+    /// select over them with other events.
+    pub fn receiver(&self) -> &Receiver<Announcement> {
+        &self.receiver
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        self.broadcast.lock().list.retain(|s| s.id != self.id);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::thread;
+
+    fn announcement(session: &str, line: &str) -> Announcement {
+        Announcement {
+            session: session.to_string(),
+            path: line.to_string(),
+            line: line.to_string(),
+        }
+    }
+
+    #[test]
+    fn announcements() {
+        let broadcast = Broadcast::new();
+        let admin = broadcast.subscribe("admin");
+        let operator = broadcast.subscribe("operator");
+        let remote = broadcast.clone();
+        let viewer = thread::spawn(move || {
+            let viewer = remote.subscribe("viewer");
+            remote.announce(&announcement("viewer", "save"));
+            viewer
+        })
+        .join()
+        .unwrap();
+
+        operator.set_enabled(false);
+        broadcast.announce(&announcement("admin", "hostname r1"));
+        operator.set_enabled(true);
+        assert!(operator.is_enabled());
+
+        let lines = |s: &Subscription| s.pending().into_iter().map(|a| a.line).collect::<Vec<_>>();
+        assert_eq!(lines(&admin), ["save"]);
+        assert_eq!(lines(&operator), ["save"]);
+        assert_eq!(lines(&viewer), ["hostname r1"]);
+
+        drop(viewer);
+        assert_eq!(broadcast.lock().list.len(), 2);
+        let sink = broadcast.sink("operator", |record| record.path.starts_with("interface"));
+        let record = |path: &str, outcome| InvocationRecord {
+            path: path.to_string(),
+            line: path.to_string(),
+            parameters: vec![],
+            outcome,
+        };
+        sink(&record("interface mtu", InvocationOutcome::Succeeded));
+        sink(&record(
+            "interface up",
+            InvocationOutcome::Failed("No.".to_string()),
+        ));
+        sink(&record("show", InvocationOutcome::Succeeded));
+        assert_eq!(lines(&admin), ["interface mtu"]);
+        assert!(operator.pending().is_empty());
+    }
+}
@@ -0,0 +1,549 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Built-in commands
+//!
+//! These are optional and can be added to a [`CommandTree`] along
+//! with the application's own commands. Each is given the session
+//! state that it works with.
+//!
+//! File arguments are path parameters, using `ValueType::Path`,
+//! so a leading `~` is expanded to the home directory.
+//!
+//! The filters, [`count`] and [`json`] along with `include`,
+//! `exclude` and `begin` when the `regex` feature is enabled,
+//! work with the output of the command before them in a pipeline,
+//! as in `show log | include error`. They can be added with
+//! [`CommandTree::filter`] so that they are only found after a `|`.
+//!
+//! [`CommandTree`]: crate::parser::CommandTree
+//! [`CommandTree::filter`]: crate::parser::CommandTree::filter
+
+use super::script::{self, ScriptError, ScriptErrorKind};
+use super::{History, Variables};
+use crate::parser::{
+    Command, CommandOutput, CommandTag, ExecContext, Parameter, Parser, ValueType,
+};
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::fs;
+use std::rc::Rc;
+use std::thread;
+use std::time::Duration;
+
+/// The number of scripts that the `source` command may nest
+/// within each other.
+pub const MAX_SOURCE_DEPTH: usize = 16;
+
+/// The `history` command, which lists the entries of the `history`
+/// along with their numbers.
+///
+/// When given a `filter`, only the entries containing it are
+/// listed. The `filter` completes over the stored entries.
+pub fn history(history: Rc<RefCell<History>>) -> Command<'static> {
+    let entries = Rc::clone(&history);
+    Command::new("history")
+        .help("List the lines entered during this session.")
+        .parameter(
+            Parameter::new("filter")
+                .help("Only list the lines containing this text.")
+                .completer(move |text| {
+                    entries
+                        .borrow()
+                        .entries()
+                        .iter()
+                        .rev()
+                        .filter(|e| e.starts_with(text))
+                        .cloned()
+                        .collect()
+                }),
+        )
+        .handler(move |context| {
+            let filter = context.parameter("filter").unwrap_or("");
+            let history = history.borrow();
+            let mut output = context.output();
+            for (i, entry) in history.entries().iter().enumerate() {
+                if entry.contains(filter) {
+                    output.line(format!("{:5}  {}", i + 1, entry));
+                }
+            }
+            Ok(())
+        })
+}
+
+/// The `set` command, which sets a variable in `variables` with
+/// `set <name> <value>` or lists the variables when used alone.
+pub fn set(variables: Rc<RefCell<Variables>>) -> Command<'static> {
+    let names = Rc::clone(&variables);
+    Command::new("set")
+        .help("Set a variable or list the variables.")
+        .parameter(
+            Parameter::new("name")
+                .help("The name of the variable.")
+                .completer(move |_| variable_names(&names.borrow())),
+        )
+        .parameter(Parameter::new("value").help("The value of the variable."))
+        .handler(move |context| {
+            match (context.parameter("name"), context.parameter("value")) {
+                (Some(name), Some(value)) => variables.borrow_mut().set(name, value),
+                (Some(_), None) => return Err("A value is required.".into()),
+                _ => {
+                    let mut output = context.output();
+                    for (name, value) in variables.borrow().iter() {
+                        output.line(format!("{name} = {value}"));
+                    }
+                }
+            }
+            Ok(())
+        })
+}
+
+/// The `unset` command, which removes a variable from `variables`.
+pub fn unset(variables: Rc<RefCell<Variables>>) -> Command<'static> {
+    let names = Rc::clone(&variables);
+    Command::new("unset")
+        .help("Remove a variable.")
+        .parameter(
+            Parameter::new("name")
+                .required(true)
+                .help("The name of the variable.")
+                .completer(move |_| variable_names(&names.borrow())),
+        )
+        .handler(move |context| {
+            let name = context.parameters().get::<String>("name")?;
+            match variables.borrow_mut().unset(&name) {
+                Some(_) => Ok(()),
+                None => Err(format!("No variable named {name}.").into()),
+            }
+        })
+}
+
+/// The `source` command, which runs each line of a script file as a
+/// command with [`script::run`], sharing the `variables`.
+///
+/// Scripts may use `source` themselves, up to [`MAX_SOURCE_DEPTH`]
+/// scripts deep. It is tagged as `CommandTag::FileAccess`, so a
+/// safe `ExecutionProfile` blocks it.
+pub fn source(variables: Rc<RefCell<Variables>>) -> Command<'static> {
+    let depth = Cell::new(0);
+    Command::new("source")
+        .help("Run the commands in a file.")
+        .tag(CommandTag::FileAccess)
+        .parameter(
+            Parameter::new("file")
+                .required(true)
+                .value_type(ValueType::Path)
+                .help("The file to read commands from."),
+        )
+        .handler(move |context| {
+            let path = context.parameters().get::<String>("file")?;
+            if depth.get() >= MAX_SOURCE_DEPTH {
+                return Err(Box::new(ScriptError {
+                    line: 0,
+                    column: None,
+                    kind: ScriptErrorKind::TooDeep(MAX_SOURCE_DEPTH),
+                }));
+            }
+            let text = fs::read_to_string(&path)?;
+            depth.set(depth.get() + 1);
+            let result = script::run(context.root(), Some(&variables), &text);
+            depth.set(depth.get() - 1);
+            context.output().append(result?);
+            Ok(())
+        })
+}
+
+/// The `watch` command, which runs the command following it every
+/// `interval` seconds, as in `watch 2 show interface`. It wraps all
+/// of the commands, so the watched command completes as usual.
+///
+/// The output of each run is given to `report`, which returns
+/// whether or not to continue watching. This is where the output
+/// is shown and where cancellation, like a key press, is checked.
+/// It is the only way to cancel, and it isn't called while waiting
+/// for the next run, so a cancellation is seen up to an `interval`
+/// after it happens.
+pub fn watch<F>(report: F) -> Command<'static>
+where
+    F: Fn(&CommandOutput) -> bool + 'static,
+{
+    Command::new("watch")
+        .help("Run a command repeatedly.")
+        .wraps(String::new())
+        .parameter(
+            Parameter::new("interval")
+                .required(true)
+                .value_type(ValueType::Float)
+                .help("The number of seconds between runs."),
+        )
+        .handler(move |context| {
+            let interval = context.parameters().get::<f64>("interval")?;
+            if interval < 0.0 {
+                return Err("The interval can not be negative.".into());
+            }
+            let interval = Duration::try_from_secs_f64(interval)?;
+            let tokens = context.wrapped_tokens();
+            if tokens.is_empty() {
+                return Err("A command to watch is required.".into());
+            }
+            loop {
+                let mut parser = Parser::new(Rc::clone(context.root()));
+                parser
+                    .parse(tokens.to_vec())
+                    .map_err(|err| err.to_string())?;
+                parser.verify()?;
+                if !report(&parser.execute()?) {
+                    return Ok(());
+                }
+                thread::sleep(interval);
+            }
+        })
+}
+
+/// The `include` filter, which keeps the lines of its input that
+/// match a regular expression.
+#[cfg(feature = "regex")]
+pub fn include() -> Command<'static> {
+    regex_filter("include", "Keep the lines matching a pattern.", true)
+}
+
+/// The `exclude` filter, which removes the lines of its input that
+/// match a regular expression.
+#[cfg(feature = "regex")]
+pub fn exclude() -> Command<'static> {
+    regex_filter("exclude", "Remove the lines matching a pattern.", false)
+}
+
+/// The `begin` filter, which keeps the lines of its input from
+/// the first that matches a regular expression.
+#[cfg(feature = "regex")]
+pub fn begin() -> Command<'static> {
+    Command::new("begin")
+        .help("Keep the lines from the first matching a pattern.")
+        .parameter(
+            Parameter::new("pattern")
+                .required(true)
+                .help("A regular expression."),
+        )
+        .handler(|context| {
+            let pattern = regex::Regex::new(&context.parameters().get::<String>("pattern")?)?;
+            let input = filter_input(context)?;
+            let mut output = context.output();
+            let lines = input.lines().iter();
+            for line in lines.skip_while(|line| !pattern.is_match(line)) {
+                output.line(line.clone());
+            }
+            Ok(())
+        })
+}
+
+#[cfg(feature = "regex")]
+fn regex_filter(name: &'static str, help: &'static str, keep: bool) -> Command<'static> {
+    Command::new(name)
+        .help(help)
+        .parameter(
+            Parameter::new("pattern")
+                .required(true)
+                .help("A regular expression."),
+        )
+        .handler(move |context| {
+            let pattern = regex::Regex::new(&context.parameters().get::<String>("pattern")?)?;
+            let input = filter_input(context)?;
+            let mut output = context.output();
+            for line in input.lines() {
+                if pattern.is_match(line) == keep {
+                    output.line(line.clone());
+                }
+            }
+            Ok(())
+        })
+}
+
+/// The `count` filter, which counts the lines of its input.
+pub fn count() -> Command<'static> {
+    Command::new("count")
+        .help("Count the lines of output.")
+        .handler(|context| {
+            let count = filter_input(context)?.lines().len();
+            context.output().line(count.to_string());
+            Ok(())
+        })
+}
+
+/// The `json` filter, which formats the lines of its input as a
+/// JSON array of strings on a single line.
+pub fn json() -> Command<'static> {
+    Command::new("json")
+        .help("Format the output as JSON.")
+        .handler(|context| {
+            let lines = filter_input(context)?
+                .lines()
+                .iter()
+                .map(|line| json_string(line))
+                .collect::<Vec<_>>();
+            context.output().line(format!("[{}]", lines.join(",")));
+            Ok(())
+        })
+}
+
+/// The output that a filter works with.
+fn filter_input<'c>(context: &'c ExecContext) -> Result<&'c CommandOutput, Box<dyn Error>> {
+    context.input().ok_or_else(|| {
+        format!(
+            "{} filters the output of another command.",
+            context.command().node.name
+        )
+        .into()
+    })
+}
+
+/// Quote and escape `text` as a JSON string.
+fn json_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn variable_names(variables: &Variables) -> Vec<String> {
+    variables.iter().map(|(name, _)| name.to_string()).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::{CommandTree, ExecutionProfile, Parser};
+    use crate::tokenizer::tokenize;
+    use std::borrow::Cow;
+
+    #[test]
+    fn history_command() {
+        let state = Rc::new(RefCell::new(History::new()));
+        state.borrow_mut().push("show log");
+        state.borrow_mut().push("ping host");
+        let mut tree = CommandTree::new();
+        tree.command(history(Rc::clone(&state)));
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("history ping") {
+            assert!(parser.parse(tokens).is_ok());
+            let output = parser.execute().unwrap();
+            assert_eq!(output.lines(), ["    2  ping host"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("history s") {
+            assert!(parser.advance(tokens[0]).is_ok());
+            let completions = parser.complete(Some(tokens[2]));
+            assert_eq!(completions.len(), 1);
+            assert!(completions[0]
+                .options
+                .iter()
+                .any(|o| o.option_string == r"show\ log" && o.complete));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn source_command() {
+        let dir = std::env::temp_dir().join(format!("commands-source-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("script");
+        let recursive = dir.join("recursive");
+        fs::write(&script, "set greeting hello\n# Comment\nshow $greeting\n").unwrap();
+        fs::write(&recursive, format!("source {}\n", recursive.display())).unwrap();
+
+        let state = Rc::new(RefCell::new(Variables::new()));
+        let mut tree = CommandTree::new();
+        tree.command(set(Rc::clone(&state)));
+        tree.command(source(Rc::clone(&state)));
+        tree.command(
+            Command::new("show")
+                .parameter(Parameter::new("what"))
+                .handler(|context| {
+                    let what = context.parameter("what").unwrap_or("");
+                    context.output().line(what.to_string());
+                    Ok(())
+                }),
+        );
+        let root = tree.finalize();
+
+        let output = script::run(&root, None, &format!("source {}", script.display())).unwrap();
+        assert_eq!(output.lines(), ["hello"]);
+        assert_eq!(state.borrow().get("greeting"), Some("hello"));
+
+        let err = script::run(&root, None, &format!("source {}", recursive.display()));
+        assert!(err.is_err());
+
+        let profile = Rc::new(Cell::new(ExecutionProfile::Safe));
+        tree.execution_profile(Rc::clone(&profile));
+        let root = tree.finalize();
+        let err = script::run(&root, None, &format!("source {}", script.display()));
+        assert!(err.is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn variable_commands() {
+        let state = Rc::new(RefCell::new(Variables::new()));
+        let mut tree = CommandTree::new();
+        tree.command(set(Rc::clone(&state)));
+        tree.command(unset(Rc::clone(&state)));
+        tree.command(Command::new("connect").parameter(Parameter::new("host")));
+        let completer = Rc::clone(&state);
+        tree.value_completer(move |text| completer.borrow().complete(text));
+        let root = tree.finalize();
+
+        let run = |line: &str| {
+            let line = state.borrow().substitute(line).map(Cow::into_owned);
+            let line = line.unwrap();
+            let mut parser = Parser::new(Rc::clone(&root));
+            match tokenize(&line) {
+                Ok(tokens) => assert!(parser.parse(tokens).is_ok()),
+                _ => panic!("Tokenize failed."),
+            }
+            parser.execute().map(|o| o.lines().to_vec())
+        };
+        assert!(run("set host 10.0.0.1").is_ok());
+        assert_eq!(state.borrow().get("host"), Some("10.0.0.1"));
+        assert_eq!(run("set").unwrap(), ["host = 10.0.0.1"]);
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("connect $h") {
+            assert!(parser.advance(tokens[0]).is_ok());
+            let completions = parser.complete(Some(tokens[2]));
+            assert!(completions[0]
+                .options
+                .iter()
+                .any(|o| o.option_string == "$host"));
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        assert!(run("unset host").is_ok());
+        assert!(run("unset host").is_err());
+        assert!(state.borrow().get("host").is_none());
+    }
+
+    #[test]
+    fn watch_command() {
+        let runs = Rc::new(Cell::new(0));
+        let mut tree = CommandTree::new();
+        let count = Rc::clone(&runs);
+        tree.command(
+            Command::new("show log")
+                .parameter(Parameter::new("lines").value_type(ValueType::Integer))
+                .handler(move |context| {
+                    count.set(count.get() + 1);
+                    let lines = context.parameter("lines").unwrap_or("all");
+                    context.output().line(lines);
+                    Ok(())
+                }),
+        );
+        let seen = Rc::new(RefCell::new(vec![]));
+        let report = Rc::clone(&seen);
+        tree.command(watch(move |output| {
+            report.borrow_mut().push(output.lines().join(""));
+            report.borrow().len() < 3
+        }));
+        let root = tree.finalize();
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("watch 0 show log 5") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.verify().is_ok());
+            assert!(parser.execute().is_ok());
+            assert_eq!(runs.get(), 3);
+            assert_eq!(*seen.borrow(), ["5", "5", "5"]);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(Rc::clone(&root));
+        if let Ok(tokens) = tokenize("watch 1e300 show log") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.execute().is_err());
+            assert_eq!(runs.get(), 3);
+        } else {
+            panic!("Tokenize failed.");
+        }
+
+        let mut parser = Parser::new(root);
+        if let Ok(tokens) = tokenize("watch 1") {
+            assert!(parser.parse(tokens).is_ok());
+            assert!(parser.execute().is_err());
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    fn filter_tree() -> Rc<crate::parser::Node> {
+        let mut tree = CommandTree::new();
+        tree.command(Command::new("show log").handler(|context| {
+            context.output().line("link up");
+            context.output().line("link \"eth0\" down");
+            Ok(())
+        }));
+        tree.command(count());
+        tree.command(json());
+        #[cfg(feature = "regex")]
+        {
+            tree.command(include());
+            tree.command(exclude());
+            tree.command(begin());
+        }
+        tree.finalize()
+    }
+
+    fn run(root: &Rc<crate::parser::Node>, line: &str) -> Vec<String> {
+        match tokenize(line) {
+            Ok(tokens) => match crate::parser::execute_pipeline(root, tokens) {
+                Ok(output) => output.lines().to_vec(),
+                Err(err) => vec![err.to_string()],
+            },
+            Err(_) => panic!("Tokenize failed."),
+        }
+    }
+
+    #[test]
+    fn filters() {
+        let root = filter_tree();
+        assert_eq!(run(&root, "show log | count"), ["2"]);
+        assert_eq!(
+            run(&root, "show log | json"),
+            [r#"["link up","link \"eth0\" down"]"#]
+        );
+        assert_eq!(run(&root, "count"), ["The command failed."]);
+    }
+
+    #[cfg(feature = "regex")]
+    #[test]
+    fn regex_filters() {
+        let root = filter_tree();
+        assert_eq!(
+            run(&root, "show log | include 'd[a-z]+n$'"),
+            [r#"link "eth0" down"#]
+        );
+        assert_eq!(run(&root, "show log | exclude down | count"), ["1"]);
+        assert_eq!(run(&root, "show log | begin eth | count"), ["1"]);
+        assert_eq!(run(&root, "show log | begin up | count"), ["2"]);
+    }
+}
@@ -0,0 +1,202 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fmt;
+
+/// The lines which have been entered during a session.
+///
+/// Entries are numbered from 1, in the order that they were
+/// entered. Previous entries can be referred to within a line
+/// and are replaced by [`expand`] before the line is tokenized.
+///
+/// [`expand`]: History::expand
+#[derive(Clone, Debug, Default)]
+pub struct History {
+    entries: Vec<String>,
+    max_entries: Option<usize>,
+}
+
+impl History {
+    /// Construct an empty `History`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Limit the number of entries which are kept. The oldest
+    /// entries are discarded first.
+    pub fn max_entries(mut self, max_entries: usize) -> Self {
+        self.max_entries = Some(max_entries);
+        self
+    }
+
+    /// Record a line. Blank lines are not recorded.
+    pub fn push(&mut self, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        self.entries.push(line.to_string());
+        if let Some(max) = self.max_entries {
+            if self.entries.len() > max {
+                let excess = self.entries.len() - max;
+                self.entries.drain(..excess);
+            }
+        }
+    }
+
+    /// The recorded entries, oldest first.
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// The entry numbered `n`, counting from 1.
+    pub fn get(&self, n: usize) -> Option<&str> {
+        n.checked_sub(1)
+            .and_then(|i| self.entries.get(i))
+            .map(String::as_str)
+    }
+
+    /// The most recent entry starting with `prefix`.
+    pub fn find(&self, prefix: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .rev()
+            .find(|e| e.starts_with(prefix))
+            .map(String::as_str)
+    }
+
+    /// Replace references to previous entries within `line`:
+    ///
+    /// * `!!` is replaced by the most recent entry.
+    /// * `!n` is replaced by the entry numbered `n`.
+    /// * `!prefix` is replaced by the most recent entry which
+    ///   starts with `prefix`.
+    ///
+    /// References are only recognized at the start of a word and
+    /// not within single quotes or after a backslash. A `!` which
+    /// is followed by whitespace or ends the line is left alone.
+    ///
+    /// The line is only copied when a reference has been replaced.
+    ///
+    /// ```
+    /// use commands::session::History;
+    ///
+    /// let mut history = History::new();
+    /// history.push("show interface eth0");
+    /// history.push("ping 10.0.0.1");
+    /// assert_eq!(history.expand("!!").unwrap(), "ping 10.0.0.1");
+    /// assert_eq!(history.expand("!1").unwrap(), "show interface eth0");
+    /// assert_eq!(history.expand("!sh").unwrap(), "show interface eth0");
+    /// assert!(history.expand("!3").is_err());
+    /// ```
+    pub fn expand<'line>(&self, line: &'line str) -> Result<Cow<'line, str>, HistoryError> {
+        let mut expanded = String::new();
+        let mut copied = 0;
+        let mut single_quoted = false;
+        let mut escaped = false;
+        let mut word_start = true;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let at_word_start = word_start;
+            word_start = c.is_whitespace();
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match c {
+                '\\' if !single_quoted => escaped = true,
+                '\'' => single_quoted = !single_quoted,
+                '!' if !single_quoted && at_word_start => {
+                    let rest = &line[i + 1..];
+                    let event_len = if rest.starts_with('!') {
+                        1
+                    } else {
+                        rest.find(char::is_whitespace).unwrap_or(rest.len())
+                    };
+                    if event_len == 0 {
+                        continue;
+                    }
+                    let event = &rest[..event_len];
+                    let entry = if event == "!" {
+                        self.entries.last().map(String::as_str)
+                    } else if let Ok(n) = event.parse::<usize>() {
+                        self.get(n)
+                    } else {
+                        self.find(event)
+                    };
+                    let entry =
+                        entry.ok_or_else(|| HistoryError::EventNotFound(event.to_string()))?;
+                    expanded.push_str(&line[copied..i]);
+                    expanded.push_str(entry);
+                    copied = i + 1 + event_len;
+                    while chars.next_if(|&(j, _)| j < copied).is_some() {}
+                }
+                _ => {}
+            }
+        }
+        if copied == 0 {
+            Ok(Cow::Borrowed(line))
+        } else {
+            expanded.push_str(&line[copied..]);
+            Ok(Cow::Owned(expanded))
+        }
+    }
+}
+
+/// Errors that expanding references to the [`History`] can raise.
+#[derive(Clone, Debug)]
+pub enum HistoryError {
+    /// No entry matched the reference, which is given without
+    /// the leading `!`.
+    EventNotFound(String),
+}
+
+impl Error for HistoryError {}
+
+impl fmt::Display for HistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            HistoryError::EventNotFound(_) => "No matching history entry.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn expand_references() {
+        let mut history = History::new();
+        history.push("show log");
+        history.push("   ");
+        history.push("ping host");
+        assert_eq!(history.entries().len(), 2);
+
+        assert_eq!(history.expand("show !! now").unwrap(), "show ping host now");
+        assert_eq!(history.expand("!1 | !p").unwrap(), "show log | ping host");
+        assert_eq!(history.expand("echo ! done!").unwrap(), "echo ! done!");
+        assert_eq!(history.expand("echo '!!' \\!!").unwrap(), "echo '!!' \\!!");
+        assert!(matches!(history.expand("echo hi"), Ok(Cow::Borrowed(_))));
+        match history.expand("!quit") {
+            Err(HistoryError::EventNotFound(event)) => assert_eq!(event, "quit"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn max_entries() {
+        let mut history = History::new().max_entries(2);
+        history.push("a");
+        history.push("b");
+        history.push("c");
+        assert_eq!(history.entries(), ["b", "c"]);
+        assert_eq!(history.get(1), Some("b"));
+        assert_eq!(history.get(0), None);
+    }
+}
@@ -0,0 +1,55 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! # Sessions
+//!
+//! Support for the state which lasts across the individual command
+//! lines of an interactive session, like the [`History`] of the
+//! lines which have been entered and the [`Variables`] which can
+//! be substituted into them. Lines can also be run from a
+//! [`script`]. A [`Broadcast`] tells each session about the
+//! configuration commands executed by the others.
+//!
+//! The state is shared with the [built-in commands] which use it
+//! by way of an `Rc<RefCell<_>>`, so that the embedding application
+//! remains in control of when lines are recorded and expanded.
+//!
+//! ```
+//! use commands::parser::{CommandTree, Parser};
+//! use commands::session::{builtins, History};
+//! use commands::tokenizer::tokenize;
+//! use std::cell::RefCell;
+//! use std::rc::Rc;
+//!
+//! let history = Rc::new(RefCell::new(History::new()));
+//! let mut tree = CommandTree::new();
+//! tree.command(builtins::history(Rc::clone(&history)));
+//! let root = tree.finalize();
+//!
+//! history.borrow_mut().push("show log");
+//! let line = history.borrow().expand("history").unwrap().into_owned();
+//! history.borrow_mut().push(&line);
+//!
+//! let mut parser = Parser::new(root);
+//! if let Ok(tokens) = tokenize(&line) {
+//!     parser.parse(tokens).unwrap();
+//! }
+//! let output = parser.execute().unwrap();
+//! assert_eq!(output.lines(), ["    1  show log", "    2  history"]);
+//! ```
+//!
+//! [built-in commands]: crate::session::builtins
+
+mod broadcast;
+pub mod builtins;
+mod history;
+pub mod script;
+mod variables;
+
+pub use self::broadcast::{Announcement, Broadcast, Subscription};
+pub use self::history::{History, HistoryError};
+pub use self::script::{ScriptError, ScriptErrorKind};
+pub use self::variables::{VariableError, Variables};
@@ -0,0 +1,147 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scripts
+//!
+//! A script is a body of text with a command on each line.
+
+use super::{VariableError, Variables};
+use crate::parser::{execute_pipeline, CommandOutput, ExecuteError, Node};
+use crate::parser::{PipelineError, VerifyError};
+use crate::tokenizer::{tokenize, TokenizerError};
+use std::cell::RefCell;
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+
+/// Run each line of the script `text` as a command, starting
+/// from the `root` of a command tree, and collect their output.
+///
+/// Each line may be a pipeline of commands separated by `|`, as
+/// with [`execute_pipeline`].
+///
+/// Blank lines and lines starting with `#` are skipped. When
+/// `variables` are given, they are substituted into each line
+/// before it is tokenized, so a script shares the variables of
+/// the session which runs it.
+///
+/// Running stops at the first line which fails, and the error
+/// identifies that line.
+///
+/// ```
+/// use commands::parser::{Command, CommandTree};
+/// use commands::session::script;
+///
+/// let mut tree = CommandTree::new();
+/// tree.command(Command::new("greet").handler(|context| {
+///     context.output().line("hello");
+///     Ok(())
+/// }));
+/// let root = tree.finalize();
+///
+/// let output = script::run(&root, None, "# Say hello.\ngreet\n\ngreet\n").unwrap();
+/// assert_eq!(output.lines(), ["hello", "hello"]);
+///
+/// let err = script::run(&root, None, "greet\nwave\n").unwrap_err();
+/// assert_eq!(err.line, 2);
+/// ```
+pub fn run(
+    root: &Rc<Node>,
+    variables: Option<&RefCell<Variables>>,
+    text: &str,
+) -> Result<CommandOutput, ScriptError> {
+    let mut output = CommandOutput::new();
+    for (i, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let error = |column, kind| ScriptError {
+            line: i + 1,
+            column,
+            kind,
+        };
+        let line = match variables {
+            Some(variables) => variables
+                .borrow()
+                .substitute(line)
+                .map_err(|e| error(None, ScriptErrorKind::Variable(e)))?
+                .into_owned(),
+            None => line.to_string(),
+        };
+        let tokens = tokenize(&line).map_err(|e| error(None, ScriptErrorKind::Tokenize(e)))?;
+        let lines = execute_pipeline(root, tokens).map_err(|e| match e {
+            PipelineError::Parse(e) => {
+                let column = e.location().start.column + 1;
+                error(Some(column), ScriptErrorKind::Parse(e.to_string()))
+            }
+            PipelineError::EmptyCommand(_) => error(None, ScriptErrorKind::Parse(e.to_string())),
+            PipelineError::Verify(e) => error(None, ScriptErrorKind::Verify(e)),
+            PipelineError::Execute(e) => error(None, ScriptErrorKind::Execute(e)),
+        })?;
+        output.append(lines);
+    }
+    Ok(output)
+}
+
+/// An error raised while running a script, identifying the
+/// line on which it happened.
+#[derive(Debug)]
+pub struct ScriptError {
+    /// The line of the script, counting from 1.
+    pub line: usize,
+    /// The column of the line, counting from 1, if known.
+    pub column: Option<usize>,
+    /// What went wrong.
+    pub kind: ScriptErrorKind,
+}
+
+/// The ways in which running a line of a script can fail.
+#[derive(Debug)]
+pub enum ScriptErrorKind {
+    /// A variable could not be substituted.
+    Variable(VariableError),
+    /// The line could not be tokenized.
+    Tokenize(TokenizerError),
+    /// The line could not be parsed. This has the description
+    /// of the `ParseError`.
+    Parse(String),
+    /// The line did not have a valid command.
+    Verify(VerifyError),
+    /// The command failed.
+    Execute(ExecuteError),
+    /// Scripts were nested more deeply than the given limit.
+    TooDeep(usize),
+}
+
+impl Error for ScriptError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self.kind {
+            ScriptErrorKind::Variable(ref err) => Some(err),
+            ScriptErrorKind::Tokenize(ref err) => Some(err),
+            ScriptErrorKind::Verify(ref err) => Some(err),
+            ScriptErrorKind::Execute(ref err) => Some(err),
+            ScriptErrorKind::Parse(_) | ScriptErrorKind::TooDeep(_) => None,
+        }
+    }
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match self.column {
+            Some(column) => write!(f, "Line {}, column {}: ", self.line, column)?,
+            None => write!(f, "Line {}: ", self.line)?,
+        }
+        match self.kind {
+            ScriptErrorKind::Variable(ref err) => err.fmt(f),
+            ScriptErrorKind::Tokenize(ref err) => err.fmt(f),
+            ScriptErrorKind::Parse(ref description) => description.fmt(f),
+            ScriptErrorKind::Verify(ref err) => err.fmt(f),
+            ScriptErrorKind::Execute(ref err) => err.fmt(f),
+            ScriptErrorKind::TooDeep(_) => "Scripts are nested too deeply.".fmt(f),
+        }
+    }
+}
@@ -0,0 +1,193 @@
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://www.apache.org/licenses/LICENSE-2.0> or the MIT license
+// <LICENSE-MIT or http://opensource.org/licenses/MIT>, at your
+// option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+
+/// Variables which can be substituted into the lines entered
+/// during a session.
+///
+/// A variable is referred to as `$name` or `${name}`. Names
+/// consist of alphanumeric characters and underscores.
+#[derive(Clone, Debug, Default)]
+pub struct Variables {
+    values: BTreeMap<String, String>,
+}
+
+impl Variables {
+    /// Construct an empty set of `Variables`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Set the variable `name` to `value`.
+    pub fn set(&mut self, name: &str, value: &str) {
+        self.values.insert(name.to_string(), value.to_string());
+    }
+
+    /// Remove the variable `name`, returning its value.
+    pub fn unset(&mut self, name: &str) -> Option<String> {
+        self.values.remove(name)
+    }
+
+    /// The value of the variable `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+
+    /// The names and values of the variables, ordered by name.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    /// The references to variables which start with `text`,
+    /// like `$host` for `$h`. This is empty unless `text`
+    /// starts with `$`.
+    ///
+    /// This is suitable for use with [`CommandTree::value_completer`].
+    ///
+    /// [`CommandTree::value_completer`]: crate::parser::CommandTree::value_completer
+    pub fn complete(&self, text: &str) -> Vec<String> {
+        match text.strip_prefix('$') {
+            Some(prefix) => self
+                .values
+                .keys()
+                .filter(|n| n.starts_with(prefix))
+                .map(|n| format!("${n}"))
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    /// Replace references to variables within `line` with their
+    /// values. References are not replaced within single quotes
+    /// or after a backslash.
+    ///
+    /// The line is only copied when a reference has been replaced.
+    ///
+    /// ```
+    /// use commands::session::Variables;
+    ///
+    /// let mut variables = Variables::new();
+    /// variables.set("host", "10.0.0.1");
+    /// assert_eq!(variables.substitute("ping $host").unwrap(), "ping 10.0.0.1");
+    /// assert_eq!(variables.substitute("ping ${host}:80").unwrap(), "ping 10.0.0.1:80");
+    /// assert!(variables.substitute("ping $port").is_err());
+    /// ```
+    pub fn substitute<'line>(&self, line: &'line str) -> Result<Cow<'line, str>, VariableError> {
+        let mut substituted = String::new();
+        let mut copied = 0;
+        let mut single_quoted = false;
+        let mut escaped = false;
+        let mut chars = line.char_indices();
+        while let Some((i, c)) = chars.next() {
+            if escaped {
+                escaped = false;
+                continue;
+            }
+            match c {
+                '\\' if !single_quoted => escaped = true,
+                '\'' => single_quoted = !single_quoted,
+                '$' if !single_quoted => {
+                    let rest = &line[i + 1..];
+                    let (name, len) = match rest.strip_prefix('{') {
+                        Some(braced) => match braced.find('}') {
+                            Some(end) => (&braced[..end], end + 2),
+                            None => return Err(VariableError::Unterminated(i)),
+                        },
+                        None => {
+                            let end = rest.find(|c: char| !is_name_char(c)).unwrap_or(rest.len());
+                            (&rest[..end], end)
+                        }
+                    };
+                    if name.is_empty() {
+                        continue;
+                    }
+                    let value = self
+                        .get(name)
+                        .ok_or_else(|| VariableError::Undefined(name.to_string()))?;
+                    substituted.push_str(&line[copied..i]);
+                    substituted.push_str(value);
+                    copied = i + 1 + len;
+                    for _ in rest[..len].chars() {
+                        chars.next();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if copied == 0 {
+            Ok(Cow::Borrowed(line))
+        } else {
+            substituted.push_str(&line[copied..]);
+            Ok(Cow::Owned(substituted))
+        }
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Errors that substituting [`Variables`] can raise.
+#[derive(Clone, Debug)]
+pub enum VariableError {
+    /// The named variable has not been set.
+    Undefined(String),
+    /// A `${` at the given byte offset has no closing `}`.
+    Unterminated(usize),
+}
+
+impl Error for VariableError {}
+
+impl fmt::Display for VariableError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        match *self {
+            VariableError::Undefined(_) => "Undefined variable.",
+            VariableError::Unterminated(_) => "Unterminated variable reference.",
+        }
+        .fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn substitute_references() {
+        let mut variables = Variables::new();
+        variables.set("a", "1");
+        variables.set("ab", "2");
+        assert_eq!(variables.substitute("$a $ab ${a}b").unwrap(), "1 2 1b");
+        assert_eq!(
+            variables.substitute("'$a' \\$a $ a$").unwrap(),
+            "'$a' \\$a $ a$"
+        );
+        assert!(matches!(variables.substitute("none"), Ok(Cow::Borrowed(_))));
+        match variables.substitute("${a") {
+            Err(VariableError::Unterminated(0)) => {}
+            _ => panic!(),
+        }
+        assert_eq!(variables.unset("a"), Some("1".to_string()));
+        match variables.substitute("$a") {
+            Err(VariableError::Undefined(name)) => assert_eq!(name, "a"),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn complete_references() {
+        let mut variables = Variables::new();
+        variables.set("host", "h");
+        variables.set("port", "p");
+        assert_eq!(variables.complete("$h"), vec!["$host"]);
+        assert_eq!(variables.complete("$").len(), 2);
+        assert!(variables.complete("h").is_empty());
+    }
+}
@@ -53,20 +53,48 @@
 //!     assert_eq!(tokens.len(), 3);
 //!     assert_eq!(tokens[2].text, r#"My\ Documents"#);
 //! }
+//!
+//! // A \ at the end of a line continues the command on the
+//! // next line, as though the lines were separated by a space.
+//! if let Ok(tokens) = tokenize("show \\\n  interface") {
+//!     assert_eq!(tokens.len(), 3);
+//!     assert_eq!(tokens[1].text, " \\\n  ");
+//!     assert_eq!(tokens[2].text, "interface");
+//! }
+//! ```
+//!
+//! The behavior of the tokenizer can be adjusted with a
+//! [`TokenizerConfig`] and [`tokenize_with`]:
+//!
+//! ```
+//! use commands::tokenizer::{tokenize_with, TokenizerConfig, TokenType};
+//!
+//! // Treat `,` and `=` like whitespace.
+//! let config = TokenizerConfig::new().separator(',').separator('=');
+//! if let Ok(tokens) = tokenize_with(&config, "set a=1,b") {
+//!     assert_eq!(tokens.len(), 7);
+//!     assert_eq!(tokens[3].token_type, TokenType::Whitespace);
+//!     assert_eq!(tokens[3].text, "=");
+//! }
 //! ```
 //!
 //! [source location]: SourceLocation
 //! [tokens]: Token
 //! [whitespace or a word]: TokenType
 
+use crate::util::display_width;
+use std::borrow::Cow;
 use std::error::Error;
 use std::fmt;
+use std::ops::Range;
 
 /// A position within a body of text.
 ///
-/// The `SourceOffset` tracks 2 different ways of locating the
+/// The `SourceOffset` tracks 3 different ways of locating the
 /// position:
 ///
+/// * The index of the first byte of the character within the body
+///   of text, for slicing it.
 /// * The index of the character within the body of text.
 /// * The column and line number of the character.
 ///
@@ -74,24 +102,39 @@ use std::fmt;
 /// indicating the start and end of a range of text as used
 /// by the [`SourceLocation`].
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SourceOffset {
+    /// The index of the first byte of this character within the
+    /// body of text.
+    pub byte: usize,
     /// The index of this character within the body of text.
     pub char: usize,
-    /// The line number on which this character may be found.
+    /// The line number on which this character may be found,
+    /// counting from 0. A line ends with a `\n`, which is the last
+    /// character of its line.
     pub line: usize,
-    /// The column on which this character may be found.
+    /// The column on which this character may be found, which is
+    /// the index of the character within its line. Tabs and wide
+    /// characters count as a single column; see
+    /// [`SourceLocation::display_columns`] for terminal columns.
     pub column: usize,
 }
 
 impl SourceOffset {
     /// Construct a `SourceOffset`.
-    pub fn new(char: usize, line: usize, column: usize) -> SourceOffset {
-        SourceOffset { char, line, column }
+    pub fn new(byte: usize, char: usize, line: usize, column: usize) -> SourceOffset {
+        SourceOffset {
+            byte,
+            char,
+            line,
+            column,
+        }
     }
 }
 
 /// A range within a body of text.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct SourceLocation {
     /// The start of the range.
     pub start: SourceOffset,
@@ -104,6 +147,51 @@ impl SourceLocation {
     pub fn new(start: SourceOffset, end: SourceOffset) -> SourceLocation {
         SourceLocation { start, end }
     }
+
+    /// The range of character indices that this location covers
+    /// within the body of text. The `end` of a location is the
+    /// last character within it, so this ends just after it.
+    ///
+    /// ```
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let tokens = tokenize("show interface").unwrap();
+    /// assert_eq!(tokens[2].location.char_range(), 5..14);
+    /// ```
+    pub fn char_range(&self) -> Range<usize> {
+        self.start.char..self.end.char + 1
+    }
+
+    /// The range of display columns that this location covers
+    /// within `line`, the text of the line that it starts on.
+    /// Unlike the `column` of a [`SourceOffset`], this accounts
+    /// for wide characters and tabs, so it can be used to place
+    /// a caret under the text in a terminal. A location which
+    /// continues onto later lines extends to the end of `line`.
+    ///
+    /// ```
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let line = "\u{8868}\u{793a} interface";
+    /// let tokens = tokenize(line).unwrap();
+    /// assert_eq!(tokens[2].location.start.column, 3);
+    /// assert_eq!(tokens[2].location.display_columns(line), 5..14);
+    /// ```
+    pub fn display_columns(&self, line: &str) -> Range<usize> {
+        let end = if self.end.line == self.start.line {
+            self.end.column + 1
+        } else {
+            line.chars().count()
+        };
+        let width = |columns| {
+            let text = line
+                .char_indices()
+                .nth(columns)
+                .map_or(line, |(i, _)| &line[..i]);
+            display_width(text)
+        };
+        width(self.start.column)..width(end)
+    }
 }
 
 /// Errors
@@ -112,9 +200,6 @@ pub enum TokenizerError {
     /// Character not allowed here
     CharacterNotAllowedHere(usize),
 
-    /// Special not yet implemented
-    SpecialNotYetImplemented(usize),
-
     /// Escaping backslash at end of input
     EscapingBackslashAtEndOfInput,
 
@@ -123,34 +208,107 @@ pub enum TokenizerError {
 
     /// Unclosed single quote at end of input
     UnclosedSingleQuote,
+
+    /// Unterminated here document at end of input
+    UnterminatedHereDocument,
+
+    /// Control character not allowed, with the offset and
+    /// the offending character.
+    ControlCharacter(usize, char),
+
+    /// The input is longer than the configured maximum length.
+    InputTooLong(usize),
+
+    /// The input has more tokens than the configured maximum.
+    TooManyTokens(usize),
 }
 
-impl Error for TokenizerError {
-    fn description(&self) -> &str {
+impl TokenizerError {
+    /// Could this error be resolved by supplying more input?
+    ///
+    /// This is `true` when the text ended while inside of a
+    /// construct which continues across lines, like an open
+    /// quote or a here document, or with a backslash which
+    /// continues the line. Line editors can use this
+    /// to decide whether to prompt for another line rather
+    /// than reporting an error.
+    pub fn is_incomplete(&self) -> bool {
+        matches!(
+            *self,
+            TokenizerError::EscapingBackslashAtEndOfInput
+                | TokenizerError::UnclosedDoubleQuote
+                | TokenizerError::UnclosedSingleQuote
+                | TokenizerError::UnterminatedHereDocument
+        )
+    }
+}
+
+impl Error for TokenizerError {}
+
+impl fmt::Display for TokenizerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
         match *self {
             TokenizerError::CharacterNotAllowedHere(_) => "Character not allowed here",
-            TokenizerError::SpecialNotYetImplemented(_) => "Special not yet implemented",
             TokenizerError::EscapingBackslashAtEndOfInput => "Escaping backlash at end of input",
             TokenizerError::UnclosedDoubleQuote => "Unclosed double quote at end of input",
             TokenizerError::UnclosedSingleQuote => "Unclosed single quote at end of input",
+            TokenizerError::UnterminatedHereDocument => {
+                "Unterminated here document at end of input"
+            }
+            TokenizerError::ControlCharacter(_, _) => "Control character not allowed",
+            TokenizerError::InputTooLong(_) => "Input exceeds the maximum length",
+            TokenizerError::TooManyTokens(_) => "Input exceeds the maximum number of tokens",
         }
+        .fmt(f)
     }
 }
 
-impl fmt::Display for TokenizerError {
-    fn fmt(&self, f: &mut fmt::Formatter) -> Result<(), fmt::Error> {
-        self.description().fmt(f)
-    }
+/// The construct left unfinished at the end of the text given
+/// to [`tokenize_partial`].
+#[derive(Clone, Debug)]
+pub enum Unfinished {
+    /// The text ends within a double quoted word.
+    OpenDoubleQuote,
+    /// The text ends within a single quoted word.
+    OpenSingleQuote,
+    /// The text ends with an escaping backslash, or with a backslash
+    /// and a line break continuing the command on the next line.
+    TrailingBackslash,
+    /// The text ends within a here document.
+    OpenHereDocument,
+    /// The text could not be tokenized any further because of
+    /// the given error.
+    Invalid(TokenizerError),
 }
 
-/// The role that a token plays: `Whitespace` or `Word`.
+/// The role that a token plays: `Whitespace`, `Word`, `HereDocument`
+/// or one of the special characters `|`, `;` and `?`.
+///
+/// With the `serde` feature, these are serialized in snake case,
+/// like `here_document`.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
 pub enum TokenType {
     /// The token represents whitespace and not a word.
     Whitespace,
     /// The token represents a word within the body of text. This
     /// takes double quoted strings into account.
     Word,
+    /// The token represents a here document, a value which spans
+    /// multiple lines. This is only produced when enabled via
+    /// [`TokenizerConfig::here_documents`].
+    HereDocument,
+    /// The token is a `|`, which separates the commands of a
+    /// pipeline.
+    Pipe,
+    /// The token is a `;`, which separates commands which are
+    /// run one after another.
+    Separator,
+    /// The token is a `?`, which asks for help with the input
+    /// before it. This is only a `Help` token at the start of a
+    /// word, so `what?` is a single word.
+    Help,
 }
 
 /// A token from a body of text.
@@ -158,13 +316,18 @@ pub enum TokenType {
 /// The lifetime parameter `'text` refers to the lifetime
 /// of the body of text that was tokenized, creating this token.
 #[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct Token<'text> {
     /// The text of the token.
     pub text: &'text str,
-    /// The type of the token (`Whitespace` or `Word`).
+    /// The type of the token, such as `Whitespace` or `Word`.
     pub token_type: TokenType,
     /// The location of the token in the source body of text.
     pub location: SourceLocation,
+    /// Whether the token is quoted with curly quotes, which the
+    /// tokenizer only allows with `TokenizerConfig::smart_quotes`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) smart_quoted: bool,
 }
 
 impl<'text> Token<'text> {
@@ -175,14 +338,381 @@ impl<'text> Token<'text> {
             text,
             token_type,
             location,
+            smart_quoted: false,
+        }
+    }
+
+    /// Construct a `Token` lexed with `config`, noting whether it
+    /// is quoted with curly quotes.
+    fn lexed(
+        config: &TokenizerConfig,
+        text: &'text str,
+        token_type: TokenType,
+        location: SourceLocation,
+    ) -> Token<'text> {
+        Token {
+            smart_quoted: config.smart_quotes && text.starts_with(['\u{201C}', '\u{2018}']),
+            ..Token::new(text, token_type, location)
+        }
+    }
+
+    /// The range of bytes that the token covers within the body of
+    /// text, for slicing it. Unlike [`SourceLocation::char_range`],
+    /// this accounts for characters which take more than one byte.
+    ///
+    /// ```
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// let line = "echo h\u{e9}llo w\u{f6}rld";
+    /// let tokens = tokenize(line).unwrap();
+    /// assert_eq!(tokens[4].location.char_range(), 11..16);
+    /// assert_eq!(tokens[4].byte_range(), 12..18);
+    /// assert_eq!(&line[tokens[4].byte_range()], "w\u{f6}rld");
+    /// ```
+    pub fn byte_range(&self) -> Range<usize> {
+        self.location.start.byte..self.location.start.byte + self.text.len()
+    }
+
+    /// The value that the token gives, without its surrounding
+    /// quotes and with the escape sequences within it interpreted.
+    /// Within double quotes, `\n`, `\t`, `\r` and `\xNN` are
+    /// interpreted as in most shells, while within single quotes,
+    /// backslashes only escape the quote and other backslashes.
+    /// Curly quotes are only removed when the token was tokenized
+    /// with `TokenizerConfig::smart_quotes`, as otherwise they are
+    /// part of the word. For a here document, this is its body. The text is only
+    /// copied when something has been removed.
+    ///
+    /// ```
+    /// use commands::tokenizer::tokenize;
+    ///
+    /// if let Ok(tokens) = tokenize(r#"cd My\ Documents "a b c""#) {
+    ///     assert_eq!(tokens[2].text, r"My\ Documents");
+    ///     assert_eq!(tokens[2].value(), "My Documents");
+    ///     assert_eq!(tokens[4].value(), "a b c");
+    /// }
+    ///
+    /// if let Ok(tokens) = tokenize(r#"echo "a\tb\x21" 'a\tb'"#) {
+    ///     assert_eq!(tokens[2].value(), "a\tb!");
+    ///     assert_eq!(tokens[4].value(), r"a\tb");
+    /// }
+    /// ```
+    pub fn value(&self) -> Cow<'text, str> {
+        match self.here_document_body() {
+            Some(body) => Cow::Borrowed(body),
+            None => unquote(self.text, self.smart_quoted),
+        }
+    }
+
+    /// The body of a here document token.
+    ///
+    /// This is the text between the line with the opening `<<DELIMITER`
+    /// and the line with the closing `DELIMITER`, without the final
+    /// line break. For other types of tokens, this returns `None`.
+    ///
+    /// ```
+    /// use commands::tokenizer::{tokenize_with, TokenizerConfig};
+    ///
+    /// let config = TokenizerConfig::new().here_documents(true);
+    /// if let Ok(tokens) = tokenize_with(&config, "load <<EOF\na\nb\nEOF") {
+    ///     assert_eq!(tokens[2].here_document_body(), Some("a\nb"));
+    /// }
+    /// ```
+    pub fn here_document_body(&self) -> Option<&'text str> {
+        if self.token_type != TokenType::HereDocument {
+            return None;
+        }
+        let text = self.text;
+        let body_start = text.find('\n')? + 1;
+        let body_end = text.rfind('\n')?;
+        if body_end < body_start {
+            Some("")
+        } else {
+            Some(&text[body_start..body_end])
+        }
+    }
+}
+
+/// How the tokenizer treats control characters in the input.
+///
+/// Control characters other than whitespace (like tabs and
+/// newlines) can arrive from serial consoles or be pasted
+/// along with text and are rarely intended as part of a
+/// command.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub enum ControlCharacters {
+    /// Control characters are allowed within words.
+    #[default]
+    Allow,
+    /// Control characters result in a `TokenizerError::ControlCharacter`.
+    Reject,
+    /// Control characters are removed by [`sanitize`]. Any control
+    /// characters that reach the tokenizer are rejected.
+    Strip,
+}
+
+/// The maximum length of input, in bytes, used for untrusted input
+/// when no `max_input_length` is configured.
+pub const UNTRUSTED_MAX_INPUT_LENGTH: usize = 4096;
+
+/// The maximum number of tokens used for untrusted input when no
+/// `max_tokens` is configured.
+pub const UNTRUSTED_MAX_TOKENS: usize = 512;
+
+/// Configuration for the tokenizer.
+///
+/// This is used with [`tokenize_with`] to adjust how text is
+/// broken into tokens. The default configuration matches the
+/// behavior of [`tokenize`].
+///
+/// Changing the quotes or the escape character only changes how
+/// the text is broken into tokens. The text of the tokens is not
+/// modified, and [`Token::value`] still interprets it with the
+/// default quotes and escape character. Curly quotes are only
+/// removed from around a token when `smart_quotes` is enabled.
+#[derive(Clone, Debug)]
+pub struct TokenizerConfig {
+    separators: Vec<char>,
+    smart_quotes: bool,
+    double_quotes: bool,
+    single_quotes: bool,
+    escape: Option<char>,
+    comment: Option<char>,
+    specials: Vec<char>,
+    whitespace_tokens: bool,
+    here_documents: bool,
+    control_characters: ControlCharacters,
+    max_input_length: Option<usize>,
+    max_tokens: Option<usize>,
+}
+
+impl Default for TokenizerConfig {
+    fn default() -> Self {
+        TokenizerConfig {
+            separators: vec![],
+            smart_quotes: false,
+            double_quotes: true,
+            single_quotes: true,
+            escape: Some('\\'),
+            comment: None,
+            specials: SPECIALS.to_vec(),
+            whitespace_tokens: true,
+            here_documents: false,
+            control_characters: ControlCharacters::Allow,
+            max_input_length: None,
+            max_tokens: None,
         }
     }
 }
 
+/// The characters which can be special: `|`, `;` and `?`.
+const SPECIALS: [char; 3] = ['|', ';', '?'];
+
+impl TokenizerConfig {
+    /// Construct a default `TokenizerConfig`.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Add a character that should be treated as a separator
+    /// in the same way as whitespace.
+    ///
+    /// Separator characters are reported as part of a
+    /// `TokenType::Whitespace` token, so the parser ignores
+    /// them. They may be escaped with a `\` or quoted to
+    /// include them within a word.
+    pub fn separator(mut self, separator: char) -> Self {
+        self.separators.push(separator);
+        self
+    }
+
+    /// Treat typographic ("smart") quotes as their ASCII equivalents.
+    ///
+    /// When enabled, `“` and `”` behave like `"` while `‘` and `’`
+    /// behave like `'`. This is useful when commands are pasted from
+    /// documents which have replaced the quotes. The text of the
+    /// resulting tokens is not modified, but their [`Token::value`]
+    /// is without the quotes. Without it, a word like `“a”` is a
+    /// single word whose value keeps the quotes.
+    pub fn smart_quotes(mut self, smart_quotes: bool) -> Self {
+        self.smart_quotes = smart_quotes;
+        self
+    }
+
+    /// Allow double quotes to give a single word which may include
+    /// whitespace. This is enabled by default. When disabled, a `"`
+    /// is part of a word like any other character.
+    pub fn double_quotes(mut self, double_quotes: bool) -> Self {
+        self.double_quotes = double_quotes;
+        self
+    }
+
+    /// Allow single quotes to give a single word which may include
+    /// whitespace. This is enabled by default. When disabled, a `'`
+    /// is part of a word like any other character.
+    pub fn single_quotes(mut self, single_quotes: bool) -> Self {
+        self.single_quotes = single_quotes;
+        self
+    }
+
+    /// Set the character which escapes the character after it, or
+    /// continues a line when it is at the end of one. This is `\`
+    /// by default. With `None`, nothing is escaped, as is wanted
+    /// when the values are Windows paths.
+    pub fn escape(mut self, escape: Option<char>) -> Self {
+        self.escape = escape;
+        self
+    }
+
+    /// Set the character which starts a comment, like `#` or `!`.
+    /// There is none by default.
+    ///
+    /// A comment starts with this character at the start of a word
+    /// and continues to the end of the line. It is reported as part
+    /// of a `TokenType::Whitespace` token, so the parser ignores it.
+    /// Within a word or quotes, the character has no meaning.
+    ///
+    /// ```
+    /// use commands::tokenizer::{tokenize_with, TokenizerConfig, TokenType};
+    ///
+    /// let config = TokenizerConfig::new().comment(Some('#'));
+    /// if let Ok(tokens) = tokenize_with(&config, "show a#b # all of it\nsave") {
+    ///     assert_eq!(tokens.len(), 5);
+    ///     assert_eq!(tokens[2].text, "a#b");
+    ///     assert_eq!(tokens[3].token_type, TokenType::Whitespace);
+    ///     assert_eq!(tokens[3].text, " # all of it\n");
+    /// }
+    /// ```
+    pub fn comment(mut self, comment: Option<char>) -> Self {
+        self.comment = comment;
+        self
+    }
+
+    /// Enable or disable one of the special characters `|`, `;` and
+    /// `?`, which are all enabled by default. A disabled special
+    /// character is part of a word like any other character. Other
+    /// characters can't be made special.
+    ///
+    /// ```
+    /// use commands::tokenizer::{tokenize_with, TokenizerConfig, TokenType};
+    ///
+    /// let config = TokenizerConfig::new().special('?', false);
+    /// if let Ok(tokens) = tokenize_with(&config, "find ? | count") {
+    ///     assert_eq!(tokens[2].token_type, TokenType::Word);
+    ///     assert_eq!(tokens[4].token_type, TokenType::Pipe);
+    /// }
+    /// ```
+    pub fn special(mut self, special: char, enabled: bool) -> Self {
+        self.specials.retain(|c| *c != special);
+        if enabled && SPECIALS.contains(&special) {
+            self.specials.push(special);
+        }
+        self
+    }
+
+    /// Produce tokens for whitespace, including separators and
+    /// comments. This is enabled by default. When disabled, only
+    /// the other tokens are produced, which is simpler for an
+    /// application which tokenizes text for its own use. The
+    /// parser doesn't need the whitespace tokens.
+    pub fn whitespace_tokens(mut self, whitespace_tokens: bool) -> Self {
+        self.whitespace_tokens = whitespace_tokens;
+        self
+    }
+
+    /// Allow here documents for supplying multi-line values.
+    ///
+    /// When enabled, a word starting with `<<` followed by a
+    /// delimiter and the end of the line starts a here document.
+    /// The here document continues until a line consisting of
+    /// just the delimiter and is produced as a single token of
+    /// type `TokenType::HereDocument`:
+    ///
+    /// ```text
+    /// certificate install <<END
+    /// -----BEGIN CERTIFICATE-----
+    /// ...
+    /// -----END CERTIFICATE-----
+    /// END
+    /// ```
+    ///
+    /// If the text ends before the closing delimiter, tokenizing
+    /// fails with `TokenizerError::UnterminatedHereDocument`, which
+    /// [reports itself as incomplete] so that more lines can be
+    /// requested from the user.
+    ///
+    /// [reports itself as incomplete]: TokenizerError::is_incomplete
+    pub fn here_documents(mut self, here_documents: bool) -> Self {
+        self.here_documents = here_documents;
+        self
+    }
+
+    /// Set the policy for handling [control characters].
+    ///
+    /// [control characters]: ControlCharacters
+    pub fn control_characters(mut self, control_characters: ControlCharacters) -> Self {
+        self.control_characters = control_characters;
+        self
+    }
+
+    /// Limit the length of the input, in bytes.
+    ///
+    /// Longer input results in `TokenizerError::InputTooLong`
+    /// before any tokenization is performed.
+    pub fn max_input_length(mut self, max_input_length: usize) -> Self {
+        self.max_input_length = Some(max_input_length);
+        self
+    }
+
+    /// Limit the number of tokens, including whitespace tokens.
+    ///
+    /// Tokenizing stops with `TokenizerError::TooManyTokens`
+    /// once the limit has been exceeded.
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.max_tokens = Some(max_tokens);
+        self
+    }
+
+    /// This configuration with the default limits for untrusted
+    /// input applied in place of any limits which aren't set.
+    pub(crate) fn bounded(&self) -> Self {
+        let mut config = self.clone();
+        config.max_input_length = config.max_input_length.or(Some(UNTRUSTED_MAX_INPUT_LENGTH));
+        config.max_tokens = config.max_tokens.or(Some(UNTRUSTED_MAX_TOKENS));
+        config
+    }
+
+    /// Is the character `c` whitespace or a separator?
+    fn is_whitespace(&self, c: char) -> bool {
+        c.is_whitespace() || self.separators.contains(&c)
+    }
+
+    /// Is the character `c` a double quote?
+    fn is_double_quote(&self, c: char) -> bool {
+        self.double_quotes
+            && (c == '"' || (self.smart_quotes && (c == '\u{201C}' || c == '\u{201D}')))
+    }
+
+    /// Is the character `c` a single quote?
+    fn is_single_quote(&self, c: char) -> bool {
+        self.single_quotes
+            && (c == '\'' || (self.smart_quotes && (c == '\u{2018}' || c == '\u{2019}')))
+    }
+
+    /// Is the character `c` the escape character?
+    fn is_escape(&self, c: char) -> bool {
+        self.escape == Some(c)
+    }
+
+    /// Is the character `c` an enabled special character?
+    fn is_special(&self, c: char) -> bool {
+        self.specials.contains(&c)
+    }
+}
+
 #[derive(Clone, Copy, Eq, PartialEq)]
 enum State {
     Initial,
-    Special,
     Whitespace,
     Doublequote,
     DoublequoteBackslash,
@@ -190,209 +720,1036 @@ enum State {
     SinglequoteBackslash,
     Word,
     WordBackslash,
+    Comment,
+    HereDocumentOperator,
+    HereDocumentDelimiter,
+    HereDocumentLineEnd,
+    HereDocumentBody,
 }
 
-struct Tokenizer<'text> {
+struct Lexer<'config, 'text> {
+    config: &'config TokenizerConfig,
     text: &'text str,
     state: State,
     token_type: Option<TokenType>,
-    token_start: usize,
-    token_end: usize,
+    token_start: SourceOffset,
+    token_end: SourceOffset,
+    // The line of the character currently being processed, and the
+    // index of the character which starts that line.
+    line: usize,
+    line_start: usize,
+    // Byte offsets of the current token, used for slicing `text`.
+    token_start_byte: usize,
+    token_end_byte: usize,
+    // Byte range of the character currently being processed.
+    byte: usize,
+    byte_end: usize,
+    // The delimiter and the current line of a here document.
+    delimiter: &'text str,
+    line_start_byte: usize,
     tokens: Vec<Token<'text>>,
+    // The state at the end of the text, before the last token was
+    // reduced.
+    end_state: State,
 }
 
-impl<'text> Tokenizer<'text> {
-    fn new(text: &'text str) -> Tokenizer<'text> {
-        Tokenizer {
+impl<'config, 'text> Lexer<'config, 'text> {
+    fn new(config: &'config TokenizerConfig, text: &'text str) -> Lexer<'config, 'text> {
+        Lexer {
+            config,
             text,
             state: State::Initial,
             token_type: None,
-            token_start: 0,
-            token_end: 0,
+            token_start: SourceOffset::new(0, 0, 0, 0),
+            token_end: SourceOffset::new(0, 0, 0, 0),
+            line: 0,
+            line_start: 0,
+            token_start_byte: 0,
+            token_end_byte: 0,
+            byte: 0,
+            byte_end: 0,
+            delimiter: "",
+            line_start_byte: 0,
             tokens: vec![],
+            end_state: State::Initial,
         }
     }
 
     fn reset(&mut self) {
         self.state = State::Initial;
         self.token_type = None;
-        self.token_start = 0;
-        self.token_end = 0;
+        self.token_start = SourceOffset::new(0, 0, 0, 0);
+        self.token_end = SourceOffset::new(0, 0, 0, 0);
+        self.token_start_byte = 0;
+        self.token_end_byte = 0;
     }
 
     fn reduce(&mut self) {
-        let token_text = &self.text[self.token_start..self.token_end + 1];
-        let loc = SourceLocation::new(
-            SourceOffset::new(self.token_start, 0, self.token_start),
-            SourceOffset::new(self.token_end, 0, self.token_end),
-        );
-        self.tokens.push(Token::new(
-            token_text,
-            self.token_type.expect("Invalid tokenization"),
-            loc,
-        ));
+        let token_text = &self.text[self.token_start_byte..self.token_end_byte];
+        let loc = SourceLocation::new(self.token_start, self.token_end);
+        match self.token_type {
+            Some(TokenType::Whitespace) if !self.config.whitespace_tokens => {}
+            Some(token_type) => {
+                let token = Token::lexed(self.config, token_text, token_type, loc);
+                self.tokens.push(token);
+            }
+            None => {}
+        }
         self.reset();
     }
 
+    /// The position of the character currently being processed,
+    /// which is at the index `offset`.
+    fn position(&self, offset: usize) -> SourceOffset {
+        SourceOffset::new(self.byte, offset, self.line, offset - self.line_start)
+    }
+
     fn shift(&mut self, offset: usize, next_state: State) {
         self.recognize(offset, next_state);
-        self.token_end = offset;
+        self.token_end = self.position(offset);
+        self.token_end_byte = self.byte_end;
         self.state = next_state;
     }
 
     fn recognize(&mut self, offset: usize, next_state: State) {
         if self.token_type.is_none() {
-            self.token_type = if next_state == State::Whitespace {
+            self.token_type = if matches!(next_state, State::Whitespace | State::Comment) {
                 Some(TokenType::Whitespace)
             } else {
                 Some(TokenType::Word)
             };
-            self.token_start = offset;
+            self.token_start = self.position(offset);
+            self.token_start_byte = self.byte;
         }
     }
 
-    fn special(&mut self, offset: usize) {
-        self.shift(offset, State::Special);
+    /// A special character is a token by itself.
+    fn special(&mut self, offset: usize, c: char) {
+        self.shift(offset, State::Word);
+        self.token_type = match c {
+            '|' => Some(TokenType::Pipe),
+            ';' => Some(TokenType::Separator),
+            _ => Some(TokenType::Help),
+        };
         self.reduce();
     }
 
     fn initial(&mut self, offset: usize, c: char) {
-        if c.is_whitespace() {
+        if self.config.is_whitespace(c) || self.is_line_continuation(c) {
             self.shift(offset, State::Whitespace);
-        } else if c == ';' || c == '?' || c == '|' {
-            self.special(offset);
-        } else if c == '"' {
+        } else if self.config.comment == Some(c) {
+            self.shift(offset, State::Comment);
+        } else if self.config.is_special(c) {
+            self.special(offset, c);
+        } else if self.config.is_double_quote(c) {
             self.shift(offset, State::Doublequote);
-        } else if c == '\'' {
+        } else if self.config.is_single_quote(c) {
             self.shift(offset, State::Singlequote);
-        } else if c == '\\' {
+        } else if self.config.is_escape(c) {
             self.recognize(offset, State::Word);
             self.shift(offset, State::WordBackslash);
+        } else if c == '<' && self.config.here_documents {
+            self.shift(offset, State::HereDocumentOperator);
         } else {
             self.shift(offset, State::Word);
         }
     }
 
+    fn word(&mut self, offset: usize, c: char) {
+        if self.config.is_whitespace(c) || self.is_line_continuation(c) {
+            self.reduce();
+            self.shift(offset, State::Whitespace);
+        } else if (c == ';' || c == '|') && self.config.is_special(c) {
+            self.reduce();
+            self.special(offset, c);
+        } else if self.config.is_double_quote(c) {
+            self.reduce();
+            self.shift(offset, State::Doublequote);
+        } else if self.config.is_single_quote(c) {
+            self.reduce();
+            self.shift(offset, State::Singlequote);
+        } else if self.config.is_escape(c) {
+            self.shift(offset, State::WordBackslash);
+        } else {
+            self.shift(offset, State::Word);
+        }
+    }
+
+    /// Does a line break start at the character `c`, which is the
+    /// character currently being processed? This is a `\n` or a
+    /// `\r\n`.
+    fn is_line_break(&self, c: char) -> bool {
+        c == '\n' || (c == '\r' && self.text[self.byte_end..].starts_with('\n'))
+    }
+
+    /// Is the character `c` an escape continuing the command on the
+    /// next line? The escape and the line break are treated as
+    /// whitespace.
+    fn is_line_continuation(&self, c: char) -> bool {
+        self.config.is_escape(c)
+            && self.text[self.byte_end..]
+                .chars()
+                .next()
+                .is_some_and(|next| {
+                    next == '\n'
+                        || (next == '\r' && self.text[self.byte_end + 1..].starts_with('\n'))
+                })
+    }
+
+    /// Is the current line of the here document the closing delimiter?
+    fn at_here_document_end(&self) -> bool {
+        let line = &self.text[self.line_start_byte..self.byte];
+        line.strip_suffix('\r').unwrap_or(line) == self.delimiter
+    }
+
+    fn check_token_count(&self) -> Result<(), TokenizerError> {
+        match self.config.max_tokens {
+            Some(max) if self.tokens.len() > max => Err(TokenizerError::TooManyTokens(max)),
+            _ => Ok(()),
+        }
+    }
+
     fn tokenize(&mut self) -> Result<(), TokenizerError> {
-        for (offset, c) in self.text.chars().enumerate() {
+        if let Some(max) = self.config.max_input_length {
+            if self.text.len() > max {
+                return Err(TokenizerError::InputTooLong(max));
+            }
+        }
+        for (offset, (byte, c)) in self.text.char_indices().enumerate() {
+            self.check_token_count()?;
+            self.byte = byte;
+            self.byte_end = byte + c.len_utf8();
+            if self.config.control_characters != ControlCharacters::Allow && is_control(c) {
+                return Err(TokenizerError::ControlCharacter(offset, c));
+            }
             match self.state {
                 State::Initial => self.initial(offset, c),
                 State::Whitespace => {
-                    if c.is_whitespace() {
+                    if self.config.is_whitespace(c) || self.is_line_continuation(c) {
                         self.shift(offset, State::Whitespace);
+                    } else if self.config.comment == Some(c) {
+                        self.shift(offset, State::Comment);
                     } else {
                         self.reduce();
                         self.initial(offset, c);
                     };
                 }
-                State::Word => {
-                    if c.is_whitespace() {
-                        self.reduce();
+                State::Word => self.word(offset, c),
+                State::Comment => {
+                    if c == '\n' {
                         self.shift(offset, State::Whitespace);
-                    } else if c == ';' || c == '|' {
-                        self.reduce();
-                        self.special(offset);
-                    } else if c == '"' {
-                        self.reduce();
-                        self.shift(offset, State::Doublequote);
-                    } else if c == '\'' {
-                        self.reduce();
-                        self.shift(offset, State::Singlequote);
-                    } else if c == '\\' {
-                        self.shift(offset, State::WordBackslash);
                     } else {
-                        self.shift(offset, State::Word);
+                        self.shift(offset, State::Comment);
                     }
                 }
                 State::WordBackslash => {
                     // XXX: This should be if !c.is_control() perhaps?
-                    if c.is_alphanumeric() || c.is_whitespace() {
+                    if c.is_alphanumeric() || self.config.is_whitespace(c) {
                         self.shift(offset, State::Word);
                     } else {
                         return Err(TokenizerError::CharacterNotAllowedHere(offset));
                     };
                 }
                 State::Doublequote => {
-                    if c == '"' {
+                    if self.config.is_double_quote(c) {
                         self.shift(offset, State::Doublequote);
                         self.reduce();
-                    } else if c == '\\' {
+                    } else if self.config.is_escape(c) {
                         self.shift(offset, State::DoublequoteBackslash);
                     } else {
                         self.shift(offset, State::Doublequote);
                     };
                 }
                 State::DoublequoteBackslash => {
-                    if !c.is_whitespace() {
+                    if !c.is_whitespace() || self.is_line_break(c) {
                         self.shift(offset, State::Doublequote);
                     } else {
                         return Err(TokenizerError::CharacterNotAllowedHere(offset));
                     };
                 }
                 State::Singlequote => {
-                    if c == '\'' {
+                    if self.config.is_single_quote(c) {
                         self.shift(offset, State::Singlequote);
                         self.reduce();
-                    } else if c == '\\' {
+                    } else if self.config.is_escape(c) {
                         self.shift(offset, State::SinglequoteBackslash);
                     } else {
                         self.shift(offset, State::Singlequote);
                     };
                 }
                 State::SinglequoteBackslash => {
-                    if !c.is_whitespace() {
+                    if !c.is_whitespace() || self.is_line_break(c) {
                         self.shift(offset, State::Singlequote);
                     } else {
                         return Err(TokenizerError::CharacterNotAllowedHere(offset));
                     };
                 }
-                State::Special => {
-                    return Err(TokenizerError::SpecialNotYetImplemented(offset));
+                State::HereDocumentOperator => {
+                    if c == '<' {
+                        self.shift(offset, State::HereDocumentDelimiter);
+                        self.token_type = Some(TokenType::HereDocument);
+                        self.delimiter = "";
+                    } else {
+                        // Just a word that starts with a `<`.
+                        self.state = State::Word;
+                        self.word(offset, c);
+                    }
                 }
+                State::HereDocumentDelimiter => {
+                    if c.is_alphanumeric() || c == '_' || c == '-' {
+                        self.shift(offset, State::HereDocumentDelimiter);
+                        let delimiter_start = self.token_start_byte + 2;
+                        self.delimiter = &self.text[delimiter_start..self.byte_end];
+                    } else if self.delimiter.is_empty() {
+                        return Err(TokenizerError::CharacterNotAllowedHere(offset));
+                    } else if c == '\n' {
+                        self.shift(offset, State::HereDocumentBody);
+                        self.line_start_byte = self.byte_end;
+                    } else if c.is_whitespace() {
+                        self.shift(offset, State::HereDocumentLineEnd);
+                    } else {
+                        return Err(TokenizerError::CharacterNotAllowedHere(offset));
+                    }
+                }
+                State::HereDocumentLineEnd => {
+                    if c == '\n' {
+                        self.shift(offset, State::HereDocumentBody);
+                        self.line_start_byte = self.byte_end;
+                    } else if c.is_whitespace() {
+                        self.shift(offset, State::HereDocumentLineEnd);
+                    } else {
+                        return Err(TokenizerError::CharacterNotAllowedHere(offset));
+                    }
+                }
+                State::HereDocumentBody => {
+                    if c == '\n' && self.at_here_document_end() {
+                        self.reduce();
+                        self.initial(offset, c);
+                    } else {
+                        self.shift(offset, State::HereDocumentBody);
+                        if c == '\n' {
+                            self.line_start_byte = self.byte_end;
+                        }
+                    }
+                }
+            }
+            if c == '\n' {
+                self.line += 1;
+                self.line_start = offset + 1;
             }
         }
 
         // Now for the end of the text...
+        self.end_state = self.state;
         match self.state {
             State::Initial => {}
-            State::Word | State::Whitespace => self.reduce(),
+            State::Word | State::Whitespace | State::Comment | State::HereDocumentOperator => {
+                self.reduce()
+            }
+            State::HereDocumentBody => {
+                self.byte = self.text.len();
+                if self.at_here_document_end() {
+                    self.reduce();
+                } else {
+                    return Err(TokenizerError::UnterminatedHereDocument);
+                }
+            }
+            State::HereDocumentDelimiter | State::HereDocumentLineEnd => {
+                return Err(TokenizerError::UnterminatedHereDocument)
+            }
             State::WordBackslash => return Err(TokenizerError::EscapingBackslashAtEndOfInput),
             State::Doublequote => return Err(TokenizerError::UnclosedDoubleQuote),
             State::Singlequote => return Err(TokenizerError::UnclosedSingleQuote),
             State::DoublequoteBackslash | State::SinglequoteBackslash => {
                 return Err(TokenizerError::EscapingBackslashAtEndOfInput)
             }
-            State::Special => {
-                return Err(TokenizerError::SpecialNotYetImplemented(
-                    self.text.len() - 1,
-                ))
+        }
+
+        self.check_token_count()
+    }
+
+    /// Tokenize as much of the text as possible, returning the
+    /// construct left unfinished at the end of it, if any.
+    fn tokenize_partial(&mut self) -> Option<Unfinished> {
+        let unfinished = match self.tokenize() {
+            Ok(_) => {
+                let continued = self.tokens.last().is_some_and(|t| {
+                    let line_break = t
+                        .text
+                        .strip_suffix('\n')
+                        .map(|s| s.strip_suffix('\r').unwrap_or(s));
+                    t.token_type == TokenType::Whitespace
+                        && line_break
+                            .and_then(|s| s.chars().next_back())
+                            .is_some_and(|c| self.config.is_escape(c))
+                });
+                return continued.then_some(Unfinished::TrailingBackslash);
+            }
+            Err(TokenizerError::UnclosedDoubleQuote) => Unfinished::OpenDoubleQuote,
+            Err(TokenizerError::UnclosedSingleQuote) => Unfinished::OpenSingleQuote,
+            Err(TokenizerError::EscapingBackslashAtEndOfInput) => Unfinished::TrailingBackslash,
+            Err(TokenizerError::UnterminatedHereDocument) => Unfinished::OpenHereDocument,
+            Err(error) => return Some(Unfinished::Invalid(error)),
+        };
+        // The text ended within a token, which is still pending.
+        self.reduce();
+        Some(unfinished)
+    }
+}
+
+/// The markers that terminals send around pasted text when
+/// bracketed paste mode is enabled.
+const BRACKETED_PASTE_MARKERS: [&str; 2] = ["\x1b[200~", "\x1b[201~"];
+
+/// Is `c` a control character which isn't also whitespace?
+fn is_control(c: char) -> bool {
+    c.is_control() && !c.is_whitespace()
+}
+
+/// Remove the surrounding quotes from the text of a word token
+/// and interpret the escape sequences within it.
+///
+/// Outside of quotes, a backslash escapes the character after it.
+/// Within double quotes, `\n`, `\t`, `\r` and `\xNN` (a character
+/// given by 2 hexadecimal digits) are interpreted as in most shells,
+/// and a backslash before any other character escapes it. Within
+/// single quotes, backslashes are literal, other than before the
+/// quote itself or another backslash. A backslash before a line
+/// break continues the line and is removed along with it. Curly
+/// quotes are only removed with `smart_quotes`.
+///
+/// The text is only copied when something has been removed.
+pub(crate) fn unquote(text: &str, smart_quotes: bool) -> Cow<'_, str> {
+    let mut chars = text.chars();
+    match (chars.next(), chars.next_back()) {
+        (Some(open @ '"'), Some('"')) | (Some(open @ '\''), Some('\'')) => {
+            unescape(chars.as_str(), Some(open))
+        }
+        (Some(open @ '\u{201C}'), Some('\u{201C}' | '\u{201D}'))
+        | (Some(open @ '\u{2018}'), Some('\u{2018}' | '\u{2019}'))
+            if smart_quotes =>
+        {
+            unescape(chars.as_str(), Some(open))
+        }
+        _ => unescape(text, None),
+    }
+}
+
+/// Interpret the escape sequences within `text`, which was quoted
+/// with the `open` quote, if any. See [`unquote`].
+fn unescape(text: &str, open: Option<char>) -> Cow<'_, str> {
+    if !text.contains('\\') {
+        return Cow::Borrowed(text);
+    }
+    let double = matches!(open, Some('"' | '\u{201C}'));
+    let single = matches!(open, Some('\'' | '\u{2018}'));
+    let mut value = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            value.push(c);
+            continue;
+        }
+        let rest = chars.as_str();
+        match chars.next() {
+            // A line continuation is removed entirely.
+            Some('\n') => {}
+            Some('\r') if chars.as_str().starts_with('\n') => {
+                chars.next();
+            }
+            Some(next) if single && next != '\\' && Some(next) != open && next != '\u{2019}' => {
+                value.push('\\');
+                value.push(next);
+            }
+            Some('n') if double => value.push('\n'),
+            Some('t') if double => value.push('\t'),
+            Some('r') if double => value.push('\r'),
+            Some('x') if double => {
+                let byte = rest
+                    .get(1..3)
+                    .filter(|digits| digits.chars().all(|d| d.is_ascii_hexdigit()))
+                    .and_then(|digits| u8::from_str_radix(digits, 16).ok());
+                match byte {
+                    Some(byte) => {
+                        value.push(char::from(byte));
+                        chars.nth(1);
+                    }
+                    None => value.push('x'),
+                }
             }
+            next => value.extend(next),
+        }
+    }
+    Cow::Owned(value)
+}
+
+/// The quote which closes a word opened with the `open` quote.
+fn closing_quote(open: char) -> Option<char> {
+    match open {
+        '"' | '\'' => Some(open),
+        '\u{201C}' => Some('\u{201D}'),
+        '\u{2018}' => Some('\u{2019}'),
+        _ => None,
+    }
+}
+
+/// Like [`unquote`], but for the text of a word which may still
+/// be being typed, so an opening quote may not be closed yet. This
+/// also returns the opening quote, if any.
+pub(crate) fn unquote_partial(text: &str, smart_quotes: bool) -> (Cow<'_, str>, Option<char>) {
+    let open = match text.chars().next() {
+        Some(open) if open.is_ascii() && closing_quote(open).is_some() => open,
+        Some(open) if smart_quotes && closing_quote(open).is_some() => open,
+        _ => return (unescape(text, None), None),
+    };
+    let mut inner = &text[open.len_utf8()..];
+    if let Some(last) = inner.chars().next_back() {
+        let escapes = inner[..inner.len() - last.len_utf8()]
+            .chars()
+            .rev()
+            .take_while(|c| *c == '\\')
+            .count();
+        if (Some(last) == closing_quote(open) || last == open) && escapes % 2 == 0 {
+            inner = &inner[..inner.len() - last.len_utf8()];
         }
+    }
+    (unescape(inner, Some(open)), Some(open))
+}
 
-        Ok(())
+/// Write `value` as the text of a word, so that it tokenizes to a
+/// single word whose value is `value`. With an `open` quote, the
+/// word is quoted, and left open unless `close` is true. Otherwise,
+/// whitespace is escaped with backslashes. As a backslash in a word
+/// may only escape whitespace or a letter, a value with quotes or
+/// backslashes is double quoted instead.
+pub(crate) fn quote(value: &str, open: Option<char>, close: bool) -> String {
+    let quoting =
+        |c: char| c == '\\' || closing_quote(c).is_some() || c == '\u{201D}' || c == '\u{2019}';
+    let open = match open {
+        None if value.chars().any(quoting) => Some('"'),
+        open => open,
+    };
+    let special = |c: char| match open {
+        Some(open) => c == '\\' || c == open || Some(c) == closing_quote(open),
+        None => c.is_whitespace(),
+    };
+    let mut text = String::with_capacity(value.len() + 2);
+    text.extend(open);
+    for c in value.chars() {
+        if special(c) {
+            text.push('\\');
+        }
+        text.push(c);
     }
+    if close {
+        text.extend(open.and_then(closing_quote));
+    }
+    text
+}
+
+/// Prepare untrusted text for tokenization.
+///
+/// This applies the [`ControlCharacters`] policy from the
+/// `config` to the text:
+///
+/// * `Allow`: The text is returned unchanged.
+/// * `Reject`: Bracketed paste markers are removed. Any other
+///   control characters result in `TokenizerError::ControlCharacter`
+///   identifying the character and its offset in the original text.
+/// * `Strip`: Bracketed paste markers and control characters are
+///   removed.
+///
+/// The text is only copied when something has been removed.
+///
+/// ```
+/// use commands::tokenizer::{sanitize, ControlCharacters, TokenizerConfig};
+///
+/// let config = TokenizerConfig::new().control_characters(ControlCharacters::Strip);
+/// let text = sanitize(&config, "\x1b[200~show\x07 log\x1b[201~").unwrap();
+/// assert_eq!(text, "show log");
+/// ```
+pub fn sanitize<'text>(
+    config: &TokenizerConfig,
+    text: &'text str,
+) -> Result<Cow<'text, str>, TokenizerError> {
+    if config.control_characters == ControlCharacters::Allow || !text.chars().any(is_control) {
+        return Ok(Cow::Borrowed(text));
+    }
+    let mut sanitized = String::with_capacity(text.len());
+    let mut chars = text.char_indices().enumerate();
+    while let Some((offset, (byte, c))) = chars.next() {
+        if let Some(marker) = BRACKETED_PASTE_MARKERS
+            .iter()
+            .find(|m| text[byte..].starts_with(*m))
+        {
+            // Skip the remainder of the marker.
+            chars.nth(marker.len() - 2);
+        } else if !is_control(c) {
+            sanitized.push(c);
+        } else if config.control_characters == ControlCharacters::Reject {
+            return Err(TokenizerError::ControlCharacter(offset, c));
+        }
+    }
+    Ok(Cow::Owned(sanitized))
 }
 
 /// Tokenize a body of text.
-pub fn tokenize(text: &str) -> Result<Vec<Token>, TokenizerError> {
-    let mut tokenizer = Tokenizer::new(text);
-    match tokenizer.tokenize() {
-        Ok(_) => Ok(tokenizer.tokens),
+pub fn tokenize(text: &str) -> Result<Vec<Token<'_>>, TokenizerError> {
+    tokenize_with(&TokenizerConfig::default(), text)
+}
+
+/// Tokenize a body of text using the given [`TokenizerConfig`].
+pub fn tokenize_with<'text>(
+    config: &TokenizerConfig,
+    text: &'text str,
+) -> Result<Vec<Token<'text>>, TokenizerError> {
+    let mut lexer = Lexer::new(config, text);
+    match lexer.tokenize() {
+        Ok(_) => Ok(lexer.tokens),
         Err(error) => Err(error),
     }
 }
 
+/// Tokenize as much of a body of text as possible, as is needed
+/// while the text is still being typed.
+///
+/// This never fails. Along with the tokens, it returns the construct
+/// left unfinished at the end of the text, if any. When that is an
+/// open quote, a trailing backslash or a here document, the partial
+/// text of it is the last token. When the text is invalid, the tokens
+/// are those recognized before the error.
+///
+/// ```
+/// use commands::tokenizer::{tokenize_partial, Unfinished};
+///
+/// let (tokens, unfinished) = tokenize_partial(r#"echo "a b"#);
+/// assert_eq!(tokens.len(), 3);
+/// assert_eq!(tokens[2].text, r#""a b"#);
+/// assert!(matches!(unfinished, Some(Unfinished::OpenDoubleQuote)));
+/// ```
+pub fn tokenize_partial(text: &str) -> (Vec<Token<'_>>, Option<Unfinished>) {
+    tokenize_partial_with(&TokenizerConfig::default(), text)
+}
+
+/// Tokenize as much of a body of text as possible using the given
+/// [`TokenizerConfig`]. See [`tokenize_partial`].
+pub fn tokenize_partial_with<'text>(
+    config: &TokenizerConfig,
+    text: &'text str,
+) -> (Vec<Token<'text>>, Option<Unfinished>) {
+    let mut lexer = Lexer::new(config, text);
+    let unfinished = lexer.tokenize_partial();
+    (lexer.tokens, unfinished)
+}
+
+/// Tokenize a body of text after an edit, reusing the tokens of
+/// the text from before the edit. See [`retokenize_with`].
+pub fn retokenize<'text>(
+    old_text: &str,
+    old_tokens: &[Token],
+    edit: Range<usize>,
+    text: &'text str,
+) -> Result<Vec<Token<'text>>, TokenizerError> {
+    retokenize_with(
+        &TokenizerConfig::default(),
+        old_text,
+        old_tokens,
+        edit,
+        text,
+    )
+}
+
+/// Tokenize a body of text after an edit using the given
+/// [`TokenizerConfig`], reusing the tokens of the text from before
+/// the edit.
+///
+/// The `edit` is the range of bytes within `old_text` which was
+/// replaced to give `text`, and `old_tokens` must be the result of
+/// successfully tokenizing `old_text` with the same `config`. Only
+/// the tokens from the whitespace before the edit to the whitespace
+/// after it are tokenized again. The others are moved to account
+/// for the edit, so the cost of an edit doesn't grow with the
+/// length of the text, as is wanted when tokenizing a long line
+/// as each key is typed.
+///
+/// The result is the same as that of [`tokenize_with`] on `text`,
+/// including any error. When an edit changes how the rest of the
+/// text is tokenized, like by opening a quote, or when here
+/// documents or comments are enabled or whitespace tokens are
+/// left out, the whole of `text` is tokenized.
+///
+/// ```
+/// use commands::tokenizer::{retokenize, tokenize};
+///
+/// let old_text = "show interface eth0 counters";
+/// let old_tokens = tokenize(old_text).unwrap();
+/// // Replace `eth0` with `eth12`.
+/// let text = "show interface eth12 counters";
+/// let tokens = retokenize(old_text, &old_tokens, 15..19, text).unwrap();
+/// assert_eq!(tokens, tokenize(text).unwrap());
+/// ```
+pub fn retokenize_with<'text>(
+    config: &TokenizerConfig,
+    old_text: &str,
+    old_tokens: &[Token],
+    edit: Range<usize>,
+    text: &'text str,
+) -> Result<Vec<Token<'text>>, TokenizerError> {
+    let valid_edit = edit.start <= edit.end
+        && old_text.get(edit.clone()).is_some()
+        && text.len() + edit.len() >= old_text.len();
+    if config.here_documents || config.comment.is_some() || !config.whitespace_tokens || !valid_edit
+    {
+        return tokenize_with(config, text);
+    }
+    let inserted = text.len() + edit.len() - old_text.len();
+    if text.get(edit.start..edit.start + inserted).is_none() {
+        return tokenize_with(config, text);
+    }
+    if let Some(max) = config.max_input_length {
+        if text.len() > max {
+            return Err(TokenizerError::InputTooLong(max));
+        }
+    }
+    // The byte offset of an old token within the old text.
+    let old_base = old_text.as_ptr() as usize;
+    let byte_of = |token: &Token| (token.text.as_ptr() as usize).wrapping_sub(old_base);
+    if old_tokens
+        .iter()
+        .any(|t| byte_of(t).saturating_add(t.text.len()) > old_text.len())
+    {
+        return tokenize_with(config, text);
+    }
+    let is_whitespace = |t: &Token| t.token_type == TokenType::Whitespace;
+    // Start after the last whitespace which isn't touching the edit,
+    // so that the tokenizer starts fresh and nothing can join with
+    // what was inserted.
+    let first = old_tokens
+        .iter()
+        .rposition(|t| is_whitespace(t) && byte_of(t) + t.text.len() < edit.start)
+        .map_or(0, |i| i + 1);
+    let (start_byte, start) = match first.checked_sub(1).map(|i| &old_tokens[i]) {
+        Some(t) => (byte_of(t) + t.text.len(), offset_after(t)),
+        None => (0, SourceOffset::new(0, 0, 0, 0)),
+    };
+    // End with the first whitespace which isn't touching the edit.
+    let last = old_tokens[first..]
+        .iter()
+        .position(|t| is_whitespace(t) && byte_of(t) > edit.end)
+        .map(|i| first + i);
+    let end_byte = match last.map(|i| &old_tokens[i]) {
+        Some(t) => byte_of(t) + t.text.len() + inserted - edit.len(),
+        None => text.len(),
+    };
+    let mut lexer = Lexer::new(config, &text[start_byte..end_byte]);
+    if lexer.tokenize().is_err() {
+        return tokenize_with(config, text);
+    }
+    let retokenized = lexer.tokens;
+    if last.is_some() && !retokenized.last().is_some_and(is_whitespace) {
+        // The whitespace has become part of a word.
+        return tokenize_with(config, text);
+    }
+
+    let byte_delta = inserted as isize - edit.len() as isize;
+    let char_delta = text[edit.start..edit.start + inserted].chars().count() as isize
+        - old_text[edit.clone()].chars().count() as isize;
+    let moved = |token: &Token, bytes: isize, offset: &dyn Fn(SourceOffset) -> SourceOffset| {
+        let start = byte_of(token).wrapping_add_signed(bytes);
+        Token {
+            text: &text[start..start + token.text.len()],
+            location: SourceLocation::new(offset(token.location.start), offset(token.location.end)),
+            ..*token
+        }
+    };
+    let mut tokens = Vec::with_capacity(old_tokens.len() + retokenized.len());
+    tokens.extend(old_tokens[..first].iter().map(|t| moved(t, 0, &|o| o)));
+    tokens.extend(retokenized.iter().map(|t| {
+        let location = rebase(t.location, start);
+        Token { location, ..*t }
+    }));
+    if let Some(last) = last {
+        // The rest of the line after the retokenized text moves with
+        // its end, and later lines move down if lines were added.
+        let old_end = old_tokens[last].location.end;
+        let new_end = tokens.last().map_or(old_end, |t| t.location.end);
+        let lines = new_end.line as isize - old_end.line as isize;
+        let columns = new_end.column as isize - old_end.column as isize;
+        let offset = |o: SourceOffset| {
+            let column = if o.line == old_end.line {
+                o.column.wrapping_add_signed(columns)
+            } else {
+                o.column
+            };
+            let line = o.line.wrapping_add_signed(lines);
+            let byte = o.byte.wrapping_add_signed(byte_delta);
+            SourceOffset::new(byte, o.char.wrapping_add_signed(char_delta), line, column)
+        };
+        let rest = &old_tokens[last + 1..];
+        tokens.extend(rest.iter().map(|t| moved(t, byte_delta, &offset)));
+    }
+    if config.max_tokens.is_some_and(|max| tokens.len() > max) {
+        // Report the error as tokenizing the whole text would.
+        return tokenize_with(config, text);
+    }
+    Ok(tokens)
+}
+
+/// The position just after the `token`.
+fn offset_after(token: &Token) -> SourceOffset {
+    let end = token.location.end;
+    let byte = token.location.start.byte + token.text.len();
+    if token.text.ends_with('\n') {
+        SourceOffset::new(byte, end.char + 1, end.line + 1, 0)
+    } else {
+        SourceOffset::new(byte, end.char + 1, end.line, end.column + 1)
+    }
+}
+
+/// The `location` within text which was tokenized on its own, moved
+/// to within a larger body of text where that text is at `start`
+/// rather than at the start of a line.
+fn rebase(location: SourceLocation, start: SourceOffset) -> SourceLocation {
+    let offset = |o: SourceOffset| {
+        let byte = o.byte + start.byte;
+        let char = o.char + start.char;
+        if o.line == 0 {
+            SourceOffset::new(byte, char, start.line, o.column + start.column)
+        } else {
+            SourceOffset::new(byte, char, o.line + start.line, o.column)
+        }
+    };
+    SourceLocation::new(offset(location.start), offset(location.end))
+}
+
+/// Where the text given to a [`Tokenizer`] so far ends.
+#[derive(Clone, Debug, Default)]
+pub enum EndState {
+    /// The text ends between tokens, or within whitespace or a
+    /// comment, as when nothing has been given.
+    #[default]
+    BetweenTokens,
+    /// The text ends within a word, which more text may continue.
+    InWord,
+    /// The text ends within a construct which must be finished, like
+    /// an open quote, or can't be tokenized any further.
+    Unfinished(Unfinished),
+}
+
+impl EndState {
+    /// Whether or not the text is complete as it is, so that it can
+    /// be parsed without waiting for more.
+    pub fn is_complete(&self) -> bool {
+        !matches!(*self, EndState::Unfinished(_))
+    }
+}
+
+/// A token of a [`Tokenizer`], kept as the range of its text.
+#[derive(Clone, Debug)]
+struct Span {
+    bytes: Range<usize>,
+    token_type: TokenType,
+    location: SourceLocation,
+}
+
+/// Tokenizes text which arrives in chunks, such as from a network
+/// connection or as it is typed into an editor, which can ask for
+/// the tokens so far after each chunk to highlight them.
+///
+/// Only the last tokens are tokenized again when more text arrives,
+/// since the tokens before them can't change, so the cost of each
+/// chunk doesn't grow with the length of the text. The tokens are
+/// always those which [`tokenize_partial_with`] would give for the
+/// whole of the text.
+///
+/// ```
+/// use commands::tokenizer::{EndState, Tokenizer, Unfinished};
+///
+/// let mut tokenizer = Tokenizer::new();
+/// tokenizer.feed("echo \"hello ");
+/// assert!(matches!(
+///     tokenizer.end_state(),
+///     EndState::Unfinished(Unfinished::OpenDoubleQuote)
+/// ));
+///
+/// tokenizer.feed_bytes(b"w\xc3");
+/// tokenizer.feed_bytes(b"\xb6rld\" f");
+/// assert!(matches!(tokenizer.end_state(), EndState::InWord));
+/// assert!(tokenizer.end_state().is_complete());
+///
+/// let tokens = tokenizer.tokens();
+/// assert_eq!(tokens.len(), 5);
+/// assert_eq!(tokens[2].text, "\"hello w\u{f6}rld\"");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct Tokenizer {
+    config: TokenizerConfig,
+    text: String,
+    // The bytes of a character which hasn't been fed completely.
+    pending: Vec<u8>,
+    // The tokens which more text can't change.
+    settled: Vec<Span>,
+    // The tokens after those, which are tokenized again when more
+    // text arrives.
+    unsettled: Vec<Span>,
+    end_state: EndState,
+}
+
+impl Tokenizer {
+    /// Construct a `Tokenizer` with the default configuration.
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Construct a `Tokenizer` with the given [`TokenizerConfig`].
+    pub fn with_config(config: TokenizerConfig) -> Self {
+        Tokenizer {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// The text given so far.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The tokens of the text given so far. When the text ends
+    /// within a token, like within an open quote, the text of it
+    /// so far is the last token.
+    pub fn tokens(&self) -> Vec<Token<'_>> {
+        self.settled
+            .iter()
+            .chain(&self.unsettled)
+            .map(|s| {
+                let text = &self.text[s.bytes.clone()];
+                Token::lexed(&self.config, text, s.token_type, s.location)
+            })
+            .collect()
+    }
+
+    /// Where the text given so far ends.
+    pub fn end_state(&self) -> &EndState {
+        &self.end_state
+    }
+
+    /// Forget the text given so far, as when starting a new command.
+    pub fn clear(&mut self) {
+        self.text.clear();
+        self.pending.clear();
+        self.settled.clear();
+        self.unsettled.clear();
+        self.end_state = EndState::BetweenTokens;
+    }
+
+    /// Add the `chunk` to the end of the text and tokenize it.
+    pub fn feed(&mut self, chunk: &str) {
+        self.text.push_str(chunk);
+        self.retokenize();
+    }
+
+    /// Add the `bytes` to the end of the text, which is encoded as
+    /// UTF-8, and tokenize it. A character whose bytes are split
+    /// across calls is added once all of its bytes have arrived.
+    /// Invalid bytes are replaced with `U+FFFD`.
+    pub fn feed_bytes(&mut self, bytes: &[u8]) {
+        self.pending.extend_from_slice(bytes);
+        let mut chunk = String::new();
+        let mut start = 0;
+        while let Err(error) = str::from_utf8(&self.pending[start..]) {
+            let valid = start + error.valid_up_to();
+            chunk.push_str(&String::from_utf8_lossy(&self.pending[start..valid]));
+            match error.error_len() {
+                Some(invalid) => {
+                    chunk.push(char::REPLACEMENT_CHARACTER);
+                    start = valid + invalid;
+                }
+                None => {
+                    start = valid;
+                    break;
+                }
+            }
+        }
+        let end = match str::from_utf8(&self.pending[start..]) {
+            Ok(rest) => {
+                chunk.push_str(rest);
+                self.pending.len()
+            }
+            Err(_) => start,
+        };
+        self.pending.drain(..end);
+        self.feed(&chunk);
+    }
+
+    /// Tokenize the text from the start of the unsettled tokens,
+    /// where the lexer was between tokens, and settle all but the
+    /// last 2 of them. Those may still change, as when a backslash
+    /// followed by a line break joins the whitespace before it.
+    fn retokenize(&mut self) {
+        if let Some(max) = self.config.max_input_length {
+            if self.text.len() > max {
+                self.unsettled.clear();
+                let error = TokenizerError::InputTooLong(max);
+                self.end_state = EndState::Unfinished(Unfinished::Invalid(error));
+                return;
+            }
+        }
+        let start = match (self.unsettled.first(), self.settled.last()) {
+            (Some(first), _) => first.location.start,
+            (None, Some(last)) => offset_after(&Token::new(
+                &self.text[last.bytes.clone()],
+                last.token_type,
+                last.location,
+            )),
+            (None, None) => SourceOffset::new(0, 0, 0, 0),
+        };
+        let mut config = Cow::Borrowed(&self.config);
+        if let Some(max) = self.config.max_tokens {
+            config.to_mut().max_tokens = Some(max.saturating_sub(self.settled.len()));
+        }
+        let mut lexer = Lexer::new(&config, &self.text[start.byte..]);
+        let unfinished = lexer
+            .tokenize_partial()
+            .map(|u| match (u, self.config.max_tokens) {
+                (Unfinished::Invalid(TokenizerError::TooManyTokens(_)), Some(max)) => {
+                    Unfinished::Invalid(TokenizerError::TooManyTokens(max))
+                }
+                (u, _) => u,
+            });
+        self.end_state = match (unfinished, lexer.end_state) {
+            (Some(unfinished), _) => EndState::Unfinished(unfinished),
+            (None, State::Word | State::HereDocumentOperator) => EndState::InWord,
+            (None, _) => EndState::BetweenTokens,
+        };
+        self.unsettled = lexer
+            .tokens
+            .iter()
+            .map(|t| {
+                let byte = t.location.start.byte + start.byte;
+                Span {
+                    bytes: byte..byte + t.text.len(),
+                    token_type: t.token_type,
+                    location: rebase(t.location, start),
+                }
+            })
+            .collect();
+        let settle = self.unsettled.len().saturating_sub(2);
+        self.settled.extend(self.unsettled.drain(..settle));
+    }
+}
+
 #[cfg(test)]
+#[allow(clippy::single_match)]
 mod test {
     use super::*;
 
-    fn mk_token(text: &str, token_type: TokenType, start: usize, end: usize) -> Token {
+    fn mk_token(text: &str, token_type: TokenType, start: usize, end: usize) -> Token<'_> {
         Token::new(
             text,
             token_type,
             SourceLocation::new(
-                SourceOffset::new(start, 0, start),
-                SourceOffset::new(end, 0, end),
+                SourceOffset::new(start, start, 0, start),
+                SourceOffset::new(end, end, 0, end),
             ),
         )
     }
@@ -483,7 +1840,586 @@ mod test {
         };
     }
 
-    // TODO: Test TokenizeError::SpecialNotYetImplemented
+    #[test]
+    fn configured_separators() {
+        let config = TokenizerConfig::new().separator(',');
+        match tokenize_with(&config, "a,b ,c") {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 5);
+                assert_eq!(ts[0], mk_token("a", TokenType::Word, 0, 0));
+                assert_eq!(ts[1], mk_token(",", TokenType::Whitespace, 1, 1));
+                assert_eq!(ts[2], mk_token("b", TokenType::Word, 2, 2));
+                assert_eq!(ts[3], mk_token(" ,", TokenType::Whitespace, 3, 4));
+                assert_eq!(ts[4], mk_token("c", TokenType::Word, 5, 5));
+            }
+            _ => panic!(),
+        };
+
+        match tokenize_with(&config, r#"a\,b "c,d""#) {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 3);
+                assert_eq!(ts[0], mk_token(r#"a\,b"#, TokenType::Word, 0, 3));
+                assert_eq!(ts[2], mk_token(r#""c,d""#, TokenType::Word, 5, 9));
+            }
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn smart_quotes() {
+        let config = TokenizerConfig::new().smart_quotes(true);
+        match tokenize_with(&config, "a \u{201C}b c\u{201D} \u{2018}d\u{2019}") {
+            Ok(ts) => {
+                let ranges =
+                    |t: &Token| (t.text.to_string(), t.location.char_range(), t.byte_range());
+                assert_eq!(ts.len(), 5);
+                assert_eq!(ts[0], mk_token("a", TokenType::Word, 0, 0));
+                assert_eq!(
+                    ranges(&ts[2]),
+                    ("\u{201C}b c\u{201D}".to_string(), 2..7, 2..11)
+                );
+                assert_eq!(ts[2].location.end.byte, 8);
+                assert_eq!(ranges(&ts[3]), (" ".to_string(), 7..8, 11..12));
+                assert_eq!(
+                    ranges(&ts[4]),
+                    ("\u{2018}d\u{2019}".to_string(), 8..11, 12..19)
+                );
+            }
+            _ => panic!(),
+        };
+
+        // Without the option, smart quotes are just part of a word.
+        match tokenize("a \u{201C}b c\u{201D}") {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 5);
+                assert_eq!(ts[2].text, "\u{201C}b");
+                assert_eq!(ts[2].location.end, SourceOffset::new(5, 3, 0, 3));
+            }
+            _ => panic!(),
+        };
+        match tokenize("a \u{201C}b\u{201D}") {
+            Ok(ts) => assert_eq!(ts[2].value(), "\u{201C}b\u{201D}"),
+            _ => panic!(),
+        };
+        match tokenize_with(&config, "a \u{201C}b\u{201D}") {
+            Ok(ts) => assert_eq!(ts[2].value(), "b"),
+            _ => panic!(),
+        };
+        let mut tokenizer = Tokenizer::with_config(config);
+        tokenizer.feed("a \u{201C}b");
+        tokenizer.feed("\u{201D}");
+        assert_eq!(tokenizer.tokens()[2].value(), "b");
+    }
+
+    #[test]
+    fn lexing_rules() {
+        let config = TokenizerConfig::new()
+            .single_quotes(false)
+            .escape(Some('^'))
+            .special(';', false)
+            .special('x', true);
+        match tokenize_with(&config, r#"it's a^ b "c\" d;x"#) {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 7);
+                assert_eq!(ts[0], mk_token("it's", TokenType::Word, 0, 3));
+                assert_eq!(ts[2], mk_token("a^ b", TokenType::Word, 5, 8));
+                assert_eq!(ts[4], mk_token(r#""c\""#, TokenType::Word, 10, 13));
+                assert_eq!(ts[6], mk_token("d;x", TokenType::Word, 15, 17));
+            }
+            _ => panic!("Tokenize failed."),
+        }
+        let (_, unfinished) = tokenize_partial_with(&config, "a ^\n");
+        assert!(matches!(unfinished, Some(Unfinished::TrailingBackslash)));
+
+        let config = TokenizerConfig::new()
+            .double_quotes(false)
+            .escape(None)
+            .comment(Some('!'))
+            .whitespace_tokens(false);
+        match tokenize_with(&config, "cd C:\\\"My ! x\n!\n  a!b |c") {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 5);
+                assert_eq!(ts[1], mk_token("C:\\\"My", TokenType::Word, 3, 8));
+                assert_eq!(ts[2].text, "a!b");
+                assert_eq!(ts[2].location.start, SourceOffset::new(18, 18, 2, 2));
+                assert_eq!(ts[3].token_type, TokenType::Pipe);
+                assert_eq!(ts[4].text, "c");
+            }
+            _ => panic!("Tokenize failed."),
+        }
+        let old_text = "a ! b";
+        let old_tokens = tokenize_with(&config, old_text).unwrap();
+        let tokens = retokenize_with(&config, old_text, &old_tokens, 2..3, "a x b").unwrap();
+        assert_eq!(tokens, tokenize_with(&config, "a x b").unwrap());
+        assert_eq!(tokens.len(), 3);
+    }
+
+    #[test]
+    fn here_documents() {
+        let config = TokenizerConfig::new().here_documents(true);
+        match tokenize_with(&config, "a <<EOF\nb\nc\nEOF\nd") {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 5);
+                assert_eq!(ts[2].text, "<<EOF\nb\nc\nEOF");
+                assert_eq!(ts[2].token_type, TokenType::HereDocument);
+                assert_eq!(ts[2].location.start, SourceOffset::new(2, 2, 0, 2));
+                assert_eq!(ts[2].location.end, SourceOffset::new(14, 14, 3, 2));
+                assert_eq!(ts[2].here_document_body(), Some("b\nc"));
+                assert_eq!(ts[3].location.start, SourceOffset::new(15, 15, 3, 3));
+                assert_eq!(ts[4].location.start, SourceOffset::new(16, 16, 4, 0));
+            }
+            _ => panic!(),
+        };
+
+        match tokenize_with(&config, "a <<EOF  \nEOF") {
+            Ok(ts) => {
+                assert_eq!(ts.len(), 3);
+                assert_eq!(ts[2].token_type, TokenType::HereDocument);
+                assert_eq!(ts[2].here_document_body(), Some(""));
+            }
+            _ => panic!(),
+        };
+
+        // A lone `<` is still a word.
+        match tokenize_with(&config, "a <b") {
+            Ok(ts) => assert_eq!(ts[2], mk_token("<b", TokenType::Word, 2, 3)),
+            _ => panic!(),
+        };
+
+        match tokenize_with(&config, "a <<EOF\nb\nEOFX") {
+            Err(e @ TokenizerError::UnterminatedHereDocument) => assert!(e.is_incomplete()),
+            _ => panic!(),
+        };
+
+        // Without the option, `<<` is just part of a word.
+        match tokenize("a <<EOF\nb") {
+            Ok(ts) => assert_eq!(ts[2], mk_token("<<EOF", TokenType::Word, 2, 6)),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn control_characters() {
+        match tokenize("a\x07b") {
+            Ok(ts) => assert_eq!(ts[0], mk_token("a\x07b", TokenType::Word, 0, 2)),
+            _ => panic!(),
+        };
+
+        let config = TokenizerConfig::new().control_characters(ControlCharacters::Reject);
+        match tokenize_with(&config, "ab \x07") {
+            Err(TokenizerError::ControlCharacter(3, '\x07')) => {}
+            _ => panic!(),
+        };
+        match tokenize_with(&config, "ab\tc\n") {
+            Ok(ts) => assert_eq!(ts.len(), 4),
+            _ => panic!(),
+        };
+        match sanitize(&config, "\x1b[200~ab\x1b[201~") {
+            Ok(text) => assert_eq!(text, "ab"),
+            _ => panic!(),
+        };
+        match sanitize(&config, "\x1b[200~a\x1bb") {
+            Err(TokenizerError::ControlCharacter(7, '\x1b')) => {}
+            _ => panic!(),
+        };
+
+        let config = TokenizerConfig::new().control_characters(ControlCharacters::Strip);
+        match sanitize(&config, "a\x00b\u{9b}c") {
+            Ok(text) => assert_eq!(text, "abc"),
+            _ => panic!(),
+        };
+        match sanitize(&config, "abc") {
+            Ok(Cow::Borrowed(text)) => assert_eq!(text, "abc"),
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn partial_tokens() {
+        let (ts, unfinished) = tokenize_partial("show log");
+        assert_eq!(ts.len(), 3);
+        assert!(unfinished.is_none());
+
+        let (ts, unfinished) = tokenize_partial("echo 'a b");
+        assert_eq!(ts[2], mk_token("'a b", TokenType::Word, 5, 8));
+        assert!(matches!(unfinished, Some(Unfinished::OpenSingleQuote)));
+
+        let (ts, unfinished) = tokenize_partial("echo a\\");
+        assert_eq!(ts[2], mk_token("a\\", TokenType::Word, 5, 6));
+        assert!(matches!(unfinished, Some(Unfinished::TrailingBackslash)));
+
+        let config = TokenizerConfig::new().here_documents(true);
+        let (ts, unfinished) = tokenize_partial_with(&config, "cat <<EOF\nbody");
+        assert_eq!(ts.len(), 3);
+        assert_eq!(ts[2].token_type, TokenType::HereDocument);
+        assert!(matches!(unfinished, Some(Unfinished::OpenHereDocument)));
+
+        let (ts, unfinished) = tokenize_partial("a \\! b");
+        assert_eq!(ts.len(), 2);
+        assert!(matches!(
+            unfinished,
+            Some(Unfinished::Invalid(
+                TokenizerError::CharacterNotAllowedHere(3)
+            ))
+        ));
+    }
+
+    #[test]
+    fn unquote_values() {
+        assert_eq!(unquote("abc", false), "abc");
+        assert_eq!(unquote(r#""a b""#, false), "a b");
+        assert_eq!(unquote("'a b'", false), "a b");
+        assert_eq!(unquote(r"My\ Documents", false), "My Documents");
+        assert_eq!(unquote(r#""say \"hi\"""#, false), r#"say "hi""#);
+        assert_eq!(unquote("\u{201C}a b\u{201D}", true), "a b");
+        assert_eq!(unquote("\u{201C}a\u{201D}", false), "\u{201C}a\u{201D}");
+        assert_eq!(unquote("\"", false), "\"");
+
+        let config = TokenizerConfig::new().here_documents(true);
+        match tokenize_with(&config, "'a b' plain <<EOF\n\"x\"\nEOF") {
+            Ok(ts) => {
+                assert_eq!(ts[0].value(), "a b");
+                assert!(matches!(ts[2].value(), Cow::Borrowed("plain")));
+                assert_eq!(ts[4].value(), "\"x\"");
+            }
+            _ => panic!("Tokenize failed."),
+        }
+    }
+
+    #[test]
+    fn escape_sequences() {
+        assert_eq!(unquote(r#""a\tb\nc""#, false), "a\tb\nc");
+        assert_eq!(unquote(r#""\x41\x7e\\""#, false), "A~\\");
+        assert_eq!(unquote(r#""\x4g \x4""#, false), "x4g x4");
+        assert_eq!(unquote(r#""\q""#, false), "q");
+        assert_eq!(unquote(r"'a\tb\n'", false), r"a\tb\n");
+        assert_eq!(unquote(r"'it\'s \\'", false), r"it's \");
+        assert_eq!(unquote("\u{2018}\\\u{2019}\u{2019}", true), "\u{2019}");
+        assert_eq!(unquote(r"a\tb", false), "atb");
+        assert_eq!(
+            unquote_partial(r#""a\x41"#, false),
+            ("aA".into(), Some('"'))
+        );
+        assert_eq!(unquote_partial(r"'a\n", false), (r"a\n".into(), Some('\'')));
+
+        match tokenize(r#"echo "one\ttwo" 'one\ttwo'"#) {
+            Ok(ts) => {
+                assert_eq!(ts[2].value(), "one\ttwo");
+                assert_eq!(ts[4].value(), r"one\ttwo");
+            }
+            _ => panic!("Tokenize failed."),
+        }
+    }
+
+    #[test]
+    fn retokenize_edits() {
+        let config = TokenizerConfig::new().separator(',');
+        let cases = [
+            ("show interface eth0 counters", 15..19, "eth12"),
+            ("show interface eth0 counters", 4..5, ""),
+            ("show interface eth0 counters", 4..4, " "),
+            ("show interface eth0 counters", 0..0, "x"),
+            ("show interface eth0 counters", 28..28, " all"),
+            ("show interface eth0 counters", 5..5, "\"a "),
+            ("show interface eth0 counters", 15..15, "\""),
+            ("show \"a b\" c d", 7..8, ""),
+            ("show a,b  c\\ d", 10..11, ""),
+            ("show a,b  c d", 6..7, " "),
+            ("show ébène d e", 5..7, "éé"),
+            ("a b c", 0..5, ""),
+            ("", 0..0, "a b"),
+            ("a b c", 2..3, "\u{7}"),
+            ("a b\nc d\ne", 0..1, "xy"),
+            ("a b\nc d\ne", 2..3, "x\ny"),
+            ("a b\nc d\ne f", 5..7, ""),
+            ("a\n\"b\nc\" d", 0..1, "z"),
+            ("a\tb\n\n c", 2..2, "q"),
+            ("a \\\nb c d", 4..5, "xy"),
+            ("a b c d", 3..4, "\\\n"),
+            ("a \\\nb c", 3..4, ""),
+        ];
+        for (old_text, edit, replacement) in cases {
+            let old_tokens = tokenize_with(&config, old_text).unwrap();
+            let text = format!(
+                "{}{replacement}{}",
+                &old_text[..edit.start],
+                &old_text[edit.end..]
+            );
+            let expected = tokenize_with(&config, &text);
+            let tokens = retokenize_with(&config, old_text, &old_tokens, edit, &text);
+            assert_eq!(format!("{tokens:?}"), format!("{expected:?}"), "{text}");
+        }
+    }
+
+    #[test]
+    fn multiline_offsets() {
+        let position = |o: SourceOffset| (o.char, o.line, o.column);
+        match tokenize("show\n  \t\u{8868}x \"a\nb\" c\r\nd") {
+            Ok(ts) => {
+                let locations = ts
+                    .iter()
+                    .map(|t| (position(t.location.start), position(t.location.end)))
+                    .collect::<Vec<_>>();
+                assert_eq!(
+                    locations,
+                    [
+                        ((0, 0, 0), (3, 0, 3)),
+                        ((4, 0, 4), (7, 1, 2)),
+                        ((8, 1, 3), (9, 1, 4)),
+                        ((10, 1, 5), (10, 1, 5)),
+                        ((11, 1, 6), (15, 2, 1)),
+                        ((16, 2, 2), (16, 2, 2)),
+                        ((17, 2, 3), (17, 2, 3)),
+                        ((18, 2, 4), (19, 2, 5)),
+                        ((20, 3, 0), (20, 3, 0)),
+                    ]
+                );
+                let line = "  \t\u{8868}x \"a";
+                assert_eq!(ts[2].location.display_columns(line), 8..11);
+                assert_eq!(ts[4].location.display_columns(line), 12..14);
+            }
+            _ => panic!("Tokenize failed."),
+        }
+    }
+
+    #[test]
+    fn unicode_offsets() {
+        let text = "\"h\u{e9}llo w\u{f6}rld\" \u{1F600}\n\u{e9}t\u{e9}";
+        match tokenize(text) {
+            Ok(ts) => {
+                let bytes = |t: &Token| (t.location.start.byte, t.location.end.byte);
+                assert_eq!(ts.len(), 5);
+                assert_eq!(bytes(&ts[0]), (0, 14));
+                assert_eq!(ts[0].location.end.char, 12);
+                assert_eq!(bytes(&ts[2]), (16, 16));
+                assert_eq!(ts[2].location.start.char, 14);
+                assert_eq!(bytes(&ts[4]), (21, 24));
+                assert_eq!(ts[4].location.start, SourceOffset::new(21, 16, 1, 0));
+                for t in &ts {
+                    assert_eq!(&text[t.byte_range()], t.text);
+                }
+                assert_eq!(ts[0].value(), "h\u{e9}llo w\u{f6}rld");
+            }
+            _ => panic!("Tokenize failed."),
+        }
+
+        let (ts, _) = tokenize_partial("\u{e9}cho \"w\u{f6}");
+        assert_eq!(ts[2].byte_range(), 6..10);
+        match retokenize(
+            "\u{e9} b \u{f6}",
+            &tokenize("\u{e9} b \u{f6}").unwrap(),
+            3..4,
+            "\u{e9} \u{fc} \u{f6}",
+        ) {
+            Ok(ts) => {
+                assert_eq!(ts[2].byte_range(), 3..5);
+                assert_eq!(ts[4].location.start, SourceOffset::new(6, 4, 0, 4));
+            }
+            _ => panic!("Tokenize failed."),
+        }
+    }
+
+    #[test]
+    fn streaming() {
+        let config = TokenizerConfig::new().here_documents(true);
+        let texts = [
+            "show interface eth0",
+            "a \\\n  b \\\r\nc",
+            "say \"h\u{e9}llo w\u{f6}rld\" 'it''s' x|y;z",
+            "a <<EOF\nb\nEOF\n\u{201C}c d\u{201D} e",
+        ];
+        for text in texts {
+            let (expected, _) = tokenize_partial_with(&config, text);
+            // Fed a byte at a time, in 3 byte chunks and all at once.
+            for size in [1, 3, text.len()] {
+                let mut tokenizer = Tokenizer::with_config(config.clone());
+                for chunk in text.as_bytes().chunks(size) {
+                    tokenizer.feed_bytes(chunk);
+                    let (tokens, _) = tokenize_partial_with(&config, tokenizer.text());
+                    assert_eq!(tokenizer.tokens(), tokens);
+                }
+                assert_eq!(tokenizer.text(), text);
+                assert_eq!(tokenizer.tokens(), expected);
+            }
+        }
+
+        let mut tokenizer = Tokenizer::new();
+        assert!(matches!(tokenizer.end_state(), EndState::BetweenTokens));
+        tokenizer.feed("a \\");
+        assert!(matches!(
+            tokenizer.end_state(),
+            EndState::Unfinished(Unfinished::TrailingBackslash)
+        ));
+        tokenizer.feed("\n");
+        assert!(!tokenizer.end_state().is_complete());
+        tokenizer.feed("b");
+        assert!(matches!(tokenizer.end_state(), EndState::InWord));
+        tokenizer.feed(" 'c");
+        assert!(matches!(
+            tokenizer.end_state(),
+            EndState::Unfinished(Unfinished::OpenSingleQuote)
+        ));
+        tokenizer.feed("' ");
+        assert!(matches!(tokenizer.end_state(), EndState::BetweenTokens));
+        assert_eq!(tokenizer.tokens().len(), 6);
+
+        tokenizer.clear();
+        tokenizer.feed_bytes(b"a\xff\xc3");
+        assert_eq!(tokenizer.text(), "a\u{fffd}");
+        tokenizer.feed_bytes(b"\xa9");
+        assert_eq!(tokenizer.text(), "a\u{fffd}\u{e9}");
+
+        let config = TokenizerConfig::new().max_tokens(3).max_input_length(6);
+        let mut tokenizer = Tokenizer::with_config(config);
+        tokenizer.feed("a b");
+        assert!(tokenizer.end_state().is_complete());
+        tokenizer.feed(" ");
+        assert!(matches!(
+            tokenizer.end_state(),
+            EndState::Unfinished(Unfinished::Invalid(TokenizerError::TooManyTokens(3)))
+        ));
+        tokenizer.feed("cde");
+        assert!(matches!(
+            tokenizer.end_state(),
+            EndState::Unfinished(Unfinished::Invalid(TokenizerError::InputTooLong(6)))
+        ));
+    }
+
+    #[test]
+    fn line_continuations() {
+        let texts = |text| match tokenize(text) {
+            Ok(ts) => ts.iter().map(|t| t.text).collect::<Vec<_>>(),
+            _ => panic!("Tokenize failed."),
+        };
+        assert_eq!(texts("a\\\nb"), ["a", "\\\n", "b"]);
+        assert_eq!(texts("a \\\r\n  b \\\n"), ["a", " \\\r\n  ", "b", " \\\n"]);
+        assert_eq!(texts("\\\na"), ["\\\n", "a"]);
+        assert_eq!(texts("a\\ b"), ["a\\ b"]);
+        assert_eq!(
+            texts("\"a\\\nb\" 'c\\\r\nd'"),
+            ["\"a\\\nb\"", " ", "'c\\\r\nd'"]
+        );
+        assert_eq!(unquote("\"a\\\nb\"", false), "ab");
+        assert_eq!(unquote("'c\\\r\nd'", false), "cd");
+
+        match tokenize("a \\\nb") {
+            Ok(ts) => assert_eq!(ts[2].location.start, SourceOffset::new(4, 4, 1, 0)),
+            _ => panic!("Tokenize failed."),
+        }
+        match tokenize("a \\") {
+            Err(e @ TokenizerError::EscapingBackslashAtEndOfInput) => assert!(e.is_incomplete()),
+            _ => panic!(),
+        }
+        let (tokens, unfinished) = tokenize_partial("a \\\n");
+        assert_eq!(tokens.len(), 2);
+        assert!(matches!(unfinished, Some(Unfinished::TrailingBackslash)));
+        assert!(tokenize_partial("a\n").1.is_none());
+    }
+
+    #[test]
+    fn partial_quoting() {
+        assert_eq!(
+            unquote_partial(r#""My Doc"#, false),
+            ("My Doc".into(), Some('"'))
+        );
+        assert_eq!(
+            unquote_partial(r#""My Doc""#, false),
+            ("My Doc".into(), Some('"'))
+        );
+        assert_eq!(
+            unquote_partial(r#""a\""#, false),
+            (r#"a""#.into(), Some('"'))
+        );
+        assert_eq!(unquote_partial(r"My\ Doc", false), ("My Doc".into(), None));
+        assert_eq!(unquote_partial("'", false), ("".into(), Some('\'')));
+        assert_eq!(
+            unquote_partial("\u{201C}a", true),
+            ("a".into(), Some('\u{201C}'))
+        );
+        assert_eq!(
+            unquote_partial("\u{201C}a", false),
+            ("\u{201C}a".into(), None)
+        );
+        assert_eq!(quote("My Doc", Some('"'), true), r#""My Doc""#);
+        assert_eq!(quote(r#"say "hi""#, Some('"'), false), r#""say \"hi\""#);
+        assert_eq!(quote("My Doc's", None, true), r#""My Doc's""#);
+        assert_eq!(quote(r"a\b", None, false), r#""a\\b"#);
+        assert_eq!(quote("a b", Some('\u{201C}'), true), "\u{201C}a b\u{201D}");
+        for value in ["My Doc", r#"a "b" \c"#, "it's"] {
+            for open in [None, Some('"'), Some('\'')] {
+                let text = quote(value, open, true);
+                assert_eq!(unquote(&text, false), value);
+                assert_eq!(unquote_partial(&text, false).0, value);
+                match tokenize(&format!("open {}", text)) {
+                    Ok(ts) => {
+                        assert_eq!(ts.len(), 3);
+                        assert_eq!(ts[2].value(), value);
+                    }
+                    _ => panic!("Tokenize failed."),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn limits() {
+        let config = TokenizerConfig::new().max_input_length(5);
+        match tokenize_with(&config, "a b c") {
+            Ok(ts) => assert_eq!(ts.len(), 5),
+            _ => panic!(),
+        };
+        match tokenize_with(&config, "a b cd") {
+            Err(TokenizerError::InputTooLong(5)) => {}
+            _ => panic!(),
+        };
+
+        let config = TokenizerConfig::new().max_tokens(3);
+        match tokenize_with(&config, "a b") {
+            Ok(ts) => assert_eq!(ts.len(), 3),
+            _ => panic!(),
+        };
+        match tokenize_with(&config, "a b ") {
+            Err(TokenizerError::TooManyTokens(3)) => {}
+            _ => panic!(),
+        };
+        match tokenize_with(&config, "a b c d e f") {
+            Err(TokenizerError::TooManyTokens(3)) => {}
+            _ => panic!(),
+        };
+    }
+
+    #[test]
+    fn pipe() {
+        if let Ok(ts) = tokenize("show log|count") {
+            assert_eq!(ts.len(), 5);
+            assert_eq!(ts[3], mk_token("|", TokenType::Pipe, 8, 8));
+            assert_eq!(ts[4], mk_token("count", TokenType::Word, 9, 13));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
+
+    #[test]
+    fn special_tokens() {
+        if let Ok(ts) = tokenize("a;b ? c?;") {
+            assert_eq!(ts.len(), 8);
+            assert_eq!(ts[0], mk_token("a", TokenType::Word, 0, 0));
+            assert_eq!(ts[1], mk_token(";", TokenType::Separator, 1, 1));
+            assert_eq!(ts[2], mk_token("b", TokenType::Word, 2, 2));
+            assert_eq!(ts[4], mk_token("?", TokenType::Help, 4, 4));
+            assert_eq!(ts[6], mk_token("c?", TokenType::Word, 6, 7));
+            assert_eq!(ts[7], mk_token(";", TokenType::Separator, 8, 8));
+        } else {
+            panic!("Tokenize failed.");
+        }
+        if let Ok(ts) = tokenize(r#"';' "?""#) {
+            assert!(ts
+                .iter()
+                .all(|t| t.token_type != TokenType::Separator && t.token_type != TokenType::Help));
+        } else {
+            panic!("Tokenize failed.");
+        }
+    }
 
     #[test]
     #[should_panic]
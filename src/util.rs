@@ -37,9 +37,109 @@ pub fn longest_common_prefix<'s>(strings: &'s [&str]) -> &'s str {
                 .count(),
         );
     }
+    // Don't split a character whose bytes differ part way through.
+    while !str0.is_char_boundary(len) {
+        len -= 1;
+    }
     &strings[0][..len]
 }
 
+/// Edit Distance
+///
+/// Calculate the Levenshtein distance between two strings: the
+/// number of characters which must be inserted, removed or
+/// replaced to turn one into the other.
+///
+/// ```
+/// use commands::util::edit_distance;
+///
+/// assert_eq!(edit_distance("show", "shwo"), 2);
+/// assert_eq!(edit_distance("show", "sho"), 1);
+/// assert_eq!(edit_distance("show", "show"), 0);
+/// ```
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut previous = (0..=b.len()).collect::<Vec<_>>();
+    let mut current = vec![0; b.len() + 1];
+    for (i, ca) in a.chars().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let replace = previous[j] + usize::from(ca != *cb);
+            current[j + 1] = cmp::min(replace, cmp::min(previous[j + 1], current[j]) + 1);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+    previous[b.len()]
+}
+
+/// The number of columns between tab stops, as used by
+/// [`display_width`].
+pub const TAB_WIDTH: usize = 8;
+
+/// Character Width
+///
+/// The number of columns that a terminal uses to display `c`:
+/// 2 for wide characters, such as those of CJK scripts, and 0 for
+/// combining marks and control characters. This does not handle
+/// tabs, which depend upon the column that they are displayed at.
+///
+/// ```
+/// use commands::util::char_width;
+///
+/// assert_eq!(char_width('a'), 1);
+/// assert_eq!(char_width('\u{4e2d}'), 2);
+/// assert_eq!(char_width('\u{301}'), 0);
+/// ```
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0..=0x1F | 0x7F..=0x9F => 0,
+        0x300..=0x36F
+        | 0x1AB0..=0x1AFF
+        | 0x1DC0..=0x1DFF
+        | 0x200B..=0x200F
+        | 0x20D0..=0x20FF
+        | 0xFE00..=0xFE0F
+        | 0xFE20..=0xFE2F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
+
+/// Display Width
+///
+/// The number of columns that a terminal uses to display `text`
+/// when it starts at the first column. Tabs advance to the next
+/// multiple of [`TAB_WIDTH`] and other characters are measured
+/// with [`char_width`].
+///
+/// ```
+/// use commands::util::display_width;
+///
+/// assert_eq!(display_width("show"), 4);
+/// assert_eq!(display_width("\u{4e2d}\u{6587}"), 4);
+/// assert_eq!(display_width("ab\tc"), 9);
+/// ```
+pub fn display_width(text: &str) -> usize {
+    text.chars().fold(0, |width, c| match c {
+        '\t' => (width / TAB_WIDTH + 1) * TAB_WIDTH,
+        _ => width + char_width(c),
+    })
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -69,4 +169,27 @@ mod test {
     fn valid_is_shortest_lcp() {
         assert_eq!(longest_common_prefix(&["aba", "ab", "abc"]), "ab");
     }
+
+    #[test]
+    fn lcp_keeps_whole_characters() {
+        assert_eq!(longest_common_prefix(&["caé", "caè"]), "ca");
+    }
+
+    #[test]
+    fn edit_distances() {
+        assert_eq!(edit_distance("", "abc"), 3);
+        assert_eq!(edit_distance("abc", ""), 3);
+        assert_eq!(edit_distance("interface", "interfcae"), 2);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("\u{e9}t\u{e9}", "ete"), 2);
+    }
+
+    #[test]
+    fn display_widths() {
+        assert_eq!(display_width(""), 0);
+        assert_eq!(display_width("\t"), 8);
+        assert_eq!(display_width("\u{4e2d}\t"), 8);
+        assert_eq!(display_width("e\u{301}"), 1);
+        assert_eq!(display_width("\u{1f600}!"), 3);
+    }
 }